@@ -0,0 +1,329 @@
+use rand::Rng;
+
+use bevy::prelude::*;
+
+use crate::{
+    ai_strategy::{AiPlayers, BattlefieldView}, combat::{ArmorClass, DamageType}, economy::{Economy, Prices},
+    locale::Locale, match_rng::MatchRng, status::EffectKind, supply::Supply, tech::Tech, verbose_log::VerboseLog, zone::SpawnZone,
+    ControlOptions, MatchConfig, Materials, PerSide, Side, Skills, SpawnOrder, Team, Timeout, UnitType, FIGHTER, MEDIC,
+    UNITS,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    Brutal,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard, Difficulty::Brutal];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+            Difficulty::Brutal => "Brutal",
+        }
+    }
+    /// Seconds between each unit the AI deploys
+    pub fn spawn_interval(self) -> f32 {
+        match self {
+            Difficulty::Easy => 3.,
+            Difficulty::Normal => 2.,
+            Difficulty::Hard => 1.4,
+            Difficulty::Brutal => 1.,
+        }
+    }
+    /// Rating the ladder treats this AI as having
+    pub fn rating(self) -> f32 {
+        match self {
+            Difficulty::Easy => 800.,
+            Difficulty::Normal => 1000.,
+            Difficulty::Hard => 1200.,
+            Difficulty::Brutal => 1400.,
+        }
+    }
+    /// How many units a wave brings relative to its base size
+    pub fn wave_scale(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.5,
+            Difficulty::Normal => 1.,
+            Difficulty::Hard => 1.5,
+            Difficulty::Brutal => 2.,
+        }
+    }
+    pub fn harder(self) -> Self {
+        let i = Self::ALL.iter().position(|&d| d == self).unwrap();
+        Self::ALL[(i + 1).min(Self::ALL.len() - 1)]
+    }
+    pub fn easier(self) -> Self {
+        let i = Self::ALL.iter().position(|&d| d == self).unwrap();
+        Self::ALL[i.saturating_sub(1)]
+    }
+}
+
+/// The sides the AI deploys for in this match
+fn ai_sides<'a>(config: &'a MatchConfig, options: &'a ControlOptions) -> impl Iterator<Item = Side> + 'a {
+    config.sides().iter().copied().filter(|&side| config.ai_controls(side, options.player_side))
+}
+
+/// Deploys units for the AI's sides at a steady rate set by the difficulty, picked by each side's strategy
+pub fn ai_spawn_system(
+    mut orders: EventWriter<SpawnOrder>,
+    fixed_time: Res<FixedTime>,
+    config: Res<MatchConfig>,
+    options: Res<ControlOptions>,
+    zone: Res<SpawnZone>,
+    economy: Res<Economy>,
+    prices: Res<Prices>,
+    supply: Res<Supply>,
+    tech: Res<Tech>,
+    fighter_query: Query<(&Transform, &Team, &UnitType)>,
+    verbose: Res<VerboseLog>,
+    mut rng: ResMut<MatchRng>,
+    mut plans: ResMut<AiPlans>,
+    mut players: ResMut<AiPlayers>,
+) {
+    let Some(difficulty) = config.ai() else {
+        return
+    };
+    let delta = fixed_time.period.as_secs_f32();
+
+    for side in ai_sides(&config, &options) {
+        let _span = debug_span!("ai_spawn", %side).entered();
+        let plan = &mut plans.0[side];
+        plan.spawn_cooldown -= delta;
+        if plan.spawn_cooldown > 0. {
+            continue
+        }
+        let view = BattlefieldView {
+            side,
+            money: economy.money[side],
+            supply_left: supply.left(side),
+            zone: zone.0[side],
+            units: UNITS.iter()
+                .filter(|skills| config.mutators.allows(skills) && tech.unlocked(side, skills))
+                .map(|&skills| (skills, prices.price(side, &skills)))
+                .collect(),
+            fighters: fighter_query.iter()
+                .map(|(transform, &Team(side), unit)| (side, unit.skills, transform.translation.truncate()))
+                .collect(),
+        };
+        let decided = players.0[side].decide(view, &mut rng);
+        if verbose.0 {
+            let units: Vec<_> = decided.iter().map(|order| order.skills.name).collect();
+            let saving_for = players.0[side].saving_for();
+            info!(strategy = config.strategies[side].name(), money = economy.money[side], ?units, ?saving_for, "AI decided");
+        }
+        if decided.is_empty() {
+            // Check again next tick, as it's saving up or out of room
+            plan.spawn_cooldown = 0.;
+            continue
+        }
+        plan.spawn_cooldown += difficulty.spawn_interval();
+
+        // Strategies can't spend more than the side has or deploy past the supply cap
+        let mut money = economy.money[side];
+        let mut supply_left = supply.left(side);
+        for mut order in decided {
+            let price = prices.price(side, &order.skills);
+            if price > money || supply_left == 0 {
+                break
+            }
+            money -= price;
+            supply_left -= 1;
+            order.side = side;
+            order.position = zone.clamp(side, order.position);
+            order.prepaid = false;
+            orders.send(order);
+        }
+    }
+}
+
+/// Every match starts with fresh strategies, as they may remember what they were saving up for
+pub fn reset_players(config: Res<MatchConfig>, mut players: ResMut<AiPlayers>) {
+    for side in Side::ALL {
+        players.0[side] = config.strategies[side].build();
+    }
+}
+
+/// How long a wave is announced before it arrives
+const WAVE_WARNING: f32 = 5.;
+/// Range of seconds between waves
+const WAVE_INTERVAL: std::ops::Range<f32> = 40.0..70.0;
+
+const RIDER: Skills = Skills {
+    name: "Rider",
+    speed: 70,
+    hp: 15,
+    damage_type: DamageType::Piercing,
+    armor: ArmorClass::Mail,
+    ..FIGHTER
+};
+
+const TORCHBEARER: Skills = Skills {
+    name: "Torchbearer",
+    strength: 3,
+    inflicts: Some(EffectKind::Burn),
+    damage_type: DamageType::Blunt,
+    ..FIGHTER
+};
+
+/// A themed burst of units the AI deploys all at once, free of charge
+struct Wave {
+    name: &'static str,
+    /// Key of the banner warning the player in the [`Locale`]
+    announcement: &'static str,
+    /// Units making up the wave at normal difficulty
+    units: &'static [Skills],
+}
+
+const WAVES: [Wave; 4] = [
+    Wave {
+        name: "Cavalry raid",
+        announcement: "wave.cavalry_raid",
+        units: &[RIDER, RIDER, RIDER, RIDER],
+    },
+    Wave {
+        name: "Warband",
+        announcement: "wave.warband",
+        units: &[FIGHTER, FIGHTER, FIGHTER, FIGHTER, FIGHTER, FIGHTER],
+    },
+    Wave {
+        name: "Field hospital",
+        announcement: "wave.field_hospital",
+        units: &[FIGHTER, MEDIC, FIGHTER, FIGHTER, MEDIC],
+    },
+    Wave {
+        name: "Torchbearers",
+        announcement: "wave.torchbearers",
+        units: &[TORCHBEARER, TORCHBEARER, TORCHBEARER, FIGHTER],
+    },
+];
+
+/// What the AI is about to deploy for one side
+#[derive(Debug, Clone, Copy)]
+pub struct AiPlan {
+    /// Seconds until the next unit, or until it's checked whether one is affordable
+    pub spawn_cooldown: f32,
+    until_next_wave: f32,
+    /// The wave being announced and when it arrives
+    incoming: Option<(usize, f32)>,
+}
+
+impl Default for AiPlan {
+    fn default() -> Self {
+        AiPlan {
+            spawn_cooldown: 0.,
+            until_next_wave: WAVE_INTERVAL.end,
+            incoming: None,
+        }
+    }
+}
+
+impl AiPlan {
+    /// Name of the wave being announced and seconds until it arrives
+    pub fn incoming_wave(&self) -> Option<(&'static str, f32)> {
+        self.incoming.map(|(wave, time_left)| (WAVES[wave].name, time_left))
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, Resource)]
+pub struct AiPlans(pub PerSide<AiPlan>);
+
+pub fn reset_plans(mut plans: ResMut<AiPlans>) {
+    *plans = AiPlans::default();
+}
+
+/// Announces a themed wave every so often and launches it after a warning
+pub fn ai_wave_system(
+    mut commands: Commands,
+    mut orders: EventWriter<SpawnOrder>,
+    fixed_time: Res<FixedTime>,
+    config: Res<MatchConfig>,
+    options: Res<ControlOptions>,
+    zone: Res<SpawnZone>,
+    materials: Res<Materials>,
+    locale: Res<Locale>,
+    verbose: Res<VerboseLog>,
+    mut rng: ResMut<MatchRng>,
+    mut plans: ResMut<AiPlans>,
+) {
+    let Some(difficulty) = config.ai() else {
+        return
+    };
+    let delta = fixed_time.period.as_secs_f32();
+
+    for side in ai_sides(&config, &options) {
+        let plan = &mut plans.0[side];
+        match plan.incoming {
+            None => {
+                plan.until_next_wave -= delta;
+                if plan.until_next_wave <= 0. {
+                    let wave = rng.gen_range(0..WAVES.len());
+                    plan.incoming = Some((wave, WAVE_WARNING));
+                    // Spectators are told whose wave it is, as neither side is "the enemy"
+                    let announcement = if config.spectating {
+                        format!("{}: {}", locale.name(&side.to_string()), locale.name(WAVES[wave].name))
+                    } else {
+                        locale.text(WAVES[wave].announcement).to_owned()
+                    };
+                    spawn_banner(&mut commands, &materials, &announcement, materials.red, WAVE_WARNING);
+                }
+            }
+            Some((wave, time_left)) if time_left > delta => {
+                plan.incoming = Some((wave, time_left - delta));
+            }
+            Some((wave, _)) => {
+                plan.incoming = None;
+                plan.until_next_wave = rng.gen_range(WAVE_INTERVAL);
+
+                let units = WAVES[wave].units;
+                let count = ((units.len() as f32 * difficulty.wave_scale()).round() as usize).max(1);
+                let rect = zone.0[side];
+                if verbose.0 {
+                    info!(%side, wave = WAVES[wave].name, count, "wave launched");
+                }
+
+                for (i, &skills) in units.iter().cycle().take(count).enumerate() {
+                    let y = rect.min.y + rect.height() * (i as f32 + 0.5) / count as f32;
+                    orders.send(SpawnOrder {
+                        side,
+                        position: Vec2::new(rect.center().x, y),
+                        skills,
+                        prepaid: true,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Shows an announcement across the top of the battlefield for `time` seconds
+pub fn spawn_banner(commands: &mut Commands, materials: &Materials, announcement: &str, color: Color, time: f32) {
+    let text = commands.spawn(TextBundle::from_section(announcement, TextStyle {
+        font: materials.font.clone(),
+        font_size: 40.,
+        color,
+    })).id();
+
+    commands.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(60.),
+                ..default()
+            },
+            size: Size::width(Val::Percent(100.)),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        ..default()
+    })
+    .add_child(text)
+    .insert(Timeout::new(time).tied_to(vec![text]));
+}
@@ -0,0 +1,215 @@
+//! How the AI picks what to deploy. The spawn system in `ai` paces the AI by its
+//! difficulty and asks the side's [`AiStrategy`] what to deploy whenever it's time to.
+
+use bevy::{math::Rect, prelude::*};
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{combat::Targeting, match_rng::MatchRng, PerSide, Side, Skills, SpawnOrder};
+
+/// What the AI gets to know about the battle when deciding what to deploy
+#[derive(Debug, Clone)]
+pub struct BattlefieldView {
+    pub side: Side,
+    pub money: u32,
+    /// How many more units the side may field under the supply cap
+    pub supply_left: u32,
+    /// Where the side may deploy
+    pub zone: Rect,
+    /// Units the side may deploy and what each costs it right now
+    pub units: Vec<(Skills, u32)>,
+    /// Every fighter on the field, with its side and position
+    pub fighters: Vec<(Side, Skills, Vec2)>,
+}
+
+impl BattlefieldView {
+    fn price(&self, skills: &Skills) -> Option<u32> {
+        self.units.iter().find(|(s, _)| s.name == skills.name).map(|&(_, price)| price)
+    }
+    fn affordable(&self) -> impl Iterator<Item = &(Skills, u32)> {
+        self.units.iter().filter(|&&(_, price)| price <= self.money)
+    }
+    /// Somewhere in the zone, away from its top and bottom edges unless it's too short for that
+    fn random_position(&self, rng: &mut impl Rng) -> Vec2 {
+        let margin = 20f32.min(self.zone.height() / 2.);
+        Vec2::new(
+            rng.gen_range(self.zone.min.x..=self.zone.max.x),
+            rng.gen_range(self.zone.min.y + margin..=self.zone.max.y - margin),
+        )
+    }
+    fn order(&self, skills: Skills, position: Vec2) -> SpawnOrder {
+        SpawnOrder {
+            side: self.side,
+            position,
+            skills,
+            prepaid: false,
+        }
+    }
+}
+
+/// Decides what one AI-controlled side deploys
+pub trait AiStrategy: Send + Sync {
+    /// Units to deploy right now, nothing to save up instead.
+    /// Orders for the wrong side or outside the zone get corrected, and ones the side can't afford are dropped.
+    /// Any randomness comes from `rng`, to keep matches reproducible
+    fn decide(&mut self, view: BattlefieldView, rng: &mut MatchRng) -> Vec<SpawnOrder>;
+    /// The unit the strategy is saving up for, if it has one in mind
+    fn saving_for(&self) -> Option<&'static str> {
+        None
+    }
+}
+
+/// The strategies to pick from in the menu
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Strategy {
+    Random,
+    #[default]
+    Rush,
+    Economy,
+    Counter,
+}
+
+impl Strategy {
+    pub const ALL: [Strategy; 4] = [Strategy::Random, Strategy::Rush, Strategy::Economy, Strategy::Counter];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Strategy::Random => "Random",
+            Strategy::Rush => "Rush",
+            Strategy::Economy => "Economy",
+            Strategy::Counter => "Counter",
+        }
+    }
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|&s| s == self).unwrap();
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+    pub fn build(self) -> Box<dyn AiStrategy> {
+        match self {
+            Strategy::Random => Box::new(Random),
+            Strategy::Rush => Box::new(Rush),
+            Strategy::Economy => Box::new(Economy::default()),
+            Strategy::Counter => Box::new(Counter::default()),
+        }
+    }
+}
+
+/// The strategy playing each side this match
+#[derive(Resource)]
+pub struct AiPlayers(pub PerSide<Box<dyn AiStrategy>>);
+
+impl Default for AiPlayers {
+    fn default() -> Self {
+        AiPlayers(PerSide {
+            left: Strategy::default().build(),
+            right: Strategy::default().build(),
+            top: Strategy::default().build(),
+            bottom: Strategy::default().build(),
+        })
+    }
+}
+
+/// Deploys whatever it can afford, picked at random
+pub struct Random;
+
+impl AiStrategy for Random {
+    fn decide(&mut self, view: BattlefieldView, rng: &mut MatchRng) -> Vec<SpawnOrder> {
+        let affordable: Vec<_> = view.affordable().collect();
+        let Some(&&(skills, _)) = affordable.choose(rng) else {
+            return Vec::new()
+        };
+        vec![view.order(skills, view.random_position(rng))]
+    }
+}
+
+/// Floods the field with its cheapest unit as soon as it's affordable
+pub struct Rush;
+
+impl AiStrategy for Rush {
+    fn decide(&mut self, view: BattlefieldView, rng: &mut MatchRng) -> Vec<SpawnOrder> {
+        let cheapest = view.units.iter().min_by_key(|&&(_, price)| price);
+        let Some(&(skills, _)) = cheapest.filter(|&&(_, price)| price <= view.money) else {
+            return Vec::new()
+        };
+        vec![view.order(skills, view.random_position(rng))]
+    }
+}
+
+/// How many units the economy strategy saves up for before deploying
+const SQUAD_SIZE: u32 = 5;
+
+/// Holds back until it can afford a whole squad of one unit, then deploys it along the zone at once
+#[derive(Default)]
+pub struct Economy {
+    target: Option<Skills>,
+}
+
+impl AiStrategy for Economy {
+    fn decide(&mut self, view: BattlefieldView, rng: &mut MatchRng) -> Vec<SpawnOrder> {
+        let target = match self.target.filter(|target| view.price(target).is_some()) {
+            Some(target) => target,
+            None => {
+                let Some(&(skills, _)) = view.units.choose(rng) else {
+                    return Vec::new()
+                };
+                *self.target.insert(skills)
+            }
+        };
+        let price = view.price(&target).unwrap_or(u32::MAX);
+        let count = SQUAD_SIZE.min(view.supply_left);
+        if count == 0 || view.money < price.saturating_mul(count) {
+            return Vec::new()
+        }
+        self.target = None;
+
+        let zone = view.zone;
+        (0..count).map(|i| {
+            let y = zone.min.y + zone.height() * (i as f32 + 0.5) / count as f32;
+            view.order(target, Vec2::new(zone.center().x, y))
+        }).collect()
+    }
+    fn saving_for(&self) -> Option<&'static str> {
+        self.target.map(|skills| skills.name)
+    }
+}
+
+/// Saves up for whatever deals the most damage through the armor the enemy fields
+#[derive(Default)]
+pub struct Counter {
+    target: Option<Skills>,
+}
+
+impl Counter {
+    /// Average share of damage `skills` gets through the enemy's armor
+    fn score(skills: &Skills, enemies: &[Skills]) -> f32 {
+        enemies.iter().map(|enemy| skills.damage_type.multiplier(enemy.armor)).sum::<f32>() / enemies.len() as f32
+    }
+}
+
+impl AiStrategy for Counter {
+    fn decide(&mut self, view: BattlefieldView, rng: &mut MatchRng) -> Vec<SpawnOrder> {
+        let enemies: Vec<Skills> = view.fighters.iter()
+            .filter(|&&(side, ..)| side != view.side)
+            .map(|&(_, skills, _)| skills)
+            .collect();
+        // Healers and siege engines don't fight the army
+        let fighters = view.units.iter()
+            .filter(|(skills, _)| skills.heal == 0 && skills.targeting == Targeting::Units);
+        let best = if enemies.is_empty() {
+            fighters.min_by_key(|&&(_, price)| price)
+        } else {
+            fighters.max_by(|(a, _), (b, _)| Self::score(a, &enemies).total_cmp(&Self::score(b, &enemies)))
+        };
+        let Some(&(target, price)) = best else {
+            return Vec::new()
+        };
+        self.target = Some(target);
+        if price > view.money {
+            return Vec::new()
+        }
+        self.target = None;
+        vec![view.order(target, view.random_position(rng))]
+    }
+    fn saving_for(&self) -> Option<&'static str> {
+        self.target.map(|skills| skills.name)
+    }
+}
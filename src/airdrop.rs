@@ -0,0 +1,100 @@
+use bevy::prelude::*;
+
+use crate::{bindings::Bindings, economy::{Economy, Prices}, supply::Supply, tech::Tech, ClickAction, ControlOptions, MatchConfig, MouseLoc, SelectedUnit, Side, Skills, SpawnOrder};
+
+/// Extra cost on top of the unit's price for dropping it outside the spawn zone
+const AIRDROP_SURCHARGE: u32 = 5;
+/// Seconds from ordering a drop until the unit lands
+const AIRDROP_DELAY: f32 = 3.;
+const MARKER_SIZE: f32 = 30.;
+
+/// A unit on its way down, shown as a marker where it'll land
+#[derive(Debug, Clone, Component)]
+pub struct Airdrop {
+    side: Side,
+    skills: Skills,
+    time_left: f32,
+}
+
+/// Clicking anywhere on your half of the battlefield with the airdrop key held orders the selected unit dropped there
+pub fn airdrop_order_system(
+    mut commands: Commands,
+    mouse_loc: Res<MouseLoc>,
+    mouse_button: Res<Input<MouseButton>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    options: Res<ControlOptions>,
+    bindings: Res<Bindings>,
+    config: Res<MatchConfig>,
+    selected: Res<SelectedUnit>,
+    mut economy: ResMut<Economy>,
+    mut prices: ResMut<Prices>,
+    tech: Res<Tech>,
+    supply: Res<Supply>,
+) {
+    if !matches!(ClickAction::held(&keyboard_input, &bindings), ClickAction::Airdrop) {
+        return
+    }
+    for &button in mouse_button.get_just_pressed() {
+        let Some(side) = options.button_side(&bindings, button) else {
+            continue
+        };
+        if config.versus_ai && side != options.player_side {
+            continue
+        }
+        let position = mouse_loc.0;
+        // Only on the own half
+        if side.advance(position) > 0. {
+            continue
+        }
+        let skills = selected.skills();
+        if !config.mutators.allows(&skills) || !tech.unlocked(side, &skills) || supply.left(side) == 0
+            || !economy.spend(side, prices.price(side, &skills) + AIRDROP_SURCHARGE) {
+            continue
+        }
+        prices.record(side, &skills);
+
+        commands.spawn(SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgba(1., 0.3, 0., 0.6),
+                custom_size: Some(Vec2::splat(MARKER_SIZE)),
+                ..default()
+            },
+            transform: Transform::from_translation(position.extend(0.5))
+                .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_4)),
+            ..default()
+        }).insert(Airdrop {
+            side,
+            skills,
+            time_left: AIRDROP_DELAY,
+        });
+    }
+}
+
+/// Counts down the drops, shrinking their markers, and lands the units
+pub fn airdrop_landing_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut orders: EventWriter<SpawnOrder>,
+    mut query: Query<(Entity, &mut Airdrop, &mut Transform)>,
+) {
+    for (ent, mut drop, mut transform) in query.iter_mut() {
+        drop.time_left -= time.delta_seconds();
+        if drop.time_left <= 0. {
+            orders.send(SpawnOrder {
+                side: drop.side,
+                position: transform.translation.truncate(),
+                skills: drop.skills,
+                prepaid: true,
+            });
+            commands.entity(ent).despawn();
+        } else {
+            transform.scale = Vec3::splat(0.4 + 0.6 * drop.time_left / AIRDROP_DELAY);
+        }
+    }
+}
+
+pub fn cleanup_airdrops(mut commands: Commands, query: Query<Entity, With<Airdrop>>) {
+    for ent in query.iter() {
+        commands.entity(ent).despawn();
+    }
+}
@@ -0,0 +1,91 @@
+//! Frame by frame animation of fighters from their sprite sheet, which has a row each for walking,
+//! attacking and dying
+
+use bevy::prelude::*;
+
+use crate::{AttackCooldown, UnitType};
+
+pub const FRAME_SIZE: Vec2 = Vec2::new(32., 64.);
+pub const FRAMES: usize = 4;
+const FRAME_TIME: f32 = 0.12;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AnimationKind {
+    #[default]
+    Walk,
+    Attack,
+    Death,
+}
+
+#[derive(Debug, Clone, Default, Component)]
+pub struct Animation {
+    kind: AnimationKind,
+    frame: usize,
+    timer: f32,
+    last_position: Vec2,
+    last_cooldown: f32,
+}
+
+impl Animation {
+    pub fn dying() -> Self {
+        let mut animation = Animation::default();
+        animation.start(AnimationKind::Death);
+        animation
+    }
+    fn start(&mut self, kind: AnimationKind) {
+        self.kind = kind;
+        self.frame = 0;
+        self.timer = 0.;
+    }
+    /// Moves on to the next frame when it's time, returning whether the animation wrapped around
+    fn advance(&mut self, delta: f32) -> bool {
+        self.timer += delta;
+        if self.timer < FRAME_TIME {
+            return false
+        }
+        self.timer -= FRAME_TIME;
+        self.frame = (self.frame + 1) % FRAMES;
+        self.frame == 0
+    }
+    pub fn index(&self) -> usize {
+        self.kind as usize * FRAMES + self.frame
+    }
+}
+
+pub fn fighter_animation_system(
+    time: Res<Time>,
+    mut query: Query<(&AttackCooldown, &Transform, &mut Animation, &mut TextureAtlasSprite)>,
+) {
+    for (cooldown, transform, mut animation, mut sprite) in query.iter_mut() {
+        let position = transform.translation.truncate();
+        let moving = position != animation.last_position;
+        // The cooldown only goes up when an attack has just been made
+        if cooldown.0 > animation.last_cooldown {
+            animation.start(AnimationKind::Attack);
+        }
+        animation.last_position = position;
+        animation.last_cooldown = cooldown.0;
+
+        if animation.advance(time.delta_seconds()) && animation.kind == AnimationKind::Attack {
+            animation.start(AnimationKind::Walk);
+        }
+        // Units standing still hold the first walking frame
+        if animation.kind == AnimationKind::Walk && !moving {
+            animation.start(AnimationKind::Walk);
+        }
+        sprite.index = animation.index();
+    }
+}
+
+pub fn body_animation_system(
+    time: Res<Time>,
+    mut query: Query<(&mut Animation, &mut TextureAtlasSprite), Without<UnitType>>,
+) {
+    for (mut animation, mut sprite) in query.iter_mut() {
+        // Bodies stay down on the last frame
+        if animation.frame + 1 < FRAMES {
+            animation.advance(time.delta_seconds());
+        }
+        sprite.index = animation.index();
+    }
+}
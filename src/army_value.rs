@@ -0,0 +1,91 @@
+use bevy::prelude::*;
+
+use crate::{ControlOptions, Health, MatchConfig, Materials, PerSide, Side, Team, UnitType};
+
+const BAR_WIDTH: f32 = 300.;
+
+/// The part of the tug-of-war bar filled by one side
+#[derive(Debug, Clone, Copy, Component)]
+pub struct ArmyValueBar(Side);
+
+pub fn setup_army_value_bar(mut commands: Commands) {
+    commands.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(8.),
+                ..default()
+            },
+            size: Size::width(Val::Percent(100.)),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        ..default()
+    }).with_children(|parent| {
+        parent.spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(BAR_WIDTH), Val::Px(12.)),
+                ..default()
+            },
+            background_color: Color::rgba(0., 0., 0., 0.33).into(),
+            ..default()
+        }).with_children(|bar| {
+            for side in Side::ALL {
+                bar.spawn(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(50.), Val::Percent(100.)),
+                        ..default()
+                    },
+                    ..default()
+                }).insert(ArmyValueBar(side));
+            }
+        });
+    });
+}
+
+/// Total price of each side's army, discounted by how hurt the units are
+fn army_value(query: &Query<(&Team, &UnitType, &Health)>) -> PerSide<f32> {
+    let mut value = PerSide::default();
+    for (&Team(side), unit, health) in query.iter() {
+        value[side] += unit.skills.price as f32 * health.fraction();
+    }
+    value
+}
+
+pub fn army_value_bar_system(
+    options: Res<ControlOptions>,
+    config: Res<MatchConfig>,
+    materials: Res<Materials>,
+    hurt_or_healed: Query<(), Changed<Health>>,
+    mut removed: RemovedComponents<UnitType>,
+    query: Query<(&Team, &UnitType, &Health)>,
+    mut bar_query: Query<(&ArmyValueBar, &mut Style, &mut BackgroundColor)>,
+) {
+    // Deaths and sieges both remove the unit, and newly deployed ones count as hurt or healed
+    let changed = removed.iter().count() > 0 || !hurt_or_healed.is_empty() || options.is_changed();
+    if !changed {
+        return
+    }
+
+    let value = army_value(&query);
+    let sides = config.sides();
+    let total: f32 = sides.iter().map(|&side| value[side]).sum();
+    for (&ArmyValueBar(side), mut style, mut color) in bar_query.iter_mut() {
+        let fraction = if !sides.contains(&side) {
+            0.
+        } else if total > 0. {
+            value[side] / total
+        } else {
+            1. / sides.len() as f32
+        };
+        style.size.width = Val::Percent(100. * fraction);
+        // Each enemy in its own colour when there's more than one
+        *color = if config.free_for_all() {
+            materials.team[side]
+        } else if side == options.player_side {
+            materials.player_zone.with_a(1.)
+        } else {
+            materials.enemy_zone.with_a(1.)
+        }.into();
+    }
+}
@@ -0,0 +1,113 @@
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+
+use crate::{Level, Team, UnitType};
+
+/// Extra attack for units near a friendly commander
+pub const INSPIRED_ATTACK: Level = 10;
+/// Extra defence for units near a friendly commander, who hold the line better
+pub const INSPIRED_DEFENCE: Level = 3;
+
+/// Marks a unit within reach of a friendly commander's aura
+#[derive(Debug, Clone, Copy, Component)]
+pub struct Inspired;
+
+/// How close allies must be to count as escorting a unit
+const ESCORT_RANGE: f32 = 45.;
+/// Allies needed nearby for a unit to be escorted
+const ESCORTS_NEEDED: usize = 2;
+
+/// Marks a unit with enough allies around it to get its escort protection
+#[derive(Debug, Clone, Copy, Component)]
+pub struct Escorted;
+
+/// Shows how far a commander's aura reaches
+#[derive(Debug, Clone, Copy, Component)]
+pub struct AuraCircle;
+
+#[derive(Debug, Clone, Resource)]
+pub struct AuraAssets {
+    circle: Mesh2dHandle,
+    material: Handle<ColorMaterial>,
+}
+
+pub fn setup_aura_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(AuraAssets {
+        circle: meshes.add(shape::Circle::new(1.).into()).into(),
+        material: color_materials.add(ColorMaterial::from(Color::rgba(1., 0.84, 0., 0.12))),
+    });
+}
+
+/// Gives newly deployed commanders their aura circle
+pub fn aura_circle_system(
+    mut commands: Commands,
+    assets: Res<AuraAssets>,
+    query: Query<(Entity, &UnitType), Added<UnitType>>,
+) {
+    for (ent, unit) in query.iter() {
+        if unit.skills.aura == 0 {
+            continue
+        }
+        let circle = commands.spawn(MaterialMesh2dBundle {
+            mesh: assets.circle.clone(),
+            material: assets.material.clone(),
+            transform: Transform::from_translation(Vec3::new(0., 0., -0.5))
+                .with_scale(Vec3::splat(unit.skills.aura as f32)),
+            ..default()
+        }).insert(AuraCircle).id();
+        commands.entity(ent).add_child(circle);
+    }
+}
+
+/// Inspires every unit within a friendly commander's aura, and stops inspiring those that left it
+pub fn aura_system(
+    mut commands: Commands,
+    query: Query<(Entity, &UnitType, &Team, &Transform, Option<&Inspired>)>,
+) {
+    let commanders: Vec<_> = query.iter()
+        .filter(|(_, unit, ..)| unit.skills.aura > 0)
+        .map(|(e, unit, &Team(side), t, _)| (e, side, t.translation.truncate(), unit.skills.aura as f32))
+        .collect();
+
+    for (ent, _, &Team(side), transform, inspired) in query.iter() {
+        let pos = transform.translation.truncate();
+        let in_aura = commanders.iter()
+            .any(|&(commander, s, p, radius)| commander != ent && s == side && p.distance(pos) <= radius);
+
+        match (in_aura, inspired.is_some()) {
+            (true, false) => { commands.entity(ent).insert(Inspired); }
+            (false, true) => { commands.entity(ent).remove::<Inspired>(); }
+            _ => (),
+        }
+    }
+}
+
+/// Marks units that need an escort as escorted while enough allies are next to them
+pub fn escort_system(
+    mut commands: Commands,
+    query: Query<(Entity, &UnitType, &Team, &Transform, Option<&Escorted>)>,
+) {
+    for (ent, unit, &Team(side), transform, escorted) in query.iter() {
+        if unit.skills.escort_protection == 0 {
+            continue
+        }
+        let pos = transform.translation.truncate();
+        let escorts = query.iter()
+            .filter(|&(e, _, &Team(s), t, _)| {
+                e != ent && s == side && t.translation.truncate().distance(pos) <= ESCORT_RANGE
+            })
+            .count();
+
+        match (escorts >= ESCORTS_NEEDED, escorted.is_some()) {
+            (true, false) => { commands.entity(ent).insert(Escorted); }
+            (false, true) => { commands.entity(ent).remove::<Escorted>(); }
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,235 @@
+//! Tweaks to the units' numbers for trying out balance changes without rebuilding the game.
+//! They're read from `balance.txt` in the working directory, and again whenever it changes,
+//! even mid-match. Units deployed from then on get the new numbers.
+//!
+//! Each line names a unit, one of its numbers and the value to use instead, separated by tabs:
+//!
+//! ```text
+//! Fighter     attack      35
+//! Medic       heal        3
+//! Berserker   cooldown    0.8
+//! ```
+//!
+//! Prices and tech tiers can't be changed, as the menus and the AI plan with them.
+//! Lines starting with `#` are comments.
+
+use std::{
+    fs,
+    time::{Duration, SystemTime},
+};
+
+use bevy::prelude::*;
+
+use crate::{Level, Skills, UNITS};
+
+const BALANCE_FILE: &str = "balance.txt";
+/// How often the file is checked for changes
+const RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+/// One of a unit's numbers that can be tweaked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stat {
+    Attack,
+    Defence,
+    Strength,
+    Hp,
+    Speed,
+    Siege,
+    Heal,
+    Crit,
+    Dodge,
+    Reach,
+    Aura,
+    Cooldown,
+    EscortProtection,
+    Cover,
+    BlastRadius,
+}
+
+impl Stat {
+    const ALL: [Stat; 15] = [
+        Stat::Attack, Stat::Defence, Stat::Strength, Stat::Hp, Stat::Speed, Stat::Siege, Stat::Heal, Stat::Crit,
+        Stat::Dodge, Stat::Reach, Stat::Aura, Stat::Cooldown, Stat::EscortProtection, Stat::Cover, Stat::BlastRadius,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Stat::Attack => "attack",
+            Stat::Defence => "defence",
+            Stat::Strength => "strength",
+            Stat::Hp => "hp",
+            Stat::Speed => "speed",
+            Stat::Siege => "siege",
+            Stat::Heal => "heal",
+            Stat::Crit => "crit",
+            Stat::Dodge => "dodge",
+            Stat::Reach => "reach",
+            Stat::Aura => "aura",
+            Stat::Cooldown => "cooldown",
+            Stat::EscortProtection => "escort_protection",
+            Stat::Cover => "cover",
+            Stat::BlastRadius => "blast_radius",
+        }
+    }
+    fn level(self, skills: &mut Skills) -> Option<&mut Level> {
+        Some(match self {
+            Stat::Attack => &mut skills.attack,
+            Stat::Defence => &mut skills.defence,
+            Stat::Strength => &mut skills.strength,
+            Stat::Hp => &mut skills.hp,
+            Stat::Speed => &mut skills.speed,
+            Stat::Siege => &mut skills.siege,
+            Stat::Heal => &mut skills.heal,
+            Stat::Crit => &mut skills.crit,
+            Stat::Dodge => &mut skills.dodge,
+            Stat::Reach => &mut skills.reach,
+            Stat::Aura => &mut skills.aura,
+            Stat::Cooldown => return None,
+            Stat::EscortProtection => &mut skills.escort_protection,
+            Stat::Cover => &mut skills.cover,
+            Stat::BlastRadius => &mut skills.blast_radius,
+        })
+    }
+}
+
+/// The tweaks in the balance file, and when it was last seen changed
+#[derive(Debug, Resource)]
+pub struct Balance {
+    /// Unit name, what to change and the value to change it to, in the order they're listed
+    tweaks: Vec<(&'static str, Stat, f32)>,
+    modified: Option<SystemTime>,
+    timer: Timer,
+}
+
+impl Balance {
+    pub fn load() -> Self {
+        let mut balance = Balance {
+            tweaks: Vec::new(),
+            modified: None,
+            timer: Timer::new(RELOAD_INTERVAL, TimerMode::Repeating),
+        };
+        balance.reload();
+        balance
+    }
+    /// Reads the file again if it has changed since it was last read, keeping the old tweaks if it's invalid
+    fn reload(&mut self) {
+        let modified = fs::metadata(BALANCE_FILE).and_then(|metadata| metadata.modified()).ok();
+        if modified == self.modified {
+            return
+        }
+        self.modified = modified;
+        if modified.is_none() {
+            self.tweaks.clear();
+            return
+        }
+        let result = fs::read_to_string(BALANCE_FILE).map_err(|e| e.to_string()).and_then(|contents| parse(&contents));
+        match result {
+            Ok(tweaks) => {
+                info!("Loaded {} unit tweaks from {BALANCE_FILE}", tweaks.len());
+                self.tweaks = tweaks;
+            }
+            Err(e) => warn!("Could not load {BALANCE_FILE}: {e}"),
+        }
+    }
+    /// The unit's numbers with the tweaks for it applied
+    pub fn apply(&self, mut skills: Skills) -> Skills {
+        let unit = skills.name;
+        for &(_, stat, value) in self.tweaks.iter().filter(|&&(name, ..)| name == unit) {
+            match stat.level(&mut skills) {
+                Some(level) => *level = value as Level,
+                None => skills.cooldown = value,
+            }
+        }
+        skills
+    }
+}
+
+fn parse(contents: &str) -> Result<Vec<(&'static str, Stat, f32)>, String> {
+    let mut tweaks = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue
+        }
+        let error = |e: &str| format!("line {}: {e}", i + 1);
+        let fields: Vec<_> = line.split('\t').map(str::trim).filter(|f| !f.is_empty()).collect();
+        let &[unit, stat, value] = &fields[..] else {
+            return Err(error("expected a unit, a stat and a value"))
+        };
+        let Some(skills) = UNITS.iter().find(|skills| skills.name.eq_ignore_ascii_case(unit)) else {
+            return Err(error(&format!("no unit called {unit}")))
+        };
+        let Some(stat) = Stat::ALL.into_iter().find(|s| s.name() == stat) else {
+            return Err(error(&format!("no stat called {stat}")))
+        };
+        let value: f32 = value.parse().ok().filter(|v: &f32| v.is_finite()).ok_or_else(|| error("expected a number"))?;
+        match stat {
+            Stat::Cooldown if value <= 0. => return Err(error("cooldown must be above 0")),
+            Stat::Cooldown => (),
+            _ if value.fract() != 0. || !(0. ..=Level::MAX as f32).contains(&value) => {
+                return Err(error(&format!("{} must be a whole number from 0 to {}", stat.name(), Level::MAX)))
+            }
+            // Structures are told apart by not moving
+            Stat::Speed if (value == 0.) != skills.is_structure() => {
+                return Err(error("speed can't be changed to or from 0"))
+            }
+            _ => (),
+        }
+        tweaks.push((skills.name, stat, value));
+    }
+    Ok(tweaks)
+}
+
+/// Picks up changes to the balance file, for units deployed from then on
+pub fn balance_reload_system(time: Res<Time>, mut balance: ResMut<Balance>) {
+    // Keeps checking while the game is paused
+    if balance.timer.tick(time.raw_delta()).just_finished() {
+        balance.reload();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn balance(contents: &str) -> Balance {
+        Balance {
+            tweaks: parse(contents).unwrap(),
+            ..Balance::load()
+        }
+    }
+
+    #[test]
+    fn tweaks_apply_to_their_unit() {
+        let balance = balance("# Stronger fighters\nfighter\tattack\t40\nFighter\tcooldown\t0.5\n\nMedic\thp\t30\n");
+        let fighter = balance.apply(UNITS[0]);
+        assert_eq!((fighter.attack, fighter.cooldown, fighter.hp), (40, 0.5, UNITS[0].hp));
+        let medic = balance.apply(UNITS[1]);
+        assert_eq!((medic.attack, medic.hp), (UNITS[1].attack, 30));
+    }
+
+    #[test]
+    fn later_lines_win() {
+        let balance = balance("Fighter\tattack\t40\nFighter\tattack\t45");
+        assert_eq!(balance.apply(UNITS[0]).attack, 45);
+    }
+
+    #[test]
+    fn rejects_bad_lines() {
+        for line in [
+            "Fighter\tattack",
+            "Fighter attack 40",
+            "Dragon\tattack\t40",
+            "Fighter\tprice\t1",
+            "Fighter\tattack\tlots",
+            "Fighter\tattack\t-1",
+            "Fighter\tattack\t256",
+            "Fighter\tattack\t4.5",
+            "Fighter\tcooldown\t0",
+            "Fighter\tspeed\t0",
+            "Fighter\tattack\t40\textra",
+        ] {
+            assert!(parse(line).is_err(), "{line}");
+        }
+    }
+}
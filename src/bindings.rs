@@ -0,0 +1,287 @@
+use std::fmt::Debug;
+
+use bevy::prelude::*;
+
+use crate::settings::{read_config_table, write_config_table};
+
+/// Where older versions kept the bindings, read until they're saved to `config.toml`
+const LEGACY_BINDINGS_FILE: &str = "bindings.txt";
+
+/// Keys that can be bound, looked up by their names in the bindings file
+const BINDABLE_KEYS: [KeyCode; 83] = [
+    KeyCode::A, KeyCode::B, KeyCode::C, KeyCode::D, KeyCode::E, KeyCode::F, KeyCode::G, KeyCode::H, KeyCode::I,
+    KeyCode::J, KeyCode::K, KeyCode::L, KeyCode::M, KeyCode::N, KeyCode::O, KeyCode::P, KeyCode::Q, KeyCode::R,
+    KeyCode::S, KeyCode::T, KeyCode::U, KeyCode::V, KeyCode::W, KeyCode::X, KeyCode::Y, KeyCode::Z,
+    KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5,
+    KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9, KeyCode::Key0,
+    KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6,
+    KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12,
+    KeyCode::Numpad0, KeyCode::Numpad1, KeyCode::Numpad2, KeyCode::Numpad3, KeyCode::Numpad4,
+    KeyCode::Numpad5, KeyCode::Numpad6, KeyCode::Numpad7, KeyCode::Numpad8, KeyCode::Numpad9,
+    KeyCode::Escape, KeyCode::Tab, KeyCode::Space, KeyCode::Return, KeyCode::Back,
+    KeyCode::Up, KeyCode::Down, KeyCode::Left, KeyCode::Right,
+    KeyCode::Home, KeyCode::End, KeyCode::PageUp, KeyCode::PageDown, KeyCode::Insert, KeyCode::Delete,
+    KeyCode::LShift, KeyCode::RShift, KeyCode::LControl, KeyCode::RControl, KeyCode::LAlt, KeyCode::RAlt,
+    KeyCode::Comma, KeyCode::Period, KeyCode::Minus, KeyCode::Equals,
+];
+
+fn parse_key(name: &str) -> Option<KeyCode> {
+    BINDABLE_KEYS.into_iter().find(|key| format!("{key:?}") == name)
+}
+
+fn parse_button(name: &str) -> Option<MouseButton> {
+    [MouseButton::Left, MouseButton::Right, MouseButton::Middle].into_iter().find(|b| format!("{b:?}") == name)
+}
+
+/// Which keys and mouse buttons do what, read from a file players can edit
+#[derive(Debug, Clone, Copy, Resource)]
+pub struct Bindings {
+    /// Held while pressing `exit` to quit
+    pub exit_modifier: KeyCode,
+    pub exit: KeyCode,
+    pub pause: KeyCode,
+    /// Runs the battle one tick at a time
+    pub frame_step: KeyCode,
+    pub slower: KeyCode,
+    pub faster: KeyCode,
+    /// Selects the unit at the same index of [`crate::UNITS`]
+    pub units: [KeyCode; 10],
+    pub next_unit: KeyCode,
+    pub switch_side: KeyCode,
+    pub swap_buttons: KeyCode,
+    pub camera_overview: KeyCode,
+    pub camera_fight: KeyCode,
+    pub camera_leader: KeyCode,
+    pub camera_follow: KeyCode,
+    /// Switches between windowed and fullscreen
+    pub fullscreen: KeyCode,
+    /// Steps through the window resolutions
+    pub next_resolution: KeyCode,
+    /// Shows or hides the FPS and entity count overlay
+    pub stats_overlay: KeyCode,
+    /// Switches the detailed battle log on and off
+    pub verbose_log: KeyCode,
+    /// Shows or hides both AIs' money and plans while spectating
+    pub reveal_plans: KeyCode,
+    /// Switches the stance of the player's whole army
+    pub stance: KeyCode,
+    /// Buys the player an income upgrade
+    pub income_upgrade: KeyCode,
+    /// Researches the player's next tech tier
+    pub research: KeyCode,
+    /// Held while clicking to build an arrow tower
+    pub build_tower: KeyCode,
+    /// Held while clicking to build a wall
+    pub build_wall: KeyCode,
+    /// Held while clicking to drop the selected unit anywhere on the own half
+    pub airdrop: KeyCode,
+    /// Held while clicking to lay a mine
+    pub mine: KeyCode,
+    /// Held while clicking to lay a snare
+    pub snare: KeyCode,
+    /// Held while clicking one of the own units to send it back to be sold
+    pub recall: KeyCode,
+    /// Held while clicking the own half to put the rally flag there
+    pub rally: KeyCode,
+    /// Held while clicking one of the own units to switch the stance of just that unit
+    pub unit_stance: KeyCode,
+    /// Orders the player's army to fall back, or to advance again
+    pub fall_back: KeyCode,
+    /// Casts a fireball at the mouse
+    pub fireball: KeyCode,
+    /// Casts a heal zone at the mouse
+    pub heal_zone: KeyCode,
+    /// Casts a slow field at the mouse
+    pub slow_field: KeyCode,
+    /// Calls up the militia when the enemy is close to the player's edge
+    pub call_militia: KeyCode,
+    /// Takes the mercenary offer
+    pub hire_mercenary: KeyCode,
+    /// Sends the mercenary on their way
+    pub decline_mercenary: KeyCode,
+    /// Deploys for the player
+    pub player_button: MouseButton,
+    /// Deploys for the opponent in hot-seat matches
+    pub opponent_button: MouseButton,
+    /// Held to drag the camera around
+    pub camera_drag: MouseButton,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Bindings {
+            exit_modifier: KeyCode::LShift,
+            exit: KeyCode::Escape,
+            pause: KeyCode::P,
+            frame_step: KeyCode::Period,
+            slower: KeyCode::Minus,
+            faster: KeyCode::Equals,
+            units: [
+                KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5,
+                KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9, KeyCode::Key0,
+            ],
+            next_unit: KeyCode::Tab,
+            switch_side: KeyCode::M,
+            swap_buttons: KeyCode::B,
+            camera_overview: KeyCode::F7,
+            camera_fight: KeyCode::F8,
+            camera_leader: KeyCode::F9,
+            camera_follow: KeyCode::F10,
+            fullscreen: KeyCode::F11,
+            next_resolution: KeyCode::F12,
+            stats_overlay: KeyCode::F3,
+            verbose_log: KeyCode::F4,
+            reveal_plans: KeyCode::Space,
+            stance: KeyCode::V,
+            income_upgrade: KeyCode::I,
+            research: KeyCode::U,
+            build_tower: KeyCode::LControl,
+            build_wall: KeyCode::LAlt,
+            airdrop: KeyCode::LShift,
+            mine: KeyCode::T,
+            snare: KeyCode::Y,
+            recall: KeyCode::R,
+            rally: KeyCode::F,
+            unit_stance: KeyCode::X,
+            fall_back: KeyCode::G,
+            fireball: KeyCode::Q,
+            heal_zone: KeyCode::W,
+            slow_field: KeyCode::E,
+            call_militia: KeyCode::C,
+            hire_mercenary: KeyCode::J,
+            decline_mercenary: KeyCode::K,
+            player_button: MouseButton::Right,
+            opponent_button: MouseButton::Left,
+            camera_drag: MouseButton::Middle,
+        }
+    }
+}
+
+impl Bindings {
+    /// Loads the bindings from the config file, writing out the defaults for players to edit if there aren't any
+    pub fn load() -> Self {
+        let mut bindings = Bindings::default();
+        let Some(entries) = read_config_table("bindings", LEGACY_BINDINGS_FILE) else {
+            bindings.save();
+            return bindings
+        };
+        for (action, input) in &entries {
+            if !bindings.bind(action, input) {
+                warn!("Ignoring binding of {action} to {input}");
+            }
+        }
+        bindings
+    }
+    /// Binds the action named `action` to the input named `input`, if both exist
+    fn bind(&mut self, action: &str, input: &str) -> bool {
+        if let Some(button) = parse_button(input) {
+            match action {
+                "player_button" => self.player_button = button,
+                "opponent_button" => self.opponent_button = button,
+                "camera_drag" => self.camera_drag = button,
+                _ => return false,
+            }
+            return true
+        }
+        let Some(key) = parse_key(input) else {
+            return false
+        };
+        let slot = match action {
+            "exit_modifier" => &mut self.exit_modifier,
+            "exit" => &mut self.exit,
+            "pause" => &mut self.pause,
+            "frame_step" => &mut self.frame_step,
+            "slower" => &mut self.slower,
+            "faster" => &mut self.faster,
+            "next_unit" => &mut self.next_unit,
+            "switch_side" => &mut self.switch_side,
+            "swap_buttons" => &mut self.swap_buttons,
+            "camera_overview" => &mut self.camera_overview,
+            "camera_fight" => &mut self.camera_fight,
+            "camera_leader" => &mut self.camera_leader,
+            "camera_follow" => &mut self.camera_follow,
+            "fullscreen" => &mut self.fullscreen,
+            "next_resolution" => &mut self.next_resolution,
+            "stats_overlay" => &mut self.stats_overlay,
+            "verbose_log" => &mut self.verbose_log,
+            "reveal_plans" => &mut self.reveal_plans,
+            "stance" => &mut self.stance,
+            "income_upgrade" => &mut self.income_upgrade,
+            "research" => &mut self.research,
+            "build_tower" => &mut self.build_tower,
+            "build_wall" => &mut self.build_wall,
+            "airdrop" => &mut self.airdrop,
+            "mine" => &mut self.mine,
+            "snare" => &mut self.snare,
+            "recall" => &mut self.recall,
+            "rally" => &mut self.rally,
+            "unit_stance" => &mut self.unit_stance,
+            "fall_back" => &mut self.fall_back,
+            "fireball" => &mut self.fireball,
+            "heal_zone" => &mut self.heal_zone,
+            "slow_field" => &mut self.slow_field,
+            "call_militia" => &mut self.call_militia,
+            "hire_mercenary" => &mut self.hire_mercenary,
+            "decline_mercenary" => &mut self.decline_mercenary,
+            unit => {
+                let Some(slot) = unit.strip_prefix("unit_")
+                    .and_then(|i| i.parse::<usize>().ok())
+                    .and_then(|i| self.units.get_mut(i.checked_sub(1)?)) else {
+                    return false
+                };
+                slot
+            }
+        };
+        *slot = key;
+        true
+    }
+    fn save(&self) {
+        let mut entries = Vec::new();
+        let mut line = |action: &str, input: &dyn Debug| entries.push((action.to_owned(), format!("{input:?}")));
+        line("exit_modifier", &self.exit_modifier);
+        line("exit", &self.exit);
+        line("pause", &self.pause);
+        line("frame_step", &self.frame_step);
+        line("slower", &self.slower);
+        line("faster", &self.faster);
+        for (i, key) in self.units.iter().enumerate() {
+            line(&format!("unit_{}", i + 1), key);
+        }
+        line("next_unit", &self.next_unit);
+        line("switch_side", &self.switch_side);
+        line("swap_buttons", &self.swap_buttons);
+        line("camera_overview", &self.camera_overview);
+        line("camera_fight", &self.camera_fight);
+        line("camera_leader", &self.camera_leader);
+        line("camera_follow", &self.camera_follow);
+        line("fullscreen", &self.fullscreen);
+        line("next_resolution", &self.next_resolution);
+        line("stats_overlay", &self.stats_overlay);
+        line("verbose_log", &self.verbose_log);
+        line("reveal_plans", &self.reveal_plans);
+        line("stance", &self.stance);
+        line("income_upgrade", &self.income_upgrade);
+        line("research", &self.research);
+        line("build_tower", &self.build_tower);
+        line("build_wall", &self.build_wall);
+        line("airdrop", &self.airdrop);
+        line("mine", &self.mine);
+        line("snare", &self.snare);
+        line("recall", &self.recall);
+        line("rally", &self.rally);
+        line("unit_stance", &self.unit_stance);
+        line("fall_back", &self.fall_back);
+        line("fireball", &self.fireball);
+        line("heal_zone", &self.heal_zone);
+        line("slow_field", &self.slow_field);
+        line("call_militia", &self.call_militia);
+        line("hire_mercenary", &self.hire_mercenary);
+        line("decline_mercenary", &self.decline_mercenary);
+        line("player_button", &self.player_button);
+        line("opponent_button", &self.opponent_button);
+        line("camera_drag", &self.camera_drag);
+        if let Err(e) = write_config_table("bindings", entries) {
+            error!("Could not save key bindings: {e}");
+        }
+    }
+}
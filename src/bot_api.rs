@@ -0,0 +1,386 @@
+//! Lets bots written in any language play a side over TCP. Each message is a
+//! JSON object on its own line.
+//!
+//! Bots send
+//! - `{"join": "left"}` to take over a side from the AI, one bot per side, which in
+//!   free-for-all may also be `"top"` or `"bottom"`,
+//! - `{"spawn": "Fighter", "x": -400, "y": 25}` to deploy a unit in their zone,
+//!
+//! and get `{"ok": true}` or `{"error": "..."}` back for each. While a match is
+//! on, bots that have joined get the state of the battlefield every tick:
+//!
+//! ```json
+//! {"time": 12.5, "side": "left", "money": 20, "supply_left": 13,
+//!  "zone": {"min_x": -640, "min_y": -360, "max_x": -440, "max_y": 360},
+//!  "units": [{"name": "Fighter", "price": 5}],
+//!  "fighters": [{"side": "right", "name": "Medic", "hp": 8, "x": 300.5, "y": -20}]}
+//! ```
+//!
+//! `supply_left` is `null` without a supply cap. Set `SIDEWARS_BOT_ADDR` to the
+//! address to listen on, like `127.0.0.1:4567`.
+
+use std::{
+    collections::HashMap,
+    env,
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::{mpsc::{channel, Receiver, Sender}, Mutex},
+    thread,
+};
+
+use bevy::prelude::*;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    economy::{Economy, Prices}, match_stats::MatchStats, supply::Supply, tech::Tech, zone::SpawnZone, GameState,
+    Health, MatchConfig, PerSide, Side, Skills, SpawnOrder, Team, UnitType, UNITS,
+};
+
+/// Longest line a bot may send, anything longer is turned down without parsing it
+const MAX_LINE: usize = 256;
+
+pub struct BotApiPlugin;
+
+impl Plugin for BotApiPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_startup_system(listen_system)
+            .add_system(bot_command_system)
+            .add_system(bot_state_system
+                .run_if(in_state(GameState::Playing))
+                .in_schedule(CoreSchedule::FixedUpdate));
+    }
+}
+
+enum BotEvent {
+    Connected(usize, Sender<String>),
+    Line(usize, String),
+    Disconnected(usize),
+}
+
+struct Bot {
+    outbox: Sender<String>,
+    side: Option<Side>,
+}
+
+impl Bot {
+    fn reply(&self, result: Result<(), String>) {
+        let reply = match result {
+            Ok(()) => json!({"ok": true}),
+            Err(e) => json!({"error": e}),
+        };
+        // A bot that has hung up is removed once its reader notices
+        let _ = self.outbox.send(reply.to_string());
+    }
+}
+
+#[derive(Resource)]
+struct BotServer {
+    events: Mutex<Receiver<BotEvent>>,
+    bots: HashMap<usize, Bot>,
+}
+
+fn listen_system(mut commands: Commands) {
+    let Ok(addr) = env::var("SIDEWARS_BOT_ADDR") else {
+        warn!("SIDEWARS_BOT_ADDR not set, bot API disabled");
+        return
+    };
+    let listener = match TcpListener::bind(&addr) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Could not listen for bots on {addr}: {e}");
+            return
+        }
+    };
+    let (tx, rx) = channel();
+    thread::spawn(move || {
+        for (id, stream) in listener.incoming().enumerate() {
+            match stream {
+                Ok(stream) => if let Err(e) = connect(id, stream, tx.clone()) {
+                    warn!("Bot {id} failed to connect: {e}");
+                }
+                Err(e) => warn!("Bot {id} failed to connect: {e}"),
+            }
+        }
+    });
+    commands.insert_resource(BotServer {
+        events: Mutex::new(rx),
+        bots: HashMap::new(),
+    });
+}
+
+/// Starts a thread reading the bot's lines and one writing what's sent to it,
+/// so a slow bot never holds up the game
+fn connect(id: usize, stream: TcpStream, tx: Sender<BotEvent>) -> std::io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let (outbox, outgoing) = channel::<String>();
+    thread::spawn(move || {
+        for line in outgoing {
+            if writeln!(writer, "{line}").is_err() {
+                break
+            }
+        }
+    });
+    if tx.send(BotEvent::Connected(id, outbox)).is_err() {
+        return Ok(())
+    }
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else {
+                break
+            };
+            if tx.send(BotEvent::Line(id, line)).is_err() {
+                return
+            }
+        }
+        let _ = tx.send(BotEvent::Disconnected(id));
+    });
+    Ok(())
+}
+
+/// A message from a bot, with every field any command might have
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Message {
+    join: Option<String>,
+    spawn: Option<String>,
+    x: Option<f32>,
+    y: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum BotCommand {
+    Join(Side),
+    Spawn { skills: Skills, position: Vec2 },
+}
+
+fn parse_command(line: &str) -> Result<BotCommand, String> {
+    if line.len() > MAX_LINE {
+        return Err(format!("line longer than {MAX_LINE} bytes"))
+    }
+    let message: Message = serde_json::from_str(line).map_err(|e| e.to_string())?;
+    let number = |key: &str, value: Option<f32>| match value {
+        Some(n) if n.is_finite() => Ok(n),
+        Some(_) => Err(format!("\"{key}\" must be a finite number")),
+        None => Err(format!("missing \"{key}\"")),
+    };
+
+    match message {
+        Message { join: Some(side), spawn: None, x: None, y: None } => {
+            let side = match side.as_str() {
+                "left" => Side::Left,
+                "right" => Side::Right,
+                "top" => Side::Top,
+                "bottom" => Side::Bottom,
+                side => return Err(format!("no side called \"{side}\"")),
+            };
+            Ok(BotCommand::Join(side))
+        }
+        Message { join: None, spawn: Some(name), x, y } => {
+            let skills = *UNITS.iter()
+                .find(|skills| skills.name.eq_ignore_ascii_case(&name))
+                .ok_or_else(|| format!("no unit called \"{name}\""))?;
+            Ok(BotCommand::Spawn { skills, position: Vec2::new(number("x", x)?, number("y", y)?) })
+        }
+        Message { join: Some(_), .. } => Err("\"join\" goes alone".to_owned()),
+        Message { .. } => Err("expected \"join\" or \"spawn\"".to_owned()),
+    }
+}
+
+fn side_name(side: Side) -> &'static str {
+    match side {
+        Side::Left => "left",
+        Side::Right => "right",
+        Side::Top => "top",
+        Side::Bottom => "bottom",
+    }
+}
+
+/// Takes in connecting and leaving bots and carries out their commands once they pass every check
+fn bot_command_system(
+    server: Option<ResMut<BotServer>>,
+    state: Res<State<GameState>>,
+    mut config: ResMut<MatchConfig>,
+    economy: Res<Economy>,
+    prices: Res<Prices>,
+    supply: Res<Supply>,
+    tech: Res<Tech>,
+    zone: Res<SpawnZone>,
+    mut orders: EventWriter<SpawnOrder>,
+) {
+    let Some(mut server) = server else {
+        return
+    };
+    let server = &mut *server;
+    // What's been ordered this frame, as it's only paid for once the orders go through
+    let mut spent = PerSide::<(u32, u32)>::default();
+
+    for event in server.events.lock().unwrap().try_iter() {
+        match event {
+            BotEvent::Connected(id, outbox) => {
+                server.bots.insert(id, Bot { outbox, side: None });
+            }
+            BotEvent::Disconnected(id) => {
+                if let Some(side) = server.bots.remove(&id).and_then(|bot| bot.side) {
+                    config.bots[side] = false;
+                }
+            }
+            BotEvent::Line(id, line) => {
+                let Some(bot) = server.bots.get(&id) else {
+                    continue
+                };
+                let result = parse_command(&line).and_then(|command| match command {
+                    BotCommand::Join(_) if bot.side.is_some() => Err("already playing a side".to_owned()),
+                    BotCommand::Join(side) if config.bots[side] => Err(format!("{side} is taken by another bot")),
+                    BotCommand::Join(side) => {
+                        config.bots[side] = true;
+                        Ok(Some(side))
+                    }
+                    BotCommand::Spawn { skills, position } => {
+                        let Some(side) = bot.side else {
+                            return Err("join a side first".to_owned())
+                        };
+                        if state.0 != GameState::Playing {
+                            return Err("no match is on".to_owned())
+                        }
+                        if !config.mutators.allows(&skills) || !tech.unlocked(side, &skills) {
+                            return Err(format!("{} can't be deployed", skills.name))
+                        }
+                        if !zone.contains(side, position) {
+                            return Err("position is outside the spawn zone".to_owned())
+                        }
+                        let (money, units) = &mut spent[side];
+                        let price = prices.price(side, &skills);
+                        if economy.money[side] < *money + price {
+                            return Err(format!("{} costs {price}", skills.name))
+                        }
+                        if supply.left(side) <= *units {
+                            return Err("supply cap reached".to_owned())
+                        }
+                        *money += price;
+                        *units += 1;
+                        orders.send(SpawnOrder { side, position, skills, prepaid: false });
+                        Ok(None)
+                    }
+                });
+                let bot = server.bots.get_mut(&id).unwrap();
+                if let Ok(Some(side)) = result {
+                    bot.side = Some(side);
+                }
+                bot.reply(result.map(|_| ()));
+            }
+        }
+    }
+}
+
+/// Sends every bot that has joined a side what the battlefield looks like this tick
+fn bot_state_system(
+    server: Option<Res<BotServer>>,
+    stats: Res<MatchStats>,
+    config: Res<MatchConfig>,
+    economy: Res<Economy>,
+    prices: Res<Prices>,
+    supply: Res<Supply>,
+    tech: Res<Tech>,
+    zone: Res<SpawnZone>,
+    fighter_query: Query<(&Transform, &Team, &UnitType, &Health)>,
+) {
+    let Some(server) = server else {
+        return
+    };
+    // Rounded, as bots have no use for more precision than that
+    let round = |value: f32, places: i32| (value * 10f32.powi(places)).round() / 10f32.powi(places);
+    let fighters: Vec<_> = fighter_query.iter()
+        .map(|(transform, &Team(side), unit, health)| json!({
+            "side": side_name(side),
+            "name": unit.skills.name,
+            "hp": health.hp,
+            "x": round(transform.translation.x, 1),
+            "y": round(transform.translation.y, 1),
+        }))
+        .collect();
+
+    for bot in server.bots.values() {
+        let Some(side) = bot.side else {
+            continue
+        };
+        let units: Vec<_> = UNITS.iter()
+            .filter(|skills| config.mutators.allows(skills) && tech.unlocked(side, skills))
+            .map(|skills| json!({"name": skills.name, "price": prices.price(side, skills)}))
+            .collect();
+        let rect = zone.0[side];
+        let state = json!({
+            "time": round(stats.duration(), 3),
+            "side": side_name(side),
+            "money": economy.money[side],
+            "supply_left": supply.cap.map(|_| supply.left(side)),
+            "zone": {"min_x": rect.min.x, "min_y": rect.min.y, "max_x": rect.max.x, "max_y": rect.max.y},
+            "units": units,
+            "fighters": fighters,
+        });
+        let _ = bot.outbox.send(state.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands() {
+        assert!(matches!(parse_command(r#"{"join": "left"}"#), Ok(BotCommand::Join(Side::Left))));
+        assert!(matches!(parse_command(r#" { "join" : "right" } "#), Ok(BotCommand::Join(Side::Right))));
+        let Ok(BotCommand::Spawn { skills, position }) = parse_command(r#"{"spawn": "fighter", "x": -400, "y": 2.5e1}"#)
+        else {
+            panic!("spawn not parsed")
+        };
+        assert_eq!((skills.name, position), ("Fighter", Vec2::new(-400., 25.)));
+    }
+
+    #[test]
+    fn escapes() {
+        assert!(matches!(parse_command(r#"{"join": "l\u0065ft"}"#), Ok(BotCommand::Join(Side::Left))));
+        let quoted = parse_command(r#"{"spawn": "Fighter\"", "x": 0, "y": 0}"#);
+        assert!(matches!(quoted, Err(e) if e.contains("Fighter\"")));
+        assert!(parse_command(r#"{"join": "left\q"}"#).is_err());
+        assert!(parse_command(r#"{"join": "left}"#).is_err());
+    }
+
+    #[test]
+    fn malformed() {
+        for line in [
+            "",
+            "join left",
+            "{",
+            r#"{"join": "left"} trailing"#,
+            r#"{"join": "left",}"#,
+            r#"{"join": "left", "join": "right"}"#,
+            r#"{"join": 1}"#,
+            r#"{"join": "left", "x": 1}"#,
+            r#"{"join": "middle"}"#,
+            r#"{"spawn": "Fighter", "x": 0}"#,
+            r#"{"spawn": "Fighter", "x": "0", "y": 0}"#,
+            r#"{"spawn": "Fighter", "x": 1e999, "y": 0}"#,
+            r#"{"spawn": "Dragon", "x": 0, "y": 0}"#,
+            r#"{"attack": "now"}"#,
+            "{}",
+        ] {
+            assert!(parse_command(line).is_err(), "{line}");
+        }
+        assert!(parse_command(&format!(r#"{{"join": "{}"}}"#, "left".repeat(100))).is_err());
+    }
+
+    #[test]
+    fn nested() {
+        for line in [
+            r#"{"join": {"side": "left"}}"#,
+            r#"{"join": ["left"]}"#,
+            r#"{"spawn": "Fighter", "x": [0], "y": 0}"#,
+            r#"[{"join": "left"}]"#,
+            &"[".repeat(200),
+        ] {
+            assert!(parse_command(line).is_err(), "{line}");
+        }
+    }
+}
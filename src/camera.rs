@@ -0,0 +1,187 @@
+use bevy::{
+    input::mouse::{MouseScrollUnit, MouseWheel},
+    prelude::*,
+    window::PrimaryWindow,
+};
+
+use crate::{bindings::Bindings, Battlefield, CombatTarget, MainCamera, MouseLoc, Side, Team, UnitType};
+
+/// Zoom while the camera is on something rather than showing the whole battlefield
+const CLOSE_UP_SCALE: f32 = 0.6;
+/// How far apart fighters can be and still count as the same fight
+const FIGHT_RADIUS: f32 = 100.;
+/// How quickly the camera catches up with what it's looking at
+const CAMERA_SPEED: f32 = 5.;
+/// Furthest the camera can be zoomed in
+const MIN_SCALE: f32 = 0.25;
+/// How much one notch of the mouse wheel zooms
+const ZOOM_STEP: f32 = 0.1;
+/// Pixels scrolled by touchpads that count as one notch of the mouse wheel
+const PIXELS_PER_NOTCH: f32 = 50.;
+/// How close to the edge of the window the cursor has to be to scroll the camera
+const EDGE_MARGIN: f32 = 12.;
+/// Pixels per second the camera scrolls when the cursor is at the edge
+const EDGE_SPEED: f32 = 600.;
+
+/// What the camera is looking at
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Resource)]
+pub enum CameraFocus {
+    #[default]
+    Overview,
+    BiggestFight,
+    MostAdvanced(Side),
+    Follow(Entity),
+    /// Zoomed and panned by the player, until another focus is picked
+    Free,
+}
+
+/// F7 shows the whole battlefield, F8 the biggest fight, F9 the most advanced unit
+/// (alternating sides), and F10 follows the unit closest to the cursor, unless rebound.
+/// Only during matches, as the menu toggles mutators with the same keys
+pub fn camera_focus_input_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    mouse_loc: Res<MouseLoc>,
+    mut focus: ResMut<CameraFocus>,
+    query: Query<(Entity, &Transform), With<UnitType>>,
+) {
+    if keyboard_input.just_pressed(bindings.camera_overview) {
+        *focus = CameraFocus::Overview;
+    }
+    if keyboard_input.just_pressed(bindings.camera_fight) {
+        *focus = CameraFocus::BiggestFight;
+    }
+    if keyboard_input.just_pressed(bindings.camera_leader) {
+        *focus = match *focus {
+            CameraFocus::MostAdvanced(side) => CameraFocus::MostAdvanced(side.opponent()),
+            _ => CameraFocus::MostAdvanced(Side::Left),
+        };
+    }
+    if keyboard_input.just_pressed(bindings.camera_follow) {
+        let closest = query.iter()
+            .min_by(|(_, a), (_, b)| {
+                let a = a.translation.truncate().distance(mouse_loc.0);
+                let b = b.translation.truncate().distance(mouse_loc.0);
+                a.total_cmp(&b)
+            });
+        if let Some((ent, _)) = closest {
+            *focus = CameraFocus::Follow(ent);
+        }
+    }
+}
+
+/// Where the most fighters are locked in combat close together
+fn biggest_fight(query: &Query<(&CombatTarget, &Team, &Transform), Without<MainCamera>>) -> Option<Vec2> {
+    let fighting: Vec<Vec2> = query.iter()
+        .filter(|(target, ..)| target.0.is_some())
+        .map(|(.., t)| t.translation.truncate())
+        .collect();
+
+    fighting.iter()
+        .map(|&pos| {
+            let nearby: Vec<_> = fighting.iter().filter(|p| p.distance(pos) <= FIGHT_RADIUS).collect();
+            let centre = nearby.iter().copied().sum::<Vec2>() / nearby.len() as f32;
+            (nearby.len(), centre)
+        })
+        .max_by_key(|&(count, _)| count)
+        .map(|(_, centre)| centre)
+}
+
+pub fn camera_system(
+    time: Res<Time>,
+    mut focus: ResMut<CameraFocus>,
+    query: Query<(&CombatTarget, &Team, &Transform), Without<MainCamera>>,
+    follow_query: Query<&Transform, With<UnitType>>,
+    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection), (With<MainCamera>, Without<UnitType>)>,
+) {
+    let target = match *focus {
+        CameraFocus::Free => return,
+        CameraFocus::Overview => None,
+        CameraFocus::BiggestFight => biggest_fight(&query),
+        CameraFocus::MostAdvanced(side) => query.iter()
+            .filter(|&(_, &Team(s), _)| s == side)
+            .map(|(.., t)| t)
+            .map(|t| t.translation.truncate())
+            .max_by(|&a, &b| side.advance(a).total_cmp(&side.advance(b))),
+        CameraFocus::Follow(ent) => {
+            let followed = follow_query.get(ent).ok().map(|t| t.translation.truncate());
+            // Back to the overview once the followed unit is gone
+            if followed.is_none() {
+                *focus = CameraFocus::Overview;
+            }
+            followed
+        }
+    };
+    let (goal, scale) = match target {
+        Some(target) => (target, CLOSE_UP_SCALE),
+        None => (Vec2::ZERO, 1.),
+    };
+
+    let t = (CAMERA_SPEED * time.raw_delta_seconds()).min(1.);
+    for (mut transform, mut projection) in camera_query.iter_mut() {
+        let pos = transform.translation.truncate().lerp(goal, t);
+        transform.translation.x = pos.x;
+        transform.translation.y = pos.y;
+        projection.scale += (scale - projection.scale) * t;
+    }
+}
+
+/// Zooms with the mouse wheel and pans by dragging, or while zoomed in, with the cursor at the edge of the window.
+/// The camera is kept within the battlefield
+pub fn camera_control_system(
+    time: Res<Time>,
+    bindings: Res<Bindings>,
+    mouse_button_input: Res<Input<MouseButton>>,
+    mut scroll_events: EventReader<MouseWheel>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    battlefield: Res<Battlefield>,
+    mut focus: ResMut<CameraFocus>,
+    mut last_cursor: Local<Option<Vec2>>,
+    mut camera_query: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+) {
+    let Ok(window) = window_query.get_single() else {
+        return
+    };
+    let half_size = battlefield.half_size;
+    let cursor = window.cursor_position();
+
+    let notches: f32 = scroll_events.iter()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / PIXELS_PER_NOTCH,
+        })
+        .sum();
+    let drag = match (*last_cursor, cursor) {
+        (Some(last), Some(cursor)) => cursor - last,
+        _ => Vec2::ZERO,
+    };
+    *last_cursor = cursor.filter(|_| mouse_button_input.pressed(bindings.camera_drag));
+    if notches != 0. || drag != Vec2::ZERO {
+        *focus = CameraFocus::Free;
+    }
+    if *focus != CameraFocus::Free {
+        return
+    }
+
+    let edge = cursor.map_or(Vec2::ZERO, |cursor| {
+        let towards = |pos: f32, size: f32| {
+            if pos < EDGE_MARGIN {
+                -1.
+            } else if pos > size - EDGE_MARGIN {
+                1.
+            } else {
+                0.
+            }
+        };
+        Vec2::new(towards(cursor.x, window.width()), towards(cursor.y, window.height()))
+    });
+
+    for (mut transform, mut projection) in camera_query.iter_mut() {
+        projection.scale = (projection.scale * (1. - ZOOM_STEP).powf(notches)).clamp(MIN_SCALE, 1.);
+        let scale = projection.scale;
+        let pos = transform.translation.truncate() - drag * scale + edge * EDGE_SPEED * scale * time.raw_delta_seconds();
+        let max = half_size * (1. - scale);
+        transform.translation.x = pos.x.clamp(-max.x, max.x);
+        transform.translation.y = pos.y.clamp(-max.y, max.y);
+    }
+}
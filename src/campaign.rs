@@ -0,0 +1,430 @@
+//! Campaign runs: matches against the AI one after another, each with a richer opponent than the last.
+//! Income upgrades, researched tech and the units still standing at the end of a won match carry over to the next,
+//! and the run is saved between matches so it can be continued another session. The first defeat ends it.
+//!
+//! Veterans ending a match below a quarter of their HP are injured, and fight a little worse for every injury
+//! until they're healed in the camp between matches. The money left over from a match pays for that.
+
+use bevy::prelude::*;
+
+use crate::{
+    balance::Balance, economy::Economy, experience::Experience, locale::Locale,
+    settings::{read_config, remove_config, write_config}, stance::{DefaultStance, Stance}, tech::Tech,
+    zone::SpawnZone, CombatStats, ControlOptions, GameState, Health, MatchConfig, MatchOverEvent, Materials, Side,
+    Team, UnitCounter, UnitName, UnitType, UNITS,
+};
+
+const CAMPAIGN_FILE: &str = "campaign.txt";
+/// Extra starting money the AI gets for every match already won in the run
+const MONEY_PER_WIN: u32 = 10;
+/// Share of its HP below which a veteran ends a match injured
+const INJURY_THRESHOLD: f32 = 0.25;
+/// Attack and max HP lost to each injury
+const INJURY_PENALTY: (u8, u8) = (3, 2);
+/// Price of healing one injury in the camp
+const HEAL_PRICE: u32 = 10;
+
+/// A unit that survived a match, deployed again at the start of the next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Veteran {
+    /// Name of the unit in [`UNITS`]
+    pub kind: &'static str,
+    pub level: u8,
+    pub xp: u32,
+    pub injuries: u8,
+}
+
+/// Injuries a veteran brought into the match, each shown by an icon next to its health bar
+#[derive(Debug, Clone, Copy, Component)]
+pub struct Injuries(u8);
+
+/// How far a campaign run has got and what it has to show for it
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Run {
+    pub won: u32,
+    /// Income upgrades bought so far
+    pub upgrades: u32,
+    /// Tech tier researched so far
+    pub tier: u8,
+    /// Money left over from the last match, for the camp and the next match
+    pub purse: u32,
+    pub veterans: Vec<Veteran>,
+}
+
+impl Run {
+    fn parse(contents: &str) -> Result<Self, String> {
+        let mut run = Run::default();
+        for (i, line) in contents.lines().enumerate() {
+            let error = |e: &str| format!("line {}: {e}", i + 1);
+            let fields: Vec<_> = line.split('\t').collect();
+            match &fields[..] {
+                [""] => (),
+                ["won", won] => run.won = won.parse().map_err(|_| error("expected a number of wins"))?,
+                ["upgrades", upgrades] => {
+                    run.upgrades = upgrades.parse().map_err(|_| error("expected a number of upgrades"))?
+                }
+                ["tier", tier] => run.tier = tier.parse().map_err(|_| error("expected a tier"))?,
+                ["purse", purse] => run.purse = purse.parse().map_err(|_| error("expected an amount of money"))?,
+                ["veteran", kind, level, xp, injuries] => {
+                    let Some(skills) = UNITS.iter().find(|skills| skills.name == *kind) else {
+                        return Err(error(&format!("no unit called {kind}")))
+                    };
+                    let level = level.parse().ok().filter(|&level| level >= 1);
+                    let level = level.ok_or_else(|| error("expected a level"))?;
+                    let xp = xp.parse().map_err(|_| error("expected experience"))?;
+                    let injuries = injuries.parse().map_err(|_| error("expected a number of injuries"))?;
+                    run.veterans.push(Veteran { kind: skills.name, level, xp, injuries });
+                }
+                _ => return Err(error("unknown line")),
+            }
+        }
+        Ok(run)
+    }
+    fn to_file(&self) -> String {
+        let mut contents = format!(
+            "won\t{}\nupgrades\t{}\ntier\t{}\npurse\t{}\n",
+            self.won, self.upgrades, self.tier, self.purse,
+        );
+        for Veteran { kind, level, xp, injuries } in &self.veterans {
+            contents += &format!("veteran\t{kind}\t{level}\t{xp}\t{injuries}\n");
+        }
+        contents
+    }
+}
+
+/// The campaign run in progress, if there is one
+#[derive(Debug, Clone, Resource)]
+pub struct Campaign {
+    pub run: Option<Run>,
+    /// Matches won by the last run, if it was lost this session
+    pub lost_after: Option<u32>,
+}
+
+impl Default for Campaign {
+    fn default() -> Self {
+        let run = read_config(CAMPAIGN_FILE).ok().and_then(|contents| {
+            Run::parse(&contents).map_err(|e| warn!("Could not load the campaign run: {e}")).ok()
+        });
+        Campaign { run, lost_after: None }
+    }
+}
+
+impl Campaign {
+    fn save(&self) {
+        let result = match &self.run {
+            Some(run) => write_config(CAMPAIGN_FILE, run.to_file()),
+            None => remove_config(CAMPAIGN_FILE),
+        };
+        if let Err(e) = result {
+            error!("Could not save the campaign run: {e}");
+        }
+    }
+}
+
+/// Hands the player what the run has carried over and the AI its head start, as a campaign match begins
+pub fn campaign_start_system(
+    mut commands: Commands,
+    config: Res<MatchConfig>,
+    options: Res<ControlOptions>,
+    materials: Res<Materials>,
+    zone: Res<SpawnZone>,
+    stances: Res<DefaultStance>,
+    balance: Res<Balance>,
+    mut campaign: ResMut<Campaign>,
+    mut economy: ResMut<Economy>,
+    mut tech: ResMut<Tech>,
+    mut counter: ResMut<UnitCounter>,
+) {
+    if !config.campaign {
+        return
+    }
+    let side = options.player_side;
+    let run = campaign.run.get_or_insert_with(Run::default);
+    economy.upgrades[side] = run.upgrades;
+    economy.money[side] += run.purse;
+    economy.money[side.opponent()] += MONEY_PER_WIN * run.won;
+    tech.tiers[side] = tech.tiers[side].max(run.tier);
+
+    // In a column down the middle of the spawn zone
+    let rect = zone.0[side];
+    let count = run.veterans.len();
+    for (i, veteran) in run.veterans.iter().enumerate() {
+        let position = Vec2::new(rect.center().x, rect.min.y + rect.height() * (i as f32 + 0.5) / count as f32);
+        let unit = Deployment { side, position, stance: stances.0[side] };
+        deploy_veteran(&mut commands, &materials, &config, &balance, &mut counter, unit, veteran);
+    }
+}
+
+/// Where and how a veteran is deployed
+#[derive(Debug, Clone, Copy)]
+pub struct Deployment {
+    pub side: Side,
+    pub position: Vec2,
+    pub stance: Stance,
+}
+
+/// Deploys a unit that fought before, at the level it reached and with the injuries it took
+pub fn deploy_veteran(
+    commands: &mut Commands,
+    materials: &Materials,
+    config: &MatchConfig,
+    balance: &Balance,
+    counter: &mut UnitCounter,
+    Deployment { side, position, stance }: Deployment,
+    veteran: &Veteran,
+) -> Option<Entity> {
+    let &skills = UNITS.iter().find(|skills| skills.name == veteran.kind)?;
+    let skills = config.mutators.apply(balance.apply(skills));
+    let ent = crate::spawn_fighter(commands, position, config.mutators.unit_size(), side, materials, skills);
+
+    let mut experience = Experience { xp: veteran.xp, level: 1 };
+    let mut stats = CombatStats::new(&skills);
+    let mut health = Health::new(skills.hp);
+    while experience.level < veteran.level {
+        experience.level_up(&mut stats, &mut health);
+    }
+    let (attack, hp) = INJURY_PENALTY;
+    stats.attack = stats.attack.saturating_sub(attack.saturating_mul(veteran.injuries));
+    health.max = health.max.saturating_sub(hp.saturating_mul(veteran.injuries)).max(1);
+    health.hp = health.max;
+    let name = UnitName {
+        side,
+        kind: skills.name,
+        number: counter.next(side),
+    };
+    commands.entity(ent)
+        .insert((experience, stats, health, name, stance, Injuries(veteran.injuries)))
+        .with_children(|parent| {
+            for i in 0..veteran.injuries {
+                parent.spawn(SpriteBundle {
+                    transform: Transform::from_translation(Vec3::new(-22. - 8. * i as f32, 30., 1.)),
+                    sprite: Sprite {
+                        color: Color::rgb(0.6, 0.05, 0.05),
+                        custom_size: Some(Vec2::new(6., 8.)),
+                        .. default()
+                    },
+                    .. default()
+                });
+            }
+        });
+    Some(ent)
+}
+
+/// Saves what the run keeps from a won match, or ends the run on a lost one
+pub fn campaign_end_system(
+    mut events: EventReader<MatchOverEvent>,
+    config: Res<MatchConfig>,
+    options: Res<ControlOptions>,
+    economy: Res<Economy>,
+    tech: Res<Tech>,
+    mut campaign: ResMut<Campaign>,
+    unit_query: Query<(&Team, &UnitType, &UnitName, &Experience, &Health, Option<&Injuries>)>,
+) {
+    let Some(event) = events.iter().last() else {
+        return
+    };
+    if !config.campaign {
+        return
+    }
+    let side = options.player_side;
+    let campaign = &mut *campaign;
+    let Some(run) = campaign.run.as_mut() else {
+        return
+    };
+    if event.winner == side {
+        run.won += 1;
+        run.upgrades = economy.upgrades[side];
+        run.tier = tech.tiers[side];
+        run.purse = economy.money[side];
+        // Oldest first, so they line up the same way every match. Structures stay where they were built
+        let mut veterans: Vec<_> = unit_query.iter()
+            .filter(|(&Team(team), unit, ..)| team == side && !unit.skills.is_structure())
+            .filter_map(|(_, _, name, experience, health, injuries)| {
+                let skills = UNITS.iter().find(|skills| skills.name == name.kind)?;
+                let injured = health.fraction() < INJURY_THRESHOLD;
+                let injuries = injuries.map_or(0, |injuries| injuries.0) + injured as u8;
+                Some((name.number, Veteran { kind: skills.name, level: experience.level, xp: experience.xp, injuries }))
+            })
+            .collect();
+        veterans.sort_by_key(|&(number, ..)| number);
+        run.veterans = veterans.into_iter().map(|(_, veteran)| veteran).collect();
+        info!(won = run.won, veterans = run.veterans.len(), "campaign match won");
+    } else {
+        info!(won = run.won, "campaign run lost");
+        campaign.lost_after = Some(run.won);
+        campaign.run = None;
+    }
+    campaign.save();
+}
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct CampRoot;
+
+/// Lists the veterans, with the one picked for healing marked
+#[derive(Debug, Clone, Copy, Component)]
+pub struct CampText {
+    selected: usize,
+}
+
+fn camp_text(run: &Run, selected: usize, locale: &Locale) -> String {
+    let mut lines = vec![locale.format("camp.purse", &[&run.purse])];
+    if run.veterans.is_empty() {
+        lines.push(locale.text("camp.no_veterans").to_owned());
+    }
+    for (i, veteran) in run.veterans.iter().enumerate() {
+        let marker = if i == selected { '>' } else { ' ' };
+        let line = match veteran.injuries {
+            0 => locale.format("camp.veteran", &[&locale.name(veteran.kind), &veteran.level]),
+            injuries => locale.format("camp.injured", &[&locale.name(veteran.kind), &veteran.level, &injuries]),
+        };
+        lines.push(format!("{marker} {line}"));
+    }
+    lines.join("\n")
+}
+
+/// The camp between campaign matches, where injured veterans are healed
+pub fn setup_camp(mut commands: Commands, materials: Res<Materials>, campaign: Res<Campaign>, locale: Res<Locale>) {
+    let Some(run) = &campaign.run else {
+        return
+    };
+    let style = |font_size| TextStyle {
+        font: materials.font.clone(),
+        font_size,
+        color: Color::WHITE,
+    };
+
+    commands.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            gap: Size::all(Val::Px(10.)),
+            ..default()
+        },
+        background_color: Color::rgba(0., 0., 0., 0.75).into(),
+        z_index: ZIndex::Global(5),
+        ..default()
+    })
+    .insert(CampRoot)
+    .with_children(|parent| {
+        parent.spawn(TextBundle::from_section(locale.format("camp.title", &[&(run.won + 1)]), style(60.)));
+        parent.spawn(TextBundle::from_section(camp_text(run, 0, &locale), style(24.))).insert(CampText { selected: 0 });
+        parent.spawn(TextBundle::from_section(locale.format("camp.controls", &[&HEAL_PRICE]), style(20.)));
+    });
+}
+
+pub fn camp_input_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    locale: Res<Locale>,
+    mut campaign: ResMut<Campaign>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut query: Query<(&mut Text, &mut CampText)>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        next_state.set(GameState::Playing);
+    }
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_state.set(GameState::Menu);
+    }
+    let Some(run) = campaign.run.as_mut() else {
+        return
+    };
+    let mut healed = false;
+    for (mut text, mut camp) in query.iter_mut() {
+        let count = run.veterans.len().max(1);
+        if keyboard_input.just_pressed(KeyCode::Down) {
+            camp.selected = (camp.selected + 1) % count;
+        }
+        if keyboard_input.just_pressed(KeyCode::Up) {
+            camp.selected = (camp.selected + count - 1) % count;
+        }
+        if keyboard_input.just_pressed(KeyCode::H) {
+            let affordable = run.purse >= HEAL_PRICE;
+            if let Some(veteran) = run.veterans.get_mut(camp.selected).filter(|v| v.injuries > 0 && affordable) {
+                veteran.injuries -= 1;
+                run.purse -= HEAL_PRICE;
+                healed = true;
+            }
+        }
+        if camp.is_changed() || healed {
+            text.sections[0].value = camp_text(run, camp.selected, &locale);
+        }
+    }
+    if healed {
+        campaign.save();
+    }
+}
+
+pub fn cleanup_camp(mut commands: Commands, query: Query<Entity, With<CampRoot>>) {
+    for ent in query.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let run = Run {
+            won: 3,
+            upgrades: 2,
+            tier: 1,
+            purse: 17,
+            veterans: vec![
+                Veteran { kind: "Fighter", level: 3, xp: 12, injuries: 0 },
+                Veteran { kind: "Medic", level: 1, xp: 0, injuries: 2 },
+            ],
+        };
+        assert_eq!(Run::parse(&run.to_file()), Ok(run));
+        assert_eq!(Run::parse(""), Ok(Run::default()));
+    }
+
+    #[test]
+    fn rejects_bad_lines() {
+        for line in [
+            "won\tmany",
+            "tier\t300",
+            "purse\t-5",
+            "veteran\tDragon\t1\t0\t0",
+            "veteran\tFighter\t0\t0\t0",
+            "veteran\tFighter\t2\t0",
+            "veteran\tFighter\t2\t0\tsome",
+            "lost\t1",
+        ] {
+            assert!(Run::parse(line).is_err(), "{line}");
+        }
+    }
+
+    /// Starts a campaign match without a window, which should bring back the veterans at their levels and with
+    /// their injuries
+    #[test]
+    fn veterans_return() {
+        let mut app = crate::app(crate::cli::Args::parse_from(["--headless", "--mode", "ai"].map(String::from)));
+        app.world.resource_mut::<MatchConfig>().campaign = true;
+        app.world.resource_mut::<Campaign>().run = Some(Run {
+            won: 2,
+            upgrades: 1,
+            tier: 0,
+            purse: 0,
+            veterans: vec![
+                Veteran { kind: "Fighter", level: 3, xp: 5, injuries: 0 },
+                Veteran { kind: "Medic", level: 1, xp: 0, injuries: 1 },
+            ],
+        });
+        for _ in 0..3 {
+            app.update();
+        }
+        let mut veterans: Vec<_> = app.world.query::<(&UnitName, &Experience, &Health, &Injuries)>()
+            .iter(&app.world)
+            .filter(|(name, ..)| name.side == Side::Left)
+            .map(|(name, experience, health, injuries)| (name.number, experience.level, health.max, injuries.0))
+            .collect();
+        veterans.sort();
+        assert_eq!(veterans, [(1, 3, UNITS[0].hp + 4, 0), (2, 1, UNITS[1].hp - INJURY_PENALTY.1, 1)]);
+        assert_eq!(app.world.resource::<Economy>().upgrades[Side::Left], 1);
+    }
+}
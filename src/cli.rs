@@ -0,0 +1,347 @@
+//! Options given on the command line, so a particular setup can be launched straight away:
+//!
+//! ```text
+//! sidewars --mode ai --difficulty brutal --map "Rocky pass" --window 1600x900 --log debug
+//! sidewars --headless --seed 42 --map 3
+//! sidewars --mode ai --sides 4
+//! sidewars --host 4568
+//! sidewars --connect 192.168.1.20:4568
+//! ```
+//!
+//! With `--mode`, the match starts right away instead of waiting in the menu. The difficulty and window size
+//! only last for the session, and aren't saved to the settings. `--sides 3` or `4` makes it a free-for-all,
+//! see `ffa`, which hot-seat matches can't be.
+//!
+//! `--seed` starts the match's randomness from a given seed, see `match_rng`, so the same match can be played
+//! out again. `--headless` runs without a window, drawing and playing sound for no one: the AI plays both sides
+//! unless another mode is given, the match runs as fast as it can be simulated, and the game quits once it's over.
+//!
+//! With the `online` feature, `--host` waits for another player to `--connect`, see `online`.
+
+use std::{env, iter, process};
+
+use bevy::{app::AppExit, log::Level, prelude::*, time::TimeUpdateStrategy, utils::Instant};
+
+use crate::{
+    ai::Difficulty, ai_strategy::Strategy, exhibition::Exhibition, maps::Maps, match_stats::MatchStats, GameState,
+    MatchConfig, Side,
+};
+
+const USAGE: &str = "\
+Usage: sidewars [options]
+
+  --mode <ai|hotseat|watch>    start a match against the AI, against a second player or between two AIs
+  --difficulty <name>          difficulty of the AI: easy, normal, hard or brutal
+  --map <name|number>          map to play, by name or by its number in the menu
+  --sides <2|3|4>              sides in the match, three or four making it a free-for-all against the AI
+  --window <width>x<height>    size of the window, until another resolution is picked in the settings
+  --log <level>                error, warn, info, debug or trace, debug and trace starting the battle log (F4)
+  --seed <number>              seed the first match is played out from, each match after it using the next one
+  --headless                   run without a window, watching the AI play itself unless another mode is given
+  --host <port>                wait for another player to connect and play them, with the online feature
+  --connect <address>          play the player hosting at the address, like 192.168.1.20:4568, with the online feature
+  --exhibition [matches] [left strategy] [right strategy]
+                               play AI-vs-AI matches unattended without a window, see src/exhibition.rs
+  --help                       show this";
+
+/// Who plays a match started from the command line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    VersusAi,
+    HotSeat,
+    Watch,
+}
+
+impl Mode {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "ai" => Some(Mode::VersusAi),
+            "hotseat" => Some(Mode::HotSeat),
+            "watch" => Some(Mode::Watch),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Resource)]
+pub struct Args {
+    /// Taken once the match has been started
+    pub mode: Option<Mode>,
+    pub difficulty: Option<Difficulty>,
+    pub map: Option<String>,
+    /// Sides in the match started, from 2 to 4
+    pub sides: Option<usize>,
+    pub window_size: Option<(f32, f32)>,
+    pub log_level: Option<Level>,
+    pub seed: Option<u64>,
+    /// Whether to run without a window
+    pub headless: bool,
+    /// Port to wait for another player on
+    #[cfg(feature = "online")]
+    pub host: Option<u16>,
+    /// Address of the player to connect to
+    #[cfg(feature = "online")]
+    pub connect: Option<String>,
+    pub exhibition: Option<Exhibition>,
+    /// Options that were ignored and why, logged once logging has started
+    pub problems: Vec<String>,
+}
+
+impl Args {
+    /// Reads the options from the command line, noting down any that don't make sense
+    pub fn parse() -> Self {
+        Args::parse_from(env::args().skip(1))
+    }
+    pub fn parse_from(args: impl IntoIterator<Item = String>) -> Self {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter().peekable();
+        while let Some(arg) = args.next() {
+            if arg == "--help" {
+                println!("{USAGE}");
+                process::exit(0);
+            }
+            if arg == "--exhibition" {
+                // All of its values are optional, so it takes whatever comes before the next option
+                let values: Vec<_> = iter::from_fn(|| args.next_if(|value| !value.starts_with("--"))).collect();
+                let (matches, names) = match values.first().and_then(|n| n.parse().ok()) {
+                    Some(matches) => (Some(matches), &values[1..]),
+                    None => (None, &values[..]),
+                };
+                let mut strategies = [None; 2];
+                for (strategy, name) in strategies.iter_mut().zip(names) {
+                    *strategy = Strategy::ALL.into_iter().find(|s| s.name().eq_ignore_ascii_case(name));
+                    if strategy.is_none() {
+                        parsed.problems.push(format!("Ignoring unknown strategy {name}, see --help"));
+                    }
+                }
+                for name in names.iter().skip(2) {
+                    parsed.problems.push(format!("Ignoring {name}, an exhibition takes a strategy for each side"));
+                }
+                parsed.exhibition = Some(Exhibition::new(matches, strategies));
+                continue
+            }
+            if !arg.starts_with("--") {
+                parsed.problems.push(format!("Ignoring {arg}, see --help"));
+                continue
+            }
+            if arg == "--headless" {
+                parsed.headless = true;
+                continue
+            }
+            let Some(value) = args.next() else {
+                parsed.problems.push(format!("{arg} needs a value, see --help"));
+                continue
+            };
+            let valid = match &*arg {
+                "--mode" => {
+                    parsed.mode = Mode::parse(&value);
+                    parsed.mode.is_some()
+                }
+                "--difficulty" => {
+                    parsed.difficulty = Difficulty::ALL.into_iter().find(|d| d.name().eq_ignore_ascii_case(&value));
+                    parsed.difficulty.is_some()
+                }
+                "--map" => {
+                    parsed.map = Some(value.clone());
+                    true
+                }
+                "--sides" => {
+                    parsed.sides = value.parse().ok().filter(|sides| (2..=Side::ALL.len()).contains(sides));
+                    parsed.sides.is_some()
+                }
+                "--window" => {
+                    parsed.window_size = value.split_once('x')
+                        .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+                        .filter(|&(width, height): &(f32, f32)| width >= 1. && height >= 1.);
+                    parsed.window_size.is_some()
+                }
+                "--log" => {
+                    parsed.log_level = value.parse().ok();
+                    parsed.log_level.is_some()
+                }
+                "--seed" => {
+                    parsed.seed = value.parse().ok();
+                    parsed.seed.is_some()
+                }
+                #[cfg(feature = "online")]
+                "--host" => {
+                    parsed.host = value.parse().ok();
+                    parsed.host.is_some()
+                }
+                #[cfg(feature = "online")]
+                "--connect" => {
+                    parsed.connect = Some(value.clone());
+                    true
+                }
+                _ => {
+                    parsed.problems.push(format!("Ignoring unknown option {arg}, see --help"));
+                    continue
+                }
+            };
+            if !valid {
+                parsed.problems.push(format!("Ignoring {arg} {value}, see --help"));
+            }
+        }
+        // Exhibitions start their matches themselves, and no one's watching them
+        if parsed.exhibition.is_some() {
+            parsed.headless = true;
+        }
+        // Without a window, there's no one to play but the AI, or another player online
+        else if parsed.headless && parsed.mode.is_none() && !parsed.online() {
+            parsed.mode = Some(Mode::Watch);
+        }
+        parsed
+    }
+    /// Whether to play another player online, see `online`
+    fn online(&self) -> bool {
+        #[cfg(feature = "online")]
+        return self.host.is_some() || self.connect.is_some();
+        #[cfg(not(feature = "online"))]
+        false
+    }
+}
+
+/// Selects the map asked for, by name or by its number in the menu
+pub fn select_map(args: Res<Args>, maps: Res<Maps>, mut config: ResMut<MatchConfig>) {
+    let Some(map) = &args.map else {
+        return
+    };
+    let found = maps.0.iter()
+        .position(|m| m.name.eq_ignore_ascii_case(map))
+        .or_else(|| map.parse::<usize>().ok().filter(|&n| (1..=maps.0.len()).contains(&n)).map(|n| n - 1));
+    match found {
+        Some(i) => config.map = i,
+        None => warn!("No map called {map}"),
+    }
+}
+
+/// Without a window, time moves on by exactly one simulation tick every frame instead of following the clock,
+/// so a match runs as fast as it can be simulated and plays out the same for the same seed
+pub fn headless_clock_system(fixed_time: Res<FixedTime>, mut strategy: ResMut<TimeUpdateStrategy>) {
+    match &mut *strategy {
+        TimeUpdateStrategy::ManualInstant(now) => *now += fixed_time.period,
+        strategy => *strategy = TimeUpdateStrategy::ManualInstant(Instant::now()),
+    }
+}
+
+/// The game speed picked in the settings would skip or repeat ticks
+pub fn headless_speed_system(mut time: ResMut<Time>) {
+    time.set_relative_speed(1.);
+}
+
+/// Quits once a match played without a window is over, as there's no one to look at the results
+pub fn headless_exit_system(stats: Res<MatchStats>, mut exit: EventWriter<AppExit>) {
+    let winner = stats.winner().map_or("none".to_owned(), |side| side.to_string());
+    info!(%winner, duration = stats.duration(), "match over");
+    exit.send(AppExit);
+}
+
+/// Starts the match asked for the first time the menu is shown
+pub fn launch_system(
+    mut args: ResMut<Args>,
+    mut config: ResMut<MatchConfig>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Some(mode) = args.mode.take() else {
+        return
+    };
+    config.versus_ai = mode != Mode::HotSeat;
+    config.spectating = mode == Mode::Watch;
+    if config.versus_ai {
+        config.extra_sides = args.sides.map_or(0, |sides| sides - 2);
+    }
+    next_state.set(GameState::Playing);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Health, UnitName};
+
+    fn parse(args: &[&str]) -> Args {
+        Args::parse_from(args.iter().map(|&arg| arg.to_owned()))
+    }
+
+    #[test]
+    fn options() {
+        let args = parse(&["--mode", "ai", "--difficulty", "Brutal", "--map", "Rocky pass", "--window", "1600x900"]);
+        assert_eq!(args.mode, Some(Mode::VersusAi));
+        assert_eq!(args.sides, None);
+        assert_eq!(args.difficulty, Some(Difficulty::Brutal));
+        assert_eq!(args.map.as_deref(), Some("Rocky pass"));
+        assert_eq!(args.window_size, Some((1600., 900.)));
+        assert!(!args.headless && args.exhibition.is_none() && args.problems.is_empty());
+    }
+
+    #[test]
+    fn problems() {
+        let args = parse(&[
+            "--mode", "chess", "--window", "0x900", "--seed", "-1", "--sides", "5", "stray", "--colour", "red",
+        ]);
+        assert_eq!(args.mode, None);
+        assert_eq!(args.window_size, None);
+        assert_eq!(args.seed, None);
+        assert_eq!(args.problems, [
+            "Ignoring --mode chess, see --help",
+            "Ignoring --window 0x900, see --help",
+            "Ignoring --seed -1, see --help",
+            "Ignoring --sides 5, see --help",
+            "Ignoring stray, see --help",
+            "Ignoring unknown option --colour, see --help",
+        ]);
+        assert_eq!(parse(&["--log"]).problems, ["--log needs a value, see --help"]);
+    }
+
+    #[test]
+    fn headless() {
+        let args = parse(&["--headless", "--seed", "42"]);
+        assert_eq!((args.headless, args.seed, args.mode), (true, Some(42), Some(Mode::Watch)));
+        let args = parse(&["--headless", "--mode", "ai", "--sides", "3"]);
+        assert_eq!((args.mode, args.sides), (Some(Mode::VersusAi), Some(3)));
+    }
+
+    #[cfg(feature = "online")]
+    #[test]
+    fn online() {
+        let args = parse(&["--headless", "--host", "4568"]);
+        assert_eq!((args.host, args.mode), (Some(4568), None));
+        let args = parse(&["--connect", "192.168.1.20:4568", "--host", "port"]);
+        assert_eq!((args.host, args.connect.as_deref()), (None, Some("192.168.1.20:4568")));
+        assert_eq!(args.problems, ["Ignoring --host port, see --help"]);
+    }
+
+    #[test]
+    fn exhibition() {
+        let args = parse(&["--exhibition", "3", "rush", "--seed", "1"]);
+        assert!(args.headless && args.exhibition.is_some() && args.problems.is_empty());
+        assert_eq!(args.seed, Some(1));
+        let args = parse(&["--exhibition", "rush", "turtle", "boom"]);
+        assert!(args.exhibition.is_some());
+        assert_eq!(args.problems.len(), 2);
+        assert!(parse(&["--exhibition"]).exhibition.is_some());
+    }
+
+    /// Plays the start of a seeded match without a window twice, which should go the same way both times
+    #[test]
+    fn seeded_match_plays_out_the_same() {
+        const TICKS: usize = 600;
+        let play = || {
+            let mut app = crate::app(parse(&["--headless", "--seed", "7"]));
+            for _ in 0..TICKS {
+                app.update();
+            }
+            assert_eq!(app.world.resource::<State<GameState>>().0, GameState::Playing);
+            let mut units: Vec<_> = app.world.query::<(&UnitName, &Transform, &Health)>()
+                .iter(&app.world)
+                .map(|(name, transform, health)| (name.side, name.number, transform.translation, health.hp))
+                .collect();
+            units.sort_by_key(|&(side, number, ..)| (side, number));
+            units
+        };
+        let units = play();
+        for side in [Side::Left, Side::Right] {
+            assert!(units.iter().any(|unit| unit.0 == side), "{side} deployed nothing");
+        }
+        assert_eq!(units, play());
+    }
+}
@@ -0,0 +1,775 @@
+use std::{cmp::Ordering, collections::{HashMap, HashSet}, sync::mpsc::sync_channel};
+
+use rand::Rng;
+
+use bevy::prelude::*;
+
+use crate::{corpse, particles::{self, Burst}, recall::Recalling, orders::FallingBack, stance::Stance, aura::{Escorted, Inspired, INSPIRED_ATTACK, INSPIRED_DEFENCE}, day_night::Clock, locale::Locale, match_rng::MatchRng, status::{EffectKind, StatusEffects}, terrain::{HighGround, HILL_ATTACK, HILL_DEFENCE}, verbose_log::VerboseLog, weather::Weather, AttackCooldown, Attackers, CombatStats, CombatTarget, Health, Materials, MovementState, Side, Skills, Team, Timeout, UnitName, UnitType, within_x};
+
+pub const COOLDOWN: f32 = 1.;
+/// How far away a healer can reach a wounded ally
+const HEAL_RANGE: f32 = 80.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DamageType {
+    Slashing,
+    Piercing,
+    Blunt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArmorClass {
+    Unarmored,
+    Padded,
+    Mail,
+    Shield,
+}
+
+/// What a unit goes after on the battlefield
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Targeting {
+    /// Engages the enemy units it runs into
+    Units,
+    /// Walks past enemy units and only damages the enemy's side
+    Structures,
+}
+
+impl DamageType {
+    /// How much of a hit of this type gets through the armor
+    pub fn multiplier(self, armor: ArmorClass) -> f32 {
+        use ArmorClass::*;
+        use DamageType::*;
+
+        match (self, armor) {
+            (Slashing, Unarmored) => 1.25,
+            (Slashing, Padded) => 1.,
+            (Slashing, Mail | Shield) => 0.75,
+            (Piercing, Unarmored) => 1.25,
+            (Piercing, Padded | Mail) => 1.,
+            (Piercing, Shield) => 0.5,
+            (Blunt, Unarmored) => 1.,
+            (Blunt, Padded) => 0.75,
+            (Blunt, Mail) => 1.25,
+            (Blunt, Shield) => 1.5,
+        }
+    }
+}
+
+/// Sent whenever an attack lands, even if the target's protection soaked all of it
+#[derive(Debug, Clone, Copy)]
+pub struct DamageEvent {
+    pub attacker: Entity,
+    /// Side of the attacker, kept here as it may be gone by the time the damage is dealt, like a bomber
+    pub attacker_side: Side,
+    pub target: Entity,
+    pub amount: u8,
+    /// The status effect dealing the damage, if it isn't a direct hit
+    pub effect: Option<EffectKind>,
+    pub critical: bool,
+}
+
+/// Sent when a hit would have landed but the target dodged it
+#[derive(Debug, Clone, Copy)]
+pub struct MissEvent {
+    pub target: Entity,
+}
+
+/// Sent when a healer restores some of an ally's HP
+#[derive(Debug, Clone, Copy)]
+pub struct HealEvent {
+    pub healer: Entity,
+    pub target: Entity,
+    pub amount: u8,
+}
+
+/// Sent once when a fighter's HP reaches zero
+#[derive(Debug, Clone, Copy)]
+pub struct DeathEvent {
+    pub entity: Entity,
+    pub killer: Entity,
+    /// Side credited with the kill, even if the killer is gone
+    pub killer_side: Side,
+    /// Name of the killer when the blow was dealt, if it's a unit
+    pub killer_name: Option<UnitName>,
+}
+
+type FightingItem<'a> = (
+    Entity,
+    &'a UnitType,
+    &'a CombatStats,
+    &'a Health,
+    &'a mut AttackCooldown,
+    &'a mut CombatTarget,
+    &'a Attackers,
+    &'a StatusEffects,
+    &'a Transform,
+    Option<&'a Inspired>,
+    Option<&'a Escorted>,
+    Option<&'a FallingBack>,
+);
+
+pub fn fighting_system(
+    fixed_time: Res<FixedTime>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut miss_events: EventWriter<MissEvent>,
+    high_ground: Res<HighGround>,
+    clock: Res<Clock>,
+    mut rng: ResMut<MatchRng>,
+    mut query: Query<FightingItem>,
+    names: Query<&UnitName>,
+    teams: Query<&Team>,
+) {
+    let _span = debug_span!("combat").entered();
+    let (tx, rx) = sync_channel(query.iter_mut().len());
+
+    let delta = fixed_time.period.as_secs_f32();
+    let high_ground = &*high_ground;
+    let clock = &*clock;
+
+    query
+        .par_iter_mut().for_each_mut(move |(ent, unit, stats, health, mut cooldown, mut target, attackers, status, transform, inspired, ..)| {
+            if !status.can_act() {
+                return
+            }
+            cooldown.0 -= delta;
+            if cooldown.0 <= 0. {
+                cooldown.0 = 0.;
+                // Healers don't fight back
+                if unit.skills.heal > 0 {
+                    return
+                }
+                if let Some(fighting) = target.0 {
+                    let mut skills = unit.skills;
+                    skills.attack = stats.attack;
+                    skills.strength = stats.strength(unit.rage(health));
+                    skills.attack = clock.attack(&skills);
+                    if inspired.is_some() {
+                        skills.attack = skills.attack.saturating_add(INSPIRED_ATTACK);
+                    }
+                    if high_ground.contains(transform.translation.truncate()) {
+                        skills.attack = skills.attack.saturating_add(HILL_ATTACK);
+                    }
+                    tx.send((ent, fighting, skills, status.damage_factor())).unwrap();
+
+                    // Takes turns hitting back at everyone fighting it
+                    if let Some(i) = attackers.0.iter().position(|&e| e == fighting) {
+                        target.0 = Some(attackers.0[(i + 1) % attackers.0.len()]);
+                    }
+                }
+            }
+        });
+
+    // Resolved in a fixed order rather than whichever order the parallel loop finished in, to stay deterministic.
+    // Entities are no good for that, as their numbers depend on whatever else was spawned for show
+    let mut attacks: Vec<_> = rx.into_iter().collect();
+    attacks.sort_by_cached_key(|&(fighter, ..)| names.get(fighter).ok().map(|name| (name.side, name.number)));
+
+    for (fighter, fought_ent, skills, damage_factor) in attacks {
+        let Ok(&Team(side)) = teams.get(fighter) else {
+            continue
+        };
+        if let Ok((
+            _, fought, fought_stats, fought_health, fought_cooldown, _, _, _,
+            fought_transform, fought_inspired, fought_escorted, fought_falling_back,
+        )) = query.get(fought_ent) {
+            // Siege units can't miss a structure
+            if skills.targeting == Targeting::Structures && fought.skills.is_structure() {
+                damage_events.send(DamageEvent {
+                    attacker: fighter,
+                    attacker_side: side,
+                    target: fought_ent,
+                    amount: skills.siege,
+                    effect: None,
+                    critical: false,
+                });
+                let (_, unit, _, health, mut cooldown, ..) = query.get_mut(fighter).unwrap();
+                cooldown.0 += unit.cooldown(health);
+                continue
+            }
+
+            // Units that just broke off from the fight have their backs turned
+            let defence = if fought_cooldown.reloading(fought) || fought_falling_back.is_some_and(FallingBack::exposed) {
+                0
+            } else if fought_inspired.is_some() {
+                fought_stats.defence.saturating_add(INSPIRED_DEFENCE)
+            } else {
+                fought_stats.defence
+            };
+            let defence = if high_ground.contains(fought_transform.translation.truncate()) {
+                defence.saturating_add(HILL_DEFENCE)
+            } else {
+                defence
+            };
+            if fought_health.hp > 0 && rng.gen_range(0..=skills.attack) > rng.gen_range(0..=defence) {
+                if rng.gen_range(0..100) < fought.skills.dodge {
+                    miss_events.send(MissEvent { target: fought_ent });
+                } else {
+                    let critical = rng.gen_range(0..100) < skills.crit;
+                    let multiplier = damage_factor * skills.damage_type.multiplier(fought.skills.armor);
+                    let mut dmg = (rng.gen_range(1..=skills.strength) as f32 * multiplier).round() as u8;
+                    if critical {
+                        dmg = dmg.saturating_mul(2);
+                    }
+
+                    let mut protection = fought_health.protection;
+                    if fought_escorted.is_some() {
+                        protection = protection.saturating_add(fought.skills.escort_protection);
+                    }
+                    let amount = dmg.saturating_sub(rng.gen_range(0..=protection));
+
+                    damage_events.send(DamageEvent {
+                        attacker: fighter,
+                        attacker_side: side,
+                        target: fought_ent,
+                        amount,
+                        effect: None,
+                        critical,
+                    });
+                }
+            }
+        } else {
+            let (_, _, _, _, _, mut target, ..) = query.get_mut(fighter).unwrap();
+            target.0 = None;
+        }
+        let (_, unit, _, health, mut cooldown, ..) = query.get_mut(fighter).unwrap();
+        cooldown.0 += unit.cooldown(health);
+    }
+}
+
+/// How far apart two units can drift and still keep fighting, so jostling doesn't break up a fight
+const TARGET_SLACK: f32 = 4.;
+
+/// Where a unit is and what it is, as seen by units looking for something to fight
+struct Candidate {
+    entity: Entity,
+    side: Side,
+    /// The way it's facing along the x-axis
+    heading: f32,
+    position: Vec2,
+    size: Vec2,
+    skills: Skills,
+    /// How far ahead it can hit, beyond what it touches
+    reach: f32,
+    alive: bool,
+    /// Whether it picks enemies fewer allies are fighting, rather than whichever is closest
+    spreads_out: bool,
+}
+
+impl Candidate {
+    /// Whether it stops to fight `other`
+    fn fights(&self, other: &Candidate) -> bool {
+        other.side != self.side && other.alive && self.skills.engages(&other.skills)
+    }
+    /// How far `other` is from being in reach, if it's in reach at all
+    fn reach_gap(&self, other: &Candidate) -> Option<f32> {
+        let reach = self.reach;
+        let gap = (other.position - self.position).abs() - 0.5 * (self.size + other.size);
+        if gap.cmple(Vec2::splat(TARGET_SLACK)).all() {
+            return Some(gap.x.max(0.))
+        }
+        // Reach only extends ahead, along the unit's row
+        let ahead = (other.position.x - self.position.x) * self.heading - 0.5 * (self.size.x + other.size.x);
+        (gap.y < 0. && ahead >= 0. && ahead <= reach).then_some(ahead)
+    }
+}
+
+type TargetingItem<'a> = (
+    Entity,
+    &'a Team,
+    &'a UnitType,
+    &'a Health,
+    &'a mut CombatTarget,
+    &'a mut Attackers,
+    &'a Transform,
+    &'a TextureAtlasSprite,
+    &'a MovementState,
+    &'a Stance,
+);
+
+/// Keeps every unit fighting something it can reach. Targets that died, left or were never in reach are dropped,
+/// and units without one take the closest enemy in reach. Aggressive units spread out over enemies fewer allies
+/// are fighting instead, while the rest stick to whatever is closest.
+/// Afterwards each unit learns who's fighting it, to hit back at them in turn.
+///
+/// Towers pick their own targets further out, after this.
+pub fn targeting_system(weather: Res<Weather>, mut query: Query<TargetingItem>) {
+    let candidates: Vec<_> = query.iter()
+        .map(|(entity, &Team(side), unit, health, .., transform, sprite, movement, stance)| Candidate {
+            entity,
+            side,
+            heading: movement.heading(side),
+            position: transform.translation.truncate(),
+            size: sprite.custom_size.unwrap(),
+            skills: unit.skills,
+            reach: if unit.skills.heal > 0 { 0. } else { weather.reach(side, unit.skills.reach as f32) },
+            alive: health.hp > 0,
+            spreads_out: stance.chases(),
+        })
+        .collect();
+    let by_entity: HashMap<Entity, &Candidate> = candidates.iter().map(|c| (c.entity, c)).collect();
+    // Sorted along the battlefield, so each unit only has to look through the ones close enough to reach
+    let mut by_x: Vec<&Candidate> = candidates.iter().collect();
+    by_x.sort_by(|a, b| a.position.x.total_cmp(&b.position.x));
+    let widest = candidates.iter().map(|c| c.size.x).fold(0., f32::max);
+
+    let mut attacker_counts: HashMap<Entity, usize> = HashMap::new();
+    for (_, _, _, _, target, ..) in query.iter() {
+        if let Some(target) = target.0 {
+            *attacker_counts.entry(target).or_default() += 1;
+        }
+    }
+
+    let mut attackers: HashMap<Entity, Vec<Entity>> = HashMap::new();
+    for this in &candidates {
+        let (_, _, _, _, mut target, ..) = query.get_mut(this.entity).unwrap();
+
+        let current = target.0.and_then(|current| by_entity.get(&current).copied());
+        let kept = current.filter(|c| this.fights(c) && this.reach_gap(c).is_some());
+        if kept.is_none() {
+            if let Some(count) = target.0.and_then(|current| attacker_counts.get_mut(&current)) {
+                *count -= 1;
+            }
+            let range = 0.5 * (this.size.x + widest) + this.reach.max(TARGET_SLACK);
+            let closest = within_x(&by_x, |c| c.position.x, this.position.x - range, this.position.x + range).iter()
+                .copied()
+                .filter(|c| this.fights(c))
+                .filter_map(|c| Some((c, this.reach_gap(c)?)))
+                .min_by(|(a, a_gap), (b, b_gap)| {
+                    let count = |c: &Candidate| attacker_counts.get(&c.entity).copied().unwrap_or_default();
+                    let by_count = if this.spreads_out { count(a).cmp(&count(b)) } else { Ordering::Equal };
+                    by_count.then(a_gap.total_cmp(b_gap))
+                })
+                .map(|(c, _)| c);
+            target.0 = closest.map(|c| c.entity);
+            if let Some(closest) = closest {
+                *attacker_counts.entry(closest.entity).or_default() += 1;
+            }
+        }
+
+        let Some(fought) = target.0.and_then(|current| by_entity.get(&current).copied()) else {
+            continue
+        };
+        if fought.fights(this) && fought.reach_gap(this).is_some() {
+            attackers.entry(fought.entity).or_default().push(this.entity);
+        }
+    }
+
+    for (ent, .., mut fought_by, _, _, _, _) in query.iter_mut() {
+        let fought_by_now = attackers.remove(&ent).unwrap_or_default();
+        if fought_by.0 != fought_by_now {
+            fought_by.0 = fought_by_now;
+        }
+    }
+}
+
+/// How far around an enemy the units fighting it spread, in radians to either side of straight ahead
+const FAN_ANGLE: f32 = 1.;
+/// How fast units shuffle into place around the enemy they're fighting, in pixels per second
+const FAN_SPEED: f32 = 40.;
+
+/// Spreads units fighting the same enemy out around it, instead of them queueing up in front of it
+pub fn fan_out_system(
+    fixed_time: Res<FixedTime>,
+    mut query: Query<
+        (Entity, &Team, &UnitType, &CombatTarget, &MovementState, &Stance, &mut Transform, &TextureAtlasSprite),
+        (Without<Recalling>, Without<FallingBack>),
+    >,
+) {
+    let delta = fixed_time.period.as_secs_f32();
+
+    // Ranged units, structures and units holding their position stay where they are
+    let mut groups: HashMap<Entity, Vec<(Entity, f32)>> = HashMap::new();
+    for (ent, _, unit, target, _, stance, transform, _) in query.iter() {
+        let moves = unit.skills.reach == 0 && !unit.skills.is_structure() && stance.advances();
+        if let Some(target) = target.0.filter(|_| moves) {
+            groups.entry(target).or_default().push((ent, transform.translation.y));
+        }
+    }
+
+    // Worked out from where everyone stood before anyone moved, so it doesn't matter which group goes first
+    let mut steps = Vec::new();
+    for (fought, mut group) in groups.into_iter().filter(|(_, group)| group.len() > 1) {
+        let Ok((.., fought_trans, fought_sprite)) = query.get(fought) else {
+            continue
+        };
+        let centre = fought_trans.translation.truncate();
+        let fought_width = fought_sprite.custom_size.unwrap().x;
+
+        // Top to bottom, so no one has to get past anyone else
+        group.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        let last = (group.len() - 1) as f32;
+        for (i, (ent, _)) in group.into_iter().enumerate() {
+            let (_, &Team(side), _, _, movement, _, transform, sprite) = query.get(ent).unwrap();
+            let angle = FAN_ANGLE * (1. - 2. * i as f32 / last);
+            let radius = 0.5 * (sprite.custom_size.unwrap().x + fought_width);
+            let place = centre + radius * Vec2::new(-movement.heading(side) * angle.cos(), angle.sin());
+            steps.push((ent, (place - transform.translation.truncate()).clamp_length_max(FAN_SPEED * delta)));
+        }
+    }
+    for (ent, step) in steps {
+        let (.., mut transform, _) = query.get_mut(ent).unwrap();
+        transform.translation += step.extend(0.);
+    }
+}
+
+/// How long an explosion stays on screen
+const BLAST_TIME: f32 = 0.4;
+
+/// Blows up units with a blast radius as soon as they engage an enemy, damaging everyone nearby
+pub fn explosion_system(
+    mut commands: Commands,
+    mut damage_events: EventWriter<DamageEvent>,
+    query: Query<(Entity, &UnitType, &CombatStats, &CombatTarget, &Team, &Transform)>,
+    name_query: Query<&UnitName>,
+) {
+    for (bomber, unit, stats, fighting, &Team(side), transform) in query.iter() {
+        if unit.skills.blast_radius == 0 || fighting.0.is_none() {
+            continue
+        }
+        let pos = transform.translation.truncate();
+        let radius = unit.skills.blast_radius as f32;
+
+        // Allies aren't spared
+        for (target, fought, .., target_trans) in query.iter() {
+            if target == bomber || target_trans.translation.truncate().distance(pos) > radius {
+                continue
+            }
+            let multiplier = unit.skills.damage_type.multiplier(fought.skills.armor);
+            damage_events.send(DamageEvent {
+                attacker: bomber,
+                attacker_side: side,
+                target,
+                amount: (stats.strength as f32 * multiplier).round() as u8,
+                effect: None,
+                critical: false,
+            });
+        }
+
+        if let Ok(name) = name_query.get(bomber) {
+            info!(unit = %name, "blew up");
+        }
+        commands.entity(bomber).despawn_recursive();
+        spawn_blast(&mut commands, transform.translation, radius);
+    }
+}
+
+/// An eight-pointed burst, made from two squares
+pub fn spawn_blast(commands: &mut Commands, at: Vec3, radius: f32) {
+    let sprite = Sprite {
+        color: Color::rgba(1., 0.55, 0., 0.7),
+        custom_size: Some(Vec2::splat(radius * std::f32::consts::FRAC_1_SQRT_2 * 2.)),
+        .. default()
+    };
+    let at = at + Vec3::Z;
+    let diagonal = commands.spawn(SpriteBundle {
+        transform: Transform::from_translation(at).with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_4)),
+        sprite: sprite.clone(),
+        .. default()
+    }).id();
+    commands.spawn(SpriteBundle {
+        transform: Transform::from_translation(at),
+        sprite,
+        .. default()
+    }).insert(Timeout::new(BLAST_TIME).tied_to(vec![diagonal]));
+}
+
+/// Raging units turn redder as they get more wounded
+pub fn rage_tint_system(
+    materials: Res<Materials>,
+    // Not only on changes in health, as a flash ending puts back the tint from before the hit
+    mut query: Query<(&UnitType, &Health, &Team, &mut TextureAtlasSprite), Without<HitFlash>>,
+) {
+    for (unit, health, &Team(side), mut sprite) in query.iter_mut() {
+        if unit.skills.rages {
+            let calm = 1. - unit.rage(health);
+            let team = materials.team[side];
+            sprite.color = Color::rgb(team.r(), team.g() * calm, team.b() * calm);
+        }
+    }
+}
+
+/// How long a unit flashes after being hit
+const FLASH_TIME: f32 = 0.12;
+const FLASH_COLOR: Color = Color::rgb(1., 0.4, 0.4);
+
+/// Shows a unit as a plain silhouette for a moment after it's hit, remembering how it looked before
+#[derive(Debug, Clone, Component)]
+pub struct HitFlash {
+    time_left: f32,
+    sheet: Handle<TextureAtlas>,
+    color: Color,
+}
+
+pub fn hit_flash_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    materials: Res<Materials>,
+    mut damage_events: EventReader<DamageEvent>,
+    mut query: Query<(Entity, &UnitType, &mut Handle<TextureAtlas>, &mut TextureAtlasSprite, Option<&mut HitFlash>)>,
+) {
+    // In the order they were hit, as the order components are added in decides the order units are gone through in
+    let mut hit = HashSet::new();
+    for target in damage_events.iter().map(|event| event.target).filter(|&target| hit.insert(target)) {
+        let Ok((ent, unit, mut sheet, mut sprite, flash)) = query.get_mut(target) else {
+            continue
+        };
+        match flash {
+            Some(mut flash) => flash.time_left = FLASH_TIME,
+            None => {
+                commands.entity(ent).insert(HitFlash {
+                    time_left: FLASH_TIME,
+                    sheet: sheet.clone(),
+                    color: sprite.color,
+                });
+                *sheet = materials.flash_sheet(unit.skills.look);
+                sprite.color = FLASH_COLOR;
+            }
+        }
+    }
+
+    for (ent, _, mut sheet, mut sprite, flash) in query.iter_mut() {
+        let Some(mut flash) = flash else {
+            continue
+        };
+        flash.time_left -= time.delta_seconds();
+        if flash.time_left <= 0. {
+            *sheet = flash.sheet.clone();
+            sprite.color = flash.color;
+            commands.entity(ent).remove::<HitFlash>();
+        }
+    }
+}
+
+/// Lets healers restore HP to the nearest wounded ally in range instead of attacking
+pub fn healing_system(
+    mut heal_events: EventWriter<HealEvent>,
+    mut rng: ResMut<MatchRng>,
+    mut query: Query<(Entity, &Team, &UnitType, &mut Health, &mut AttackCooldown, &StatusEffects, &Transform)>,
+) {
+    let healers: Vec<_> = query.iter()
+        .filter(|(_, _, unit, _, cooldown, status, _)| unit.skills.heal > 0 && cooldown.0 <= 0. && status.can_act())
+        .map(|(e, &Team(side), unit, .., t)| (e, unit.skills.heal, t.translation.truncate(), side))
+        .collect();
+
+    for (healer, heal, pos, side) in healers {
+        let nearest = query.iter()
+            .filter(|(e, &Team(s), _, health, ..)| *e != healer && health.wounded() && s == side)
+            // Towers and walls can't be mended
+            .filter(|(_, _, unit, ..)| !unit.skills.is_structure())
+            .map(|(e, .., t)| (e, t.translation.truncate().distance(pos)))
+            .filter(|&(_, distance)| distance <= HEAL_RANGE)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b));
+        let Some((target, _)) = nearest else {
+            continue
+        };
+
+        let (_, _, _, mut patient, ..) = query.get_mut(target).unwrap();
+        let amount = rng.gen_range(1..=heal).min(patient.max - patient.hp);
+        patient.hp += amount;
+        heal_events.send(HealEvent { healer, target, amount });
+
+        let (_, _, unit, health, mut cooldown, ..) = query.get_mut(healer).unwrap();
+        cooldown.0 += unit.cooldown(&health);
+    }
+}
+
+pub fn damage_system(
+    mut damage_events: EventReader<DamageEvent>,
+    mut death_events: EventWriter<DeathEvent>,
+    verbose: Res<VerboseLog>,
+    name_query: Query<&UnitName>,
+    mut query: Query<&mut Health>,
+) {
+    for &DamageEvent { attacker, attacker_side, target, amount, critical, .. } in damage_events.iter() {
+        let Ok(mut fought) = query.get_mut(target) else {
+            continue
+        };
+        if fought.hp == 0 {
+            continue
+        }
+
+        fought.hp = fought.hp.saturating_sub(amount);
+        if verbose.0 {
+            if let (Ok(attacker), Ok(target)) = (name_query.get(attacker), name_query.get(target)) {
+                info!(%attacker, %target, amount, critical, hp_left = fought.hp, "hit");
+            }
+        }
+
+        if fought.hp == 0 {
+            death_events.send(DeathEvent {
+                entity: target,
+                killer: attacker,
+                killer_side: attacker_side,
+                killer_name: name_query.get(attacker).ok().copied(),
+            });
+        }
+    }
+}
+
+const POPUP_TIME: f32 = 1.15;
+/// How fast popups drift upwards, in pixels per second
+const POPUP_RISE: f32 = 30.;
+/// How far to either side of a unit its popups can appear, so they don't all line up
+const POPUP_JITTER: f32 = 10.;
+/// How close popups can get before the newer one is pushed up above the older one
+const POPUP_SPACING: Vec2 = Vec2::new(30., 18.);
+
+/// Text rising up and fading out over a unit
+#[derive(Debug, Clone, Copy, Component)]
+pub struct FloatingText {
+    age: f32,
+    marker_alpha: f32,
+}
+
+pub fn spawn_popup(commands: &mut Commands, materials: &Materials, at: Vec3, value: String, font_size: f32, text_color: Color, marker_color: Option<Color>) {
+    let jitter = rand::thread_rng().gen_range(-POPUP_JITTER..POPUP_JITTER);
+    let transform = Transform::from_translation(at + Vec3::new(jitter, 45., 3.));
+
+    let marker = marker_color.map(|color| commands.spawn(SpriteBundle {
+        transform: Transform::from_translation(Vec3::new(0., 0., -2.)),
+        sprite: Sprite {
+            color,
+            custom_size: Some(Vec2::new(15., 15.)),
+            .. default()
+        },
+        .. default()
+    }).id());
+    let ent = commands.spawn(Text2dBundle {
+        text: Text {
+            sections: vec![
+                TextSection {
+                    value,
+                    style: TextStyle {
+                        font: materials.font.clone(),
+                        font_size,
+                        color: text_color,
+                    }
+                }
+            ],
+            .. Default::default()
+        },
+        transform,
+        .. Default::default()
+    }).insert((
+        FloatingText { age: 0., marker_alpha: marker_color.map_or(0., |c| c.a()) },
+        Timeout::new(POPUP_TIME).tied_to(marker.into_iter().collect()),
+    )).id();
+    if let Some(marker) = marker {
+        commands.entity(ent).add_child(marker);
+    }
+}
+
+/// Drifts popups upwards while fading them out, keeping newer ones from covering older ones
+pub fn floating_text_system(
+    time: Res<Time>,
+    mut query: Query<(&mut FloatingText, &mut Transform, &mut Text, Option<&Children>)>,
+    mut marker_query: Query<&mut Sprite>,
+) {
+    let delta = time.delta_seconds();
+    let mut popups: Vec<_> = query.iter_mut().collect();
+    // Oldest first, so each popup only has to make room for the ones before it
+    popups.sort_unstable_by(|(a, ..), (b, ..)| b.age.total_cmp(&a.age));
+
+    for i in 0..popups.len() {
+        let (older, rest) = popups.split_at_mut(i);
+        let (popup, transform, text, children) = &mut rest[0];
+        popup.age += delta;
+        transform.translation.y += POPUP_RISE * delta;
+        for (_, other, ..) in older.iter() {
+            let offset = transform.translation.truncate() - other.translation.truncate();
+            if offset.abs().cmplt(POPUP_SPACING).all() {
+                transform.translation.y = other.translation.y + POPUP_SPACING.y;
+            }
+        }
+
+        let fade = 1. - (popup.age / POPUP_TIME).powi(2);
+        for section in &mut text.sections {
+            section.style.color.set_a(fade);
+        }
+        for &child in children.iter().flat_map(|c| c.iter()) {
+            if let Ok(mut sprite) = marker_query.get_mut(child) {
+                sprite.color.set_a(popup.marker_alpha * fade);
+            }
+        }
+    }
+}
+
+pub fn damage_popup_system(
+    mut commands: Commands,
+    materials: Res<Materials>,
+    mut damage_events: EventReader<DamageEvent>,
+    query: Query<&Transform>,
+) {
+    for event in damage_events.iter() {
+        if let Ok(f_trans) = query.get(event.target) {
+            if event.critical {
+                let value = format!("{}!", event.amount);
+                spawn_popup(&mut commands, &materials, f_trans.translation, value, 28., Color::rgb(1., 0.5, 0.), None);
+            } else {
+                let value = format!("{}", event.amount);
+                spawn_popup(&mut commands, &materials, f_trans.translation, value, 20., Color::WHITE, None);
+            }
+            particles::spawn_burst(&mut commands, f_trans.translation, Burst::blood(f_trans.scale.x));
+        }
+    }
+}
+
+pub fn heal_popup_system(
+    mut commands: Commands,
+    materials: Res<Materials>,
+    mut heal_events: EventReader<HealEvent>,
+    query: Query<&Transform>,
+) {
+    for event in heal_events.iter() {
+        if let Ok(f_trans) = query.get(event.target) {
+            let value = format!("+{}", event.amount);
+            spawn_popup(&mut commands, &materials, f_trans.translation, value, 20., Color::rgb(0., 0.5, 0.), Some(materials.green));
+        }
+    }
+}
+
+pub fn miss_popup_system(
+    mut commands: Commands,
+    materials: Res<Materials>,
+    locale: Res<Locale>,
+    mut miss_events: EventReader<MissEvent>,
+    query: Query<&Transform>,
+) {
+    for event in miss_events.iter() {
+        if let Ok(f_trans) = query.get(event.target) {
+            let value = locale.text("popup.miss").to_owned();
+            spawn_popup(&mut commands, &materials, f_trans.translation, value, 18., Color::GRAY, Some(materials.black));
+        }
+    }
+}
+
+pub fn death_system(
+    mut commands: Commands,
+    mut death_events: EventReader<DeathEvent>,
+    mut query: Query<&mut CombatTarget>,
+    name_query: Query<&UnitName>,
+    body_query: Query<(&Transform, &TextureAtlasSprite, &Handle<TextureAtlas>, &Visibility, Option<&HitFlash>)>,
+) {
+    for event in death_events.iter() {
+        if let (Some(killer), Ok(victim)) = (event.killer_name, name_query.get(event.entity)) {
+            info!(%killer, %victim, "slain");
+        }
+        if let Ok((&transform, sprite, sheet, &visibility, flash)) = body_query.get(event.entity) {
+            // Units usually die flashing from the final blow, but their bodies shouldn't
+            let (sheet, sprite) = match flash {
+                Some(flash) => (flash.sheet.clone(), TextureAtlasSprite { color: flash.color, ..sprite.clone() }),
+                None => (sheet.clone(), sprite.clone()),
+            };
+            corpse::spawn_corpse(&mut commands, sheet, transform, &sprite, visibility);
+            if visibility != Visibility::Hidden {
+                particles::spawn_burst(&mut commands, transform.translation, Burst::death());
+            }
+        }
+        commands.entity(event.entity).despawn_recursive();
+        if let Ok(mut killer) = query.get_mut(event.killer) {
+            if killer.0 == Some(event.entity) {
+                killer.0 = None;
+            }
+        }
+    }
+}
@@ -0,0 +1,63 @@
+//! Bodies left on the ground where units died, so the battlefield shows how the battle went
+
+use bevy::prelude::*;
+
+use crate::{animation::Animation, Timeout};
+
+/// How long a corpse lies on the battlefield
+const CORPSE_TIME: f32 = 30.;
+/// Seconds at the end of its time over which a corpse fades away
+const FADE_TIME: f32 = 5.;
+/// Corpses beyond this many are cleared away, oldest first
+const MAX_CORPSES: usize = 80;
+/// Units walk over corpses, but they're still above the terrain
+const CORPSE_Z: f32 = -0.5;
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct Corpse {
+    color: Color,
+}
+
+pub fn spawn_corpse(
+    commands: &mut Commands,
+    texture_atlas: Handle<TextureAtlas>,
+    mut transform: Transform,
+    sprite: &TextureAtlasSprite,
+    visibility: Visibility,
+) {
+    let animation = Animation::dying();
+    transform.translation.z = CORPSE_Z;
+    // The dead are a bit duller than the living
+    let color = sprite.color * 0.7;
+    commands.spawn(SpriteSheetBundle {
+        texture_atlas,
+        transform,
+        sprite: TextureAtlasSprite {
+            index: animation.index(),
+            color,
+            custom_size: sprite.custom_size,
+            ..default()
+        },
+        visibility,
+        ..default()
+    }).insert((animation, Corpse { color }, Timeout::new(CORPSE_TIME)));
+}
+
+pub fn corpse_system(
+    mut commands: Commands,
+    mut query: Query<(Entity, &Corpse, &Timeout, &mut TextureAtlasSprite)>,
+) {
+    for (_, corpse, timeout, mut sprite) in query.iter_mut() {
+        let fade = (timeout.time_left / FADE_TIME).min(1.);
+        sprite.color.set_a(corpse.color.a() * fade);
+    }
+
+    let count = query.iter().len();
+    if count > MAX_CORPSES {
+        let mut corpses: Vec<_> = query.iter().map(|(ent, _, timeout, _)| (ent, timeout.time_left)).collect();
+        corpses.sort_unstable_by(|(_, a), (_, b)| a.total_cmp(b));
+        for &(ent, _) in &corpses[..count - MAX_CORPSES] {
+            commands.entity(ent).despawn();
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{Health, Level, MovementState, Team, UnitType};
+
+/// Protection units get from bracing themselves
+const BRACE_PROTECTION: Level = 2;
+/// Seconds a unit has to stand still before it's braced
+const BRACE_TIME: f32 = 1.;
+/// How far a unit can move in a tick and still count as standing still, so being jostled doesn't break its stance
+const STILL_DISTANCE: f32 = 0.5;
+/// Widest gap between a shieldsman and the ally behind it that its shield still covers
+const COVER_GAP: f32 = 8.;
+
+/// How long a unit has been standing still, bracing itself for the enemy
+#[derive(Debug, Clone, Copy, Component)]
+pub struct Brace {
+    still_for: f32,
+    position: Vec2,
+}
+
+impl Brace {
+    pub fn at(position: Vec2) -> Self {
+        Brace {
+            still_for: 0.,
+            position,
+        }
+    }
+    fn braced(&self) -> bool {
+        self.still_for >= BRACE_TIME
+    }
+}
+
+/// Shown above a unit while something protects it
+#[derive(Debug, Clone, Copy, Component)]
+pub struct ShieldIcon;
+
+/// Gives every unit the protection of bracing itself while standing still, and of the shield of a shieldsman
+/// right in front of it
+pub fn cover_system(
+    fixed_time: Res<FixedTime>,
+    mut query: Query<(
+        Entity,
+        &Team,
+        &UnitType,
+        &MovementState,
+        &Transform,
+        &TextureAtlasSprite,
+        &mut Health,
+        &mut Brace,
+    )>,
+) {
+    let delta = fixed_time.period.as_secs_f32();
+
+    let units: Vec<_> = query.iter()
+        .filter(|(_, _, unit, ..)| !unit.skills.is_structure())
+        .map(|(ent, &Team(side), unit, movement, t, sprite, ..)| {
+            let size = sprite.custom_size.unwrap();
+            (ent, side, movement.heading(side), t.translation.truncate(), size, unit.skills.cover)
+        })
+        .collect();
+
+    // Each shieldsman covers the closest ally behind it along its row
+    let mut cover: HashMap<Entity, Level> = HashMap::new();
+    for &(shieldsman, side, heading, pos, size, amount) in units.iter().filter(|&&(.., cover)| cover > 0) {
+        let behind = units.iter()
+            .filter(|&&(ent, s, ..)| ent != shieldsman && s == side)
+            .filter(|&&(.., p, _, _)| (pos.x - p.x) * heading > 0.)
+            .filter_map(|&(ent, _, _, p, other_size, _)| {
+                let gap = (pos.x - p.x) * heading - 0.5 * (size.x + other_size.x);
+                let overlapping = (pos.y - p.y).abs() < 0.5 * (size.y + other_size.y);
+                (overlapping && gap <= COVER_GAP).then_some((ent, gap))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b));
+        if let Some((covered, _)) = behind {
+            let best = cover.entry(covered).or_default();
+            *best = (*best).max(amount);
+        }
+    }
+
+    for (ent, _, unit, _, transform, _, mut health, mut brace) in query.iter_mut() {
+        if unit.skills.is_structure() {
+            continue
+        }
+        let pos = transform.translation.truncate();
+        if pos.distance(brace.position) > STILL_DISTANCE {
+            brace.still_for = 0.;
+        } else {
+            brace.still_for += delta;
+        }
+        brace.position = pos;
+
+        let braced = if brace.braced() { BRACE_PROTECTION } else { 0 };
+        let protection = braced.saturating_add(cover.get(&ent).copied().unwrap_or_default());
+        if health.protection != protection {
+            health.protection = protection;
+        }
+    }
+}
+
+/// Shows the shield icon of units while they're protected
+pub fn shield_icon_system(
+    query: Query<(&Health, &Children), Changed<Health>>,
+    mut icon_query: Query<&mut Visibility, With<ShieldIcon>>,
+) {
+    for (health, children) in query.iter() {
+        for &child in children {
+            if let Ok(mut visibility) = icon_query.get_mut(child) {
+                let shown = if health.protection > 0 { Visibility::Inherited } else { Visibility::Hidden };
+                if *visibility != shown {
+                    *visibility = shown;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+
+use crate::{Level, Skills};
+
+/// Seconds for a full day and night
+const DAY_LENGTH: f32 = 120.;
+/// How dark it has to get before it counts as night
+const DUSK: f32 = 0.25;
+/// Fraction of their attack units keep when fighting in the dark
+const NIGHT_ACCURACY: f32 = 0.7;
+/// Attack bonus for nocturnal units at night
+const NIGHT_ATTACK: Level = 8;
+
+const DAY_COLOR: Color = Color::rgb(0.24, 0.5, 0.01);
+const NIGHT_COLOR: Color = Color::rgb(0.04, 0.1, 0.08);
+
+/// Time of day on the battlefield
+#[derive(Debug, Clone, Copy, Default, Resource)]
+pub struct Clock {
+    time: f32,
+}
+
+impl Clock {
+    /// How bright it is, from 0 at midnight to 1 at noon. Matches start in the morning
+    pub fn daylight(&self) -> f32 {
+        0.5 + 0.5 * (TAU * self.time / DAY_LENGTH).sin()
+    }
+    pub fn is_night(&self) -> bool {
+        self.daylight() < DUSK
+    }
+    /// The attack of a unit with `skills` at this time of day
+    pub fn attack(&self, skills: &Skills) -> Level {
+        if !self.is_night() {
+            skills.attack
+        } else if skills.nocturnal {
+            skills.attack.saturating_add(NIGHT_ATTACK)
+        } else {
+            (skills.attack as f32 * NIGHT_ACCURACY) as Level
+        }
+    }
+}
+
+pub fn reset_clock(mut clock: ResMut<Clock>) {
+    *clock = Clock::default();
+}
+
+pub fn clock_system(fixed_time: Res<FixedTime>, mut clock: ResMut<Clock>) {
+    clock.time = (clock.time + fixed_time.period.as_secs_f32()) % DAY_LENGTH;
+}
+
+/// Darkens the battlefield as night falls
+pub fn sky_system(clock: Res<Clock>, mut clear_color: ResMut<ClearColor>) {
+    if !clock.is_changed() {
+        return
+    }
+    let t = clock.daylight();
+    let [dr, dg, db, _] = DAY_COLOR.as_rgba_f32();
+    let [nr, ng, nb, _] = NIGHT_COLOR.as_rgba_f32();
+    clear_color.0 = Color::rgb(nr + (dr - nr) * t, ng + (dg - ng) * t, nb + (db - nb) * t);
+}
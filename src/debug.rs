@@ -0,0 +1,116 @@
+//! Overlay showing what every fighter is up to: who it's fighting, how far it
+//! reaches, whether it's waiting, and where it's heading.
+
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+
+use crate::{CombatTarget, GameState, MovementState, Team, UnitType};
+
+/// Seconds of movement the steering vector looks ahead
+const STEERING_LOOKAHEAD: f32 = 0.5;
+const Z: f32 = 5.;
+
+pub struct DebugOverlayPlugin;
+
+impl Plugin for DebugOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_startup_system(setup_debug_assets)
+            .add_system(debug_overlay_system.in_set(OnUpdate(GameState::Playing)))
+            .add_system(clear_debug_shapes.in_schedule(OnExit(GameState::Playing)));
+    }
+}
+
+#[derive(Debug, Clone, Resource)]
+struct DebugAssets {
+    circle: Mesh2dHandle,
+    range: Handle<ColorMaterial>,
+}
+
+/// Drawn for a single frame
+#[derive(Debug, Clone, Copy, Component)]
+struct DebugShape;
+
+fn setup_debug_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(DebugAssets {
+        circle: meshes.add(shape::Circle::new(1.).into()).into(),
+        range: color_materials.add(ColorMaterial::from(Color::rgba(1., 1., 1., 0.15))),
+    });
+}
+
+fn line(from: Vec2, to: Vec2, color: Color) -> SpriteBundle {
+    let delta = to - from;
+    let mut transform = Transform::from_translation(((from + to) / 2.).extend(Z));
+    transform.rotation = Quat::from_rotation_z(delta.y.atan2(delta.x));
+    SpriteBundle {
+        transform,
+        sprite: Sprite {
+            color,
+            custom_size: Some(Vec2::new(delta.length(), 2.)),
+            .. default()
+        },
+        .. default()
+    }
+}
+
+fn clear_debug_shapes(mut commands: Commands, query: Query<Entity, With<DebugShape>>) {
+    for ent in query.iter() {
+        commands.entity(ent).despawn();
+    }
+}
+
+fn debug_overlay_system(
+    mut commands: Commands,
+    assets: Res<DebugAssets>,
+    old_shapes: Query<Entity, With<DebugShape>>,
+    query: Query<(&Team, &UnitType, &CombatTarget, &MovementState, &Transform, &TextureAtlasSprite)>,
+    target_query: Query<&Transform, With<UnitType>>,
+) {
+    for ent in old_shapes.iter() {
+        commands.entity(ent).despawn();
+    }
+
+    for (&Team(side), unit, target, movement, transform, sprite) in query.iter() {
+        let pos = transform.translation.truncate();
+        let reach = 0.5 * sprite.custom_size.unwrap_or(Vec2::ONE).x;
+
+        commands.spawn(MaterialMesh2dBundle {
+            mesh: assets.circle.clone(),
+            material: assets.range.clone(),
+            transform: Transform::from_translation(pos.extend(Z - 0.1)).with_scale(Vec3::splat(reach)),
+            .. default()
+        }).insert(DebugShape);
+
+        let state_color = if target.0.is_some() {
+            Color::RED
+        } else if movement.held_up() {
+            Color::YELLOW
+        } else {
+            Color::GREEN
+        };
+        commands.spawn(SpriteBundle {
+            transform: Transform::from_translation(pos.extend(Z)),
+            sprite: Sprite {
+                color: state_color,
+                custom_size: Some(Vec2::splat(6.)),
+                .. default()
+            },
+            .. default()
+        }).insert(DebugShape);
+
+        if let Some(target) = target.0.and_then(|e| target_query.get(e).ok()) {
+            commands.spawn(line(pos, target.translation.truncate(), Color::RED)).insert(DebugShape);
+        }
+        if target.0.is_none() {
+            let velocity = 3. * movement.heading(side) * unit.skills.speed as f32 * movement.pace;
+            let ahead = pos + Vec2::new(velocity * STEERING_LOOKAHEAD, 0.);
+            commands.spawn(line(pos, ahead, Color::CYAN)).insert(DebugShape);
+        }
+    }
+}
@@ -0,0 +1,359 @@
+//! Draft rounds against the AI, in the manner of an auto-battler. Before every round each side drafts units into a
+//! fixed number of slots, then both armies are released together and nothing more is deployed until the round is
+//! over. Units reaching the far edge hurt the other side like a siege, and the side with an army left when the
+//! other's is gone hurts it by the siege of everything still standing. The survivors keep their slots and their
+//! levels for the next round, and the first side out of health loses.
+
+use rand::Rng;
+
+use bevy::prelude::*;
+
+use crate::{
+    ai::spawn_banner,
+    balance::Balance,
+    campaign::{deploy_veteran, Deployment, Veteran},
+    combat::DeathEvent,
+    experience::Experience,
+    locale::Locale,
+    match_rng::MatchRng,
+    sound::SiegeEvent,
+    stance::DefaultStance,
+    zone::SpawnZone,
+    ControlOptions, GameState, Health, MatchConfig, MatchOverEvent, Materials, PerSide, SelectedUnit, Side, Team,
+    UnitCounter, UnitName, UnitType, UNITS,
+};
+
+/// Units each side can field in a round
+pub const SLOTS: usize = 8;
+const STARTING_HEALTH: i32 = 30;
+/// Money each side gets to draft with before the first round, and how much more it gets every round after
+const ROUND_MONEY: (u32, u32) = (25, 5);
+/// Seconds a round can last, in case neither army gets anywhere
+const ROUND_TIME: f32 = 90.;
+
+/// The state of a game of draft rounds
+#[derive(Debug, Clone, Resource)]
+pub struct Draft {
+    pub round: u32,
+    pub health: PerSide<i32>,
+    pub money: PerSide<u32>,
+    pub slots: PerSide<[Option<Veteran>; SLOTS]>,
+    /// Seconds left of the round being fought
+    time_left: f32,
+}
+
+impl Default for Draft {
+    fn default() -> Self {
+        Draft {
+            round: 1,
+            health: PerSide::splat(STARTING_HEALTH),
+            money: PerSide::splat(ROUND_MONEY.0),
+            slots: PerSide::default(),
+            time_left: ROUND_TIME,
+        }
+    }
+}
+
+impl Draft {
+    /// Puts a unit like the one at `unit` in [`UNITS`] in the slot if it's empty and the side can afford it
+    fn draft(&mut self, side: Side, slot: usize, unit: usize) -> bool {
+        let skills = &UNITS[unit];
+        if self.slots[side][slot].is_some() || self.money[side] < skills.price {
+            return false
+        }
+        self.money[side] -= skills.price;
+        self.slots[side][slot] = Some(Veteran { kind: skills.name, level: 1, xp: 0, injuries: 0 });
+        true
+    }
+    /// Fills the AI's empty slots with whatever it can afford
+    fn draft_for_ai(&mut self, side: Side, config: &MatchConfig, rng: &mut MatchRng) {
+        for slot in 0..SLOTS {
+            let affordable: Vec<_> = (0..UNITS.len())
+                .filter(|&unit| config.mutators.allows(&UNITS[unit]) && UNITS[unit].price <= self.money[side])
+                .collect();
+            if affordable.is_empty() {
+                break
+            }
+            let unit = affordable[rng.gen_range(0..affordable.len())];
+            self.draft(side, slot, unit);
+        }
+    }
+}
+
+/// Which slot a unit was deployed from
+#[derive(Debug, Clone, Copy, Component)]
+pub struct DraftSlot(usize);
+
+/// Releases both drafted armies at once, as a round begins
+pub fn round_start_system(
+    mut commands: Commands,
+    config: Res<MatchConfig>,
+    options: Res<ControlOptions>,
+    materials: Res<Materials>,
+    zone: Res<SpawnZone>,
+    stances: Res<DefaultStance>,
+    balance: Res<Balance>,
+    locale: Res<Locale>,
+    mut draft: ResMut<Draft>,
+    mut counter: ResMut<UnitCounter>,
+) {
+    if !config.draft {
+        return
+    }
+    draft.time_left = ROUND_TIME;
+    for side in [Side::Left, Side::Right] {
+        // Each slot has its place down the middle of the spawn zone
+        let rect = zone.0[side];
+        for (i, veteran) in draft.slots[side].iter().enumerate() {
+            let Some(veteran) = veteran else {
+                continue
+            };
+            let position = Vec2::new(rect.center().x, rect.min.y + rect.height() * (i as f32 + 0.5) / SLOTS as f32);
+            let unit = Deployment { side, position, stance: stances.0[side] };
+            let deployed = deploy_veteran(&mut commands, &materials, &config, &balance, &mut counter, unit, veteran);
+            if let Some(ent) = deployed {
+                commands.entity(ent).insert(DraftSlot(i));
+            }
+        }
+    }
+    let player = options.player_side;
+    let health = (draft.health[player], draft.health[player.opponent()]);
+    let announcement = locale.format("banner.round", &[&draft.round, &health.0, &health.1]);
+    spawn_banner(&mut commands, &materials, &announcement, Color::WHITE, 2.);
+}
+
+/// Keeps track of the drafted units through a round, and ends it once either army is gone
+pub fn round_end_system(
+    fixed_time: Res<FixedTime>,
+    config: Res<MatchConfig>,
+    mut draft: ResMut<Draft>,
+    mut death_events: EventReader<DeathEvent>,
+    mut siege_events: EventReader<SiegeEvent>,
+    mut match_over: EventWriter<MatchOverEvent>,
+    mut next_state: ResMut<NextState<GameState>>,
+    unit_query: Query<(&Team, &UnitType, &UnitName, &Health, &Experience, Option<&DraftSlot>)>,
+) {
+    // Ticks can run again before the round is left
+    if !config.draft || next_state.0.is_some() {
+        return
+    }
+    // Units dying or sieging are only removed at the end of the tick, so they're still here
+    for event in death_events.iter() {
+        if let Ok((&Team(side), .., Some(&DraftSlot(slot)))) = unit_query.get(event.entity) {
+            draft.slots[side][slot] = None;
+        }
+    }
+    let sieged: Vec<_> = siege_events.iter().map(|event| (event.name.side, event.name.number)).collect();
+    let mut standing = PerSide::<Vec<u8>>::default();
+    for (&Team(side), unit, name, health, experience, slot) in unit_query.iter() {
+        if health.hp == 0 {
+            continue
+        }
+        if sieged.contains(&(side, name.number)) {
+            draft.health[side.opponent()] -= unit.skills.siege as i32;
+        } else {
+            standing[side].push(unit.skills.siege);
+        }
+        // What they've learned is kept for the next round, sieging or not
+        if let Some(veteran) = slot.and_then(|&DraftSlot(slot)| draft.slots[side][slot].as_mut()) {
+            veteran.level = experience.level;
+            veteran.xp = experience.xp;
+        }
+    }
+
+    draft.time_left -= fixed_time.period.as_secs_f32();
+    let wiped_out = [Side::Left, Side::Right].into_iter().find(|&side| standing[side].is_empty());
+    if wiped_out.is_none() && draft.time_left > 0. {
+        return
+    }
+    // Both armies running out at once, or the time, hurts no one
+    if let Some(loser) = wiped_out.filter(|&side| !standing[side.opponent()].is_empty()) {
+        let damage: i32 = standing[loser.opponent()].iter().map(|&siege| siege as i32).sum();
+        draft.health[loser] -= damage;
+        info!(round = draft.round, %loser, damage, "round lost");
+    }
+
+    if draft.health.left <= 0 || draft.health.right <= 0 {
+        // Both running out of health in the same round goes to whoever has more left
+        let winner = if draft.health.left >= draft.health.right { Side::Left } else { Side::Right };
+        match_over.send(MatchOverEvent { winner });
+        next_state.set(GameState::Results);
+    } else {
+        draft.round += 1;
+        for side in [Side::Left, Side::Right] {
+            draft.money[side] += ROUND_MONEY.0 + ROUND_MONEY.1 * (draft.round - 1);
+        }
+        next_state.set(GameState::Draft);
+    }
+}
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct DraftRoot;
+
+/// The player's slots and money, with the slot being drafted into marked
+#[derive(Debug, Clone, Copy, Component)]
+pub struct DraftText {
+    selected: usize,
+}
+
+fn draft_text(draft: &Draft, player: Side, selected: usize, unit: usize, locale: &Locale) -> String {
+    let opponent = player.opponent();
+    let mut lines = vec![
+        locale.format("draft.health", &[&draft.health[player], &draft.health[opponent]]),
+        locale.format("draft.money", &[&draft.money[player]]),
+    ];
+    for (i, slot) in draft.slots[player].iter().enumerate() {
+        let marker = if i == selected { '>' } else { ' ' };
+        let line = match slot {
+            Some(veteran) => locale.format("draft.slot", &[&(i + 1), &locale.name(veteran.kind), &veteran.level]),
+            None => locale.format("draft.empty", &[&(i + 1)]),
+        };
+        lines.push(format!("{marker} {line}"));
+    }
+    let skills = &UNITS[unit];
+    lines.push(locale.format("draft.unit", &[&locale.name(skills.name), &skills.price]));
+    lines.join("\n")
+}
+
+/// Lets the AI draft, and shows the player's slots to draft into
+pub fn setup_draft(
+    mut commands: Commands,
+    materials: Res<Materials>,
+    config: Res<MatchConfig>,
+    options: Res<ControlOptions>,
+    selected: Res<SelectedUnit>,
+    locale: Res<Locale>,
+    mut rng: ResMut<MatchRng>,
+    mut draft: ResMut<Draft>,
+) {
+    let player = options.player_side;
+    draft.draft_for_ai(player.opponent(), &config, &mut rng);
+
+    let style = |font_size| TextStyle {
+        font: materials.font.clone(),
+        font_size,
+        color: Color::WHITE,
+    };
+    commands.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            gap: Size::all(Val::Px(10.)),
+            ..default()
+        },
+        background_color: Color::rgba(0., 0., 0., 0.75).into(),
+        z_index: ZIndex::Global(5),
+        ..default()
+    })
+    .insert(DraftRoot)
+    .with_children(|parent| {
+        parent.spawn(TextBundle::from_section(locale.format("draft.title", &[&draft.round]), style(60.)));
+        let text = draft_text(&draft, player, 0, selected.0, &locale);
+        parent.spawn(TextBundle::from_section(text, style(24.))).insert(DraftText { selected: 0 });
+        parent.spawn(TextBundle::from_section(locale.text("draft.controls"), style(20.)));
+    });
+}
+
+pub fn draft_input_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    options: Res<ControlOptions>,
+    locale: Res<Locale>,
+    mut selected_unit: ResMut<SelectedUnit>,
+    mut draft: ResMut<Draft>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut query: Query<(&mut Text, &mut DraftText)>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        next_state.set(GameState::Playing);
+    }
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_state.set(GameState::Menu);
+    }
+    if keyboard_input.just_pressed(KeyCode::Right) {
+        selected_unit.0 = (selected_unit.0 + 1) % UNITS.len();
+    }
+    if keyboard_input.just_pressed(KeyCode::Left) {
+        selected_unit.0 = (selected_unit.0 + UNITS.len() - 1) % UNITS.len();
+    }
+    let player = options.player_side;
+    for (mut text, mut slots) in query.iter_mut() {
+        if keyboard_input.just_pressed(KeyCode::Down) {
+            slots.selected = (slots.selected + 1) % SLOTS;
+        }
+        if keyboard_input.just_pressed(KeyCode::Up) {
+            slots.selected = (slots.selected + SLOTS - 1) % SLOTS;
+        }
+        if keyboard_input.just_pressed(KeyCode::Space) {
+            draft.draft(player, slots.selected, selected_unit.0);
+        }
+        // Dismissing a unit gives nothing back, or it would be free to sell a unit and draft it again
+        if keyboard_input.just_pressed(KeyCode::Back) {
+            draft.slots[player][slots.selected] = None;
+        }
+        if slots.is_changed() || draft.is_changed() || selected_unit.is_changed() {
+            text.sections[0].value = draft_text(&draft, player, slots.selected, selected_unit.0, &locale);
+        }
+    }
+}
+
+pub fn cleanup_draft(mut commands: Commands, query: Query<Entity, With<DraftRoot>>) {
+    for ent in query.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drafting_takes_money_and_slots() {
+        let mut draft = Draft::default();
+        assert!(draft.draft(Side::Left, 0, 0));
+        assert_eq!(draft.money[Side::Left], ROUND_MONEY.0 - UNITS[0].price);
+        // The slot is taken
+        assert!(!draft.draft(Side::Left, 0, 0));
+        draft.money[Side::Left] = UNITS[0].price - 1;
+        assert!(!draft.draft(Side::Left, 1, 0));
+        assert_eq!(draft.slots[Side::Right], [None; SLOTS]);
+    }
+
+    #[test]
+    fn ai_spends_what_it_can() {
+        let mut draft = Draft::default();
+        draft.draft_for_ai(Side::Right, &MatchConfig::default(), &mut MatchRng::new(Some(1)));
+        let cheapest = UNITS.iter().map(|skills| skills.price).min().unwrap();
+        let drafted = draft.slots[Side::Right].iter().flatten().count();
+        assert!(drafted > 0);
+        assert!(drafted == SLOTS || draft.money[Side::Right] < cheapest);
+    }
+
+    /// Fights a round without a window, which the bigger army should win, hurting the other side
+    #[test]
+    fn a_round_plays_out() {
+        let args = ["--headless", "--mode", "ai", "--seed", "3"].map(String::from);
+        let mut app = crate::app(crate::cli::Args::parse_from(args));
+        app.world.resource_mut::<MatchConfig>().draft = true;
+        let mut draft = app.world.resource_mut::<Draft>();
+        for slot in 0..3 {
+            draft.draft(Side::Left, slot, 0);
+        }
+        draft.draft(Side::Right, 0, 1);
+
+        for _ in 0..5000 {
+            app.update();
+            if app.world.resource::<State<GameState>>().0 == GameState::Draft {
+                break
+            }
+        }
+        assert_eq!(app.world.resource::<State<GameState>>().0, GameState::Draft);
+        let draft = app.world.resource::<Draft>();
+        assert_eq!(draft.round, 2);
+        assert_eq!(draft.health.left, STARTING_HEALTH);
+        assert!(draft.health.right < STARTING_HEALTH);
+        assert!(draft.slots[Side::Left].iter().flatten().count() > 0);
+    }
+}
@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{ai::spawn_banner, bindings::Bindings, combat::{spawn_popup, DeathEvent}, experience::Experience, locale::Locale, maps::Maps, mutators::Mutator, unit_stats::UnitStats, ControlOptions, MatchConfig, Materials, PerSide, Side, Skills, Team, UnitType, UNITS};
+
+/// Share of the victim's price paid to the killer's side for a kill
+const KILL_BOUNTY: f32 = 0.3;
+/// Paid on top of the bounty for every level the victim had gained
+const BOUNTY_PER_LEVEL: u32 = 1;
+/// Money every side gets per second regardless of how the battle goes
+const BASE_INCOME: f32 = 0.5;
+/// Extra income per second from each income upgrade
+const INCOME_PER_UPGRADE: f32 = 0.25;
+/// Price of the first income upgrade, each further one costs this much more
+const UPGRADE_PRICE: u32 = 15;
+/// Seconds between interest payouts under the interest rule
+const INTEREST_INTERVAL: f32 = 15.;
+/// Fraction of unspent money paid as interest
+const INTEREST_RATE: f32 = 0.1;
+/// Most interest paid out at once, so hoarding doesn't snowball
+const MAX_INTEREST: u32 = 20;
+
+/// Ways a side makes money
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IncomeSource {
+    Passive,
+    Kills,
+    Siege,
+    Interest,
+}
+
+/// How much a side has made from each source
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Earnings {
+    pub passive: u32,
+    pub kills: u32,
+    pub siege: u32,
+    pub interest: u32,
+}
+
+/// Each side's money and where it comes from
+#[derive(Debug, Default, Clone, Copy, Resource)]
+pub struct Economy {
+    /// What each side has to spend on deploying units
+    pub money: PerSide<u32>,
+    /// Income upgrades each side has bought
+    pub upgrades: PerSide<u32>,
+    /// Passive income not paid out yet, as only whole money is
+    pending: PerSide<f32>,
+    /// What each side has earned this match
+    pub earnings: PerSide<Earnings>,
+    /// What each side has spent this match
+    pub spent: PerSide<u32>,
+    /// Seconds until the next interest payout, if money earns interest
+    pub next_interest: Option<f32>,
+}
+
+impl Economy {
+    /// Takes `price` from `side` if it can afford it
+    pub fn spend(&mut self, side: Side, price: u32) -> bool {
+        let money = &mut self.money[side];
+        if *money >= price {
+            *money -= price;
+            self.spent[side] += price;
+            true
+        } else {
+            false
+        }
+    }
+    pub fn earn(&mut self, side: Side, amount: u32, source: IncomeSource) {
+        self.money[side] += amount;
+        let earnings = &mut self.earnings[side];
+        match source {
+            IncomeSource::Passive => earnings.passive += amount,
+            IncomeSource::Kills => earnings.kills += amount,
+            IncomeSource::Siege => earnings.siege += amount,
+            IncomeSource::Interest => earnings.interest += amount,
+        }
+    }
+    /// Passive income of `side` per second
+    pub fn income_rate(&self, side: Side) -> f32 {
+        BASE_INCOME + INCOME_PER_UPGRADE * self.upgrades[side] as f32
+    }
+    pub fn upgrade_price(&self, side: Side) -> u32 {
+        UPGRADE_PRICE * (self.upgrades[side] + 1)
+    }
+    /// Buys `side` another income upgrade if it can afford it
+    pub fn buy_upgrade(&mut self, side: Side) -> bool {
+        let bought = self.spend(side, self.upgrade_price(side));
+        if bought {
+            self.upgrades[side] += 1;
+        }
+        bought
+    }
+}
+
+/// How much pricier a unit gets for every recent purchase of it
+const SURGE_PER_PURCHASE: f32 = 0.2;
+/// Recent purchases forgotten per second
+const SURGE_DECAY: f32 = 0.25;
+
+/// Adjustments to unit prices from the pricing rules of the match
+#[derive(Debug, Default, Clone, Resource)]
+pub struct Prices {
+    surge_enabled: bool,
+    /// Recent purchases of each kind of unit, which raise its price under the price surge rule
+    recent: PerSide<HashMap<&'static str, f32>>,
+    /// Multipliers from past results under the meta prices rule
+    meta: HashMap<&'static str, f32>,
+}
+
+impl Prices {
+    /// What `side` currently pays for a unit like this
+    pub fn price(&self, side: Side, skills: &Skills) -> u32 {
+        let recent = self.recent[side].get(skills.name).copied().unwrap_or(0.);
+        let meta = self.meta.get(skills.name).copied().unwrap_or(1.);
+        (skills.price as f32 * meta * (1. + SURGE_PER_PURCHASE * recent)).round() as u32
+    }
+    pub fn record(&mut self, side: Side, skills: &Skills) {
+        if self.surge_enabled {
+            *self.recent[side].entry(skills.name).or_insert(0.) += 1.;
+        }
+    }
+}
+
+pub fn reset_prices(config: Res<MatchConfig>, stats: Res<UnitStats>, mut prices: ResMut<Prices>) {
+    let meta = if config.mutators.contains(Mutator::MetaPrices) {
+        UNITS.iter().map(|s| (s.name, stats.price_factor(s.name))).collect()
+    } else {
+        HashMap::new()
+    };
+    *prices = Prices {
+        surge_enabled: config.mutators.contains(Mutator::PriceSurge),
+        meta,
+        ..default()
+    };
+}
+
+pub fn price_surge_decay_system(fixed_time: Res<FixedTime>, mut prices: ResMut<Prices>) {
+    let decay = SURGE_DECAY * fixed_time.period.as_secs_f32();
+    for side in Side::ALL {
+        prices.recent[side].retain(|_, recent| {
+            *recent -= decay;
+            *recent > 0.
+        });
+    }
+}
+
+pub fn reset_economy(config: Res<MatchConfig>, maps: Res<Maps>, mut economy: ResMut<Economy>) {
+    *economy = Economy {
+        money: PerSide::splat(maps.0[config.map].starting_money),
+        next_interest: config.mutators.contains(Mutator::Interest).then_some(INTEREST_INTERVAL),
+        ..default()
+    };
+}
+
+pub fn income_system(fixed_time: Res<FixedTime>, mut economy: ResMut<Economy>) {
+    let delta = fixed_time.period.as_secs_f32();
+    for side in Side::ALL {
+        economy.pending[side] += economy.income_rate(side) * delta;
+        let paid = economy.pending[side].floor();
+        if paid >= 1. {
+            economy.pending[side] -= paid;
+            economy.earn(side, paid as u32, IncomeSource::Passive);
+        }
+    }
+}
+
+/// Pays out interest on unspent money, for matches played with the interest rule
+pub fn interest_system(
+    mut commands: Commands,
+    fixed_time: Res<FixedTime>,
+    options: Res<ControlOptions>,
+    materials: Res<Materials>,
+    locale: Res<Locale>,
+    mut economy: ResMut<Economy>,
+) {
+    let Some(next_interest) = economy.next_interest.as_mut() else {
+        return
+    };
+    *next_interest -= fixed_time.period.as_secs_f32();
+    if *next_interest > 0. {
+        return
+    }
+    *next_interest += INTEREST_INTERVAL;
+
+    for side in Side::ALL {
+        let interest = ((economy.money[side] as f32 * INTEREST_RATE) as u32).min(MAX_INTEREST);
+        economy.earn(side, interest, IncomeSource::Interest);
+        if side == options.player_side && interest > 0 {
+            spawn_banner(&mut commands, &materials, &locale.format("banner.interest", &[&interest]), Color::rgb(1.0, 0.84, 0.0), 1.5);
+        }
+    }
+}
+
+/// Buys the player an income upgrade
+pub fn income_upgrade_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    options: Res<ControlOptions>,
+    mut economy: ResMut<Economy>,
+) {
+    if keyboard_input.just_pressed(bindings.income_upgrade) {
+        economy.buy_upgrade(options.player_side);
+    }
+}
+
+pub fn kill_bounty_system(
+    mut commands: Commands,
+    materials: Res<Materials>,
+    mut death_events: EventReader<DeathEvent>,
+    mut economy: ResMut<Economy>,
+    victim_query: Query<(&Transform, &Team, &UnitType, Option<&Experience>)>,
+) {
+    for event in death_events.iter() {
+        let killer = event.killer_side;
+        let Ok((victim, &Team(victim_side), unit, experience)) = victim_query.get(event.entity) else {
+            continue
+        };
+        // Nothing for friendly fire
+        if killer == victim_side {
+            continue
+        }
+        let levels = experience.map_or(0, |e| e.level as u32 - 1);
+        let bounty = ((unit.skills.price as f32 * KILL_BOUNTY).round() as u32).max(1) + BOUNTY_PER_LEVEL * levels;
+        economy.earn(killer, bounty, IncomeSource::Kills);
+        spawn_popup(&mut commands, &materials, victim.translation, format!("+{bounty}¤"), 18., Color::rgb(1.0, 0.84, 0.0), Some(materials.black));
+    }
+}
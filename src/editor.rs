@@ -0,0 +1,345 @@
+//! Placing terrain and spawn zones on a map with the mouse, saving it to a map
+//! file and trying it out right away. Built in maps are edited as a copy.
+
+use bevy::{input::mouse::MouseWheel, prelude::*};
+
+use crate::{
+    locale::Locale,
+    maps::{HillDescriptor, MapDescriptor, Maps, ObstacleDescriptor, RiverDescriptor, MAX_LANES},
+    terrain::{lay_out_map, MapPart, ObstacleKind, TerrainAssets, BRIDGE_HALF_HEIGHT},
+    toolbar, Battlefield, GameState, MatchConfig, Materials, MouseLoc,
+};
+
+const MIN_SIZE: f32 = 5.;
+const MAX_SIZE: f32 = 200.;
+/// How much a notch of the mouse wheel changes the size of what's placed
+const SIZE_STEP: f32 = 5.;
+const MONEY_STEP: u32 = 10;
+
+const BUTTON_COLOR: Color = Color::rgba(0., 0., 0., 0.5);
+const HOVERED_COLOR: Color = Color::rgba(0.2, 0.2, 0.2, 0.7);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    Rock,
+    Tree,
+    Hill,
+    River,
+    Bridge,
+    SpawnZone,
+}
+
+impl Tool {
+    const ALL: [Tool; 6] = [Tool::Rock, Tool::Tree, Tool::Hill, Tool::River, Tool::Bridge, Tool::SpawnZone];
+    const KEYS: [KeyCode; 6] = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5, KeyCode::Key6];
+
+    fn name(self) -> &'static str {
+        match self {
+            Tool::Rock => "Rock",
+            Tool::Tree => "Tree",
+            Tool::Hill => "Hill",
+            Tool::River => "River",
+            Tool::Bridge => "Bridge",
+            Tool::SpawnZone => "Spawn zone",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Resource)]
+pub struct Editor {
+    tool: Tool,
+    /// Radius of rocks, trees and hills, and width of rivers
+    size: f32,
+    /// Last thing that happened, like where the map was saved
+    status: String,
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Editor {
+            tool: Tool::Rock,
+            size: 30.,
+            status: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct EditorRoot;
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct EditorText;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+pub enum EditorButton {
+    Save,
+    Playtest,
+}
+
+impl EditorButton {
+    /// Key of its label in the [`Locale`]
+    fn label(self) -> &'static str {
+        match self {
+            EditorButton::Save => "editor.save",
+            EditorButton::Playtest => "editor.playtest",
+        }
+    }
+}
+
+/// Switches to a copy of the selected map if it's a built in one, and shows the editor's controls
+pub fn setup_editor(
+    mut commands: Commands,
+    materials: Res<Materials>,
+    mut config: ResMut<MatchConfig>,
+    mut maps: ResMut<Maps>,
+    mut editor: ResMut<Editor>,
+    locale: Res<Locale>,
+) {
+    if maps.0[config.map].file.is_none() {
+        let original = &maps.0[config.map];
+        let copy = MapDescriptor {
+            name: locale.format("editor.copy", &[&original.name]),
+            ..original.clone()
+        };
+        maps.0.push(copy);
+        config.map = maps.0.len() - 1;
+    }
+    // Have the terrain drawn even when nothing had to be copied
+    maps.set_changed();
+    editor.status.clear();
+
+    commands.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(5.),
+                left: Val::Px(5.),
+                ..default()
+            },
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::FlexStart,
+            gap: Size::all(Val::Px(5.)),
+            ..default()
+        },
+        ..default()
+    }).insert(EditorRoot).with_children(|parent| {
+        parent.spawn(TextBundle {
+            text: Text::from_section("", TextStyle {
+                font: materials.font.clone(),
+                font_size: 16.,
+                color: Color::WHITE,
+            }),
+            background_color: Color::rgba(0., 0., 0., 0.5).into(),
+            ..default()
+        }).insert(EditorText);
+        parent.spawn(NodeBundle {
+            style: Style {
+                gap: Size::all(Val::Px(5.)),
+                ..default()
+            },
+            ..default()
+        }).with_children(|row| {
+            for button in [EditorButton::Save, EditorButton::Playtest] {
+                row.spawn(ButtonBundle {
+                    style: Style {
+                        padding: UiRect::all(Val::Px(5.)),
+                        ..default()
+                    },
+                    background_color: BUTTON_COLOR.into(),
+                    ..default()
+                }).insert(button).with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(locale.text(button.label()), TextStyle {
+                        font: materials.font.clone(),
+                        font_size: 18.,
+                        color: Color::WHITE,
+                    }));
+                });
+            }
+        });
+    });
+}
+
+/// Places and removes terrain where the mouse is clicked, and takes the editor's keys and buttons
+pub fn editor_input_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    mut wheel: EventReader<MouseWheel>,
+    mouse_loc: Res<MouseLoc>,
+    battlefield: Res<Battlefield>,
+    mut config: ResMut<MatchConfig>,
+    mut maps: ResMut<Maps>,
+    mut editor: ResMut<Editor>,
+    mut next_state: ResMut<NextState<GameState>>,
+    locale: Res<Locale>,
+    hover_query: Query<&Interaction, With<Button>>,
+    mut button_query: Query<(&Interaction, &EditorButton, &mut BackgroundColor)>,
+) {
+    let half_size = battlefield.half_size;
+    // Edited on the side so the terrain is only redrawn when something actually changed
+    let mut map = maps.0[config.map].clone();
+
+    for (i, key) in Tool::KEYS.into_iter().enumerate() {
+        if keyboard_input.just_pressed(key) {
+            editor.tool = Tool::ALL[i];
+        }
+    }
+    for event in wheel.iter() {
+        editor.size = (editor.size + SIZE_STEP * event.y.signum()).clamp(MIN_SIZE, MAX_SIZE);
+    }
+    if keyboard_input.just_pressed(KeyCode::L) {
+        map.lanes = (map.lanes + 1) % (MAX_LANES + 1);
+    }
+    if keyboard_input.just_pressed(KeyCode::PageUp) {
+        map.starting_money += MONEY_STEP;
+    }
+    if keyboard_input.just_pressed(KeyCode::PageDown) {
+        map.starting_money = map.starting_money.saturating_sub(MONEY_STEP);
+    }
+
+    let mut pressed: Vec<EditorButton> = Vec::new();
+    for (interaction, &button, mut background) in button_query.iter_mut() {
+        *background = if *interaction == Interaction::None {
+            BUTTON_COLOR
+        } else {
+            HOVERED_COLOR
+        }.into();
+        if *interaction == Interaction::Clicked && mouse_input.just_pressed(MouseButton::Left) {
+            pressed.push(button);
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::S) {
+        pressed.push(EditorButton::Save);
+    }
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        pressed.push(EditorButton::Playtest);
+    }
+    for button in pressed {
+        match button {
+            EditorButton::Save => editor.status = match map.save() {
+                Ok(path) => locale.format("editor.saved", &[&path.display()]),
+                Err(e) => locale.format("editor.save_failed", &[&e]),
+            },
+            EditorButton::Playtest => {
+                config.versus_ai = true;
+                config.spectating = false;
+                config.campaign = false;
+                config.draft = false;
+                config.extra_sides = 0;
+                next_state.set(GameState::Playing);
+            }
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_state.set(GameState::Menu);
+    }
+
+    if !toolbar::hovered(&hover_query) {
+        let cursor = mouse_loc.0;
+        if mouse_input.just_pressed(MouseButton::Left) {
+            // Map files place things in fractions of the battlefield's half-size
+            place(&mut map, editor.tool, editor.size, cursor / half_size, half_size);
+        }
+        if mouse_input.just_pressed(MouseButton::Right) {
+            remove(&mut map, editor.tool, cursor, half_size);
+        }
+    }
+    if map != maps.0[config.map] {
+        maps.0[config.map] = map;
+    }
+}
+
+fn place(map: &mut MapDescriptor, tool: Tool, size: f32, position: Vec2, half_size: Vec2) {
+    match tool {
+        Tool::Rock | Tool::Tree => map.obstacles.push(ObstacleDescriptor {
+            kind: if tool == Tool::Rock { ObstacleKind::Rock } else { ObstacleKind::Tree },
+            position,
+            radius: size,
+        }),
+        Tool::Hill => map.hills.push(HillDescriptor { position, radius: size }),
+        Tool::River => {
+            let bridges = map.river.take().map(|river| river.bridges).unwrap_or_default();
+            map.river = Some(RiverDescriptor {
+                x: position.x,
+                width: size,
+                bridges,
+            });
+        }
+        Tool::Bridge => if let Some(river) = &mut map.river {
+            river.bridges.push(position.y);
+        }
+        Tool::SpawnZone => {
+            // As far in as the cursor is from the closest edge, leaving the middle of the battlefield free
+            let from_edge = half_size.x - (position.x * half_size.x).abs();
+            map.zone_width = from_edge.clamp(MIN_SIZE, half_size.x / 2.);
+        }
+    }
+}
+
+/// Removes whatever the tool places under the cursor, given in world coordinates
+fn remove(map: &mut MapDescriptor, tool: Tool, cursor: Vec2, half_size: Vec2) {
+    match tool {
+        Tool::Rock | Tool::Tree | Tool::Hill => {
+            // Obstacles are drawn on top of hills, so they're removed first
+            let obstacle = map.obstacles.iter()
+                .rposition(|o| (o.position * half_size).distance(cursor) < o.radius);
+            if let Some(i) = obstacle {
+                map.obstacles.remove(i);
+            } else if let Some(i) = map.hills.iter().rposition(|h| (h.position * half_size).distance(cursor) < h.radius) {
+                map.hills.remove(i);
+            }
+        }
+        Tool::River => map.river = None,
+        Tool::Bridge => if let Some(river) = &mut map.river {
+            river.bridges.retain(|&y| (y * half_size.y - cursor.y).abs() >= BRIDGE_HALF_HEIGHT);
+        }
+        Tool::SpawnZone => map.zone_width = MapDescriptor::default().zone_width,
+    }
+}
+
+/// Redraws the terrain whenever the map or the battlefield's size changes, and keeps the help text current
+pub fn editor_draw_system(
+    mut commands: Commands,
+    battlefield: Res<Battlefield>,
+    config: Res<MatchConfig>,
+    maps: Res<Maps>,
+    editor: Res<Editor>,
+    locale: Res<Locale>,
+    assets: Res<TerrainAssets>,
+    part_query: Query<Entity, MapPart>,
+    mut text_query: Query<&mut Text, With<EditorText>>,
+) {
+    let map = &maps.0[config.map];
+    if maps.is_changed() || battlefield.is_changed() {
+        for ent in part_query.iter() {
+            commands.entity(ent).despawn();
+        }
+        lay_out_map(&mut commands, battlefield.half_size, map, &assets);
+    }
+    if maps.is_changed() || editor.is_changed() || locale.is_changed() {
+        let tools: Vec<String> = Tool::ALL.iter().enumerate()
+            .map(|(i, tool)| {
+                let marker = if *tool == editor.tool { '>' } else { ' ' };
+                format!("{marker}{} {}", i + 1, locale.name(tool.name()))
+            })
+            .collect();
+        let mut contents = locale.format("editor.help", &[
+            &map.name,
+            &tools.join("  "),
+            &editor.size,
+            &map.lanes,
+            &map.starting_money,
+        ]);
+        if !editor.status.is_empty() {
+            contents += &format!("\n{}", editor.status);
+        }
+        for mut text in text_query.iter_mut() {
+            text.sections[0].value = contents.clone();
+        }
+    }
+}
+
+pub fn cleanup_editor(mut commands: Commands, query: Query<Entity, With<EditorRoot>>) {
+    for ent in query.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+}
@@ -0,0 +1,81 @@
+//! Unattended AI-vs-AI matches for gathering balance statistics, started with
+//! `sidewars --exhibition [matches] [left strategy] [right strategy]`.
+//!
+//! Matches run back to back without a window, as fast as they can be simulated, see `cli`.
+//! Every result is appended to `exhibition.txt` in the config folder, and the units deployed
+//! count towards the unit statistics like in any other match.
+
+use std::{fs::{self, OpenOptions}, io::Write};
+
+use bevy::{app::AppExit, prelude::*};
+
+use crate::{ai_strategy::Strategy, match_stats::MatchStats, settings::config_path, GameState, MatchConfig, Side};
+
+const EXHIBITION_FILE: &str = "exhibition.txt";
+const DEFAULT_MATCHES: u32 = 10;
+
+#[derive(Debug, Clone, Copy, Resource)]
+pub struct Exhibition {
+    matches_left: u32,
+    strategies: [Option<Strategy>; 2],
+}
+
+impl Exhibition {
+    /// Plays `matches`, or ten if not given, with the strategies for each side that aren't left to the AI
+    pub fn new(matches: Option<u32>, strategies: [Option<Strategy>; 2]) -> Self {
+        Exhibition {
+            matches_left: matches.unwrap_or(DEFAULT_MATCHES),
+            strategies,
+        }
+    }
+}
+
+/// Starts the next match right away instead of waiting in the menu
+pub fn exhibition_start_system(
+    exhibition: Res<Exhibition>,
+    mut config: ResMut<MatchConfig>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    config.versus_ai = true;
+    config.spectating = true;
+    for (side, strategy) in [Side::Left, Side::Right].into_iter().zip(exhibition.strategies) {
+        if let Some(strategy) = strategy {
+            config.strategies[side] = strategy;
+        }
+    }
+    next_state.set(GameState::Playing);
+}
+
+/// Records the result and moves on to the next match, or quits after the last one
+pub fn exhibition_results_system(
+    mut exhibition: ResMut<Exhibition>,
+    config: Res<MatchConfig>,
+    stats: Res<MatchStats>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let winner = stats.winner().map_or("none".to_owned(), |side| side.to_string());
+    let line = format!(
+        "{winner}\t{:.0}\t{}\t{}\t{}\t{}\t{}\n",
+        stats.duration(),
+        config.strategies[Side::Left].name(),
+        config.strategies[Side::Right].name(),
+        config.difficulty.name(),
+        stats.kills(Side::Left),
+        stats.kills(Side::Right),
+    );
+    let path = config_path(EXHIBITION_FILE);
+    let written = path.parent().map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| OpenOptions::new().create(true).append(true).open(&path))
+        .and_then(|mut file| file.write_all(line.as_bytes()));
+    if let Err(e) = written {
+        error!("Could not record exhibition result: {e}");
+    }
+
+    exhibition.matches_left = exhibition.matches_left.saturating_sub(1);
+    if exhibition.matches_left == 0 {
+        exit.send(AppExit);
+    } else {
+        next_state.set(GameState::Menu);
+    }
+}
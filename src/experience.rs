@@ -0,0 +1,81 @@
+use bevy::prelude::*;
+
+use crate::{
+    combat::{DamageEvent, DeathEvent, HealEvent},
+    locale::Locale,
+    CombatStats, Health,
+};
+
+/// Experience gained per point of damage dealt
+const XP_PER_DAMAGE: u32 = 1;
+/// Experience gained per point of HP restored
+const XP_PER_HEAL: u32 = 1;
+const XP_PER_KILL: u32 = 10;
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct Experience {
+    pub xp: u32,
+    pub level: u8,
+}
+
+impl Default for Experience {
+    fn default() -> Self {
+        Experience { xp: 0, level: 1 }
+    }
+}
+
+impl Experience {
+    /// Experience needed to advance past the current level
+    fn needed(&self) -> u32 {
+        20 * self.level as u32
+    }
+    /// Advances a level, making the unit stronger
+    pub fn level_up(&mut self, stats: &mut CombatStats, health: &mut Health) {
+        self.level += 1;
+        stats.attack = stats.attack.saturating_add(3);
+        stats.strength = stats.strength.saturating_add(1);
+        health.max = health.max.saturating_add(2);
+        health.hp = health.hp.saturating_add(2);
+    }
+}
+
+/// Shows the level of the fighter it is a child of
+#[derive(Debug, Clone, Copy, Component)]
+pub struct LevelBadge;
+
+pub fn experience_system(
+    mut damage_events: EventReader<DamageEvent>,
+    mut death_events: EventReader<DeathEvent>,
+    mut heal_events: EventReader<HealEvent>,
+    mut query: Query<(&mut Experience, &mut CombatStats, &mut Health)>,
+) {
+    let gains = damage_events.iter().map(|e| (e.attacker, e.amount as u32 * XP_PER_DAMAGE))
+        .chain(death_events.iter().map(|e| (e.killer, XP_PER_KILL)))
+        .chain(heal_events.iter().map(|e| (e.healer, e.amount as u32 * XP_PER_HEAL)));
+
+    for (ent, gain) in gains {
+        let Ok((mut experience, mut stats, mut health)) = query.get_mut(ent) else {
+            continue
+        };
+        experience.xp += gain;
+
+        while experience.xp >= experience.needed() {
+            experience.xp -= experience.needed();
+            experience.level_up(&mut stats, &mut health);
+        }
+    }
+}
+
+pub fn level_badge_system(
+    locale: Res<Locale>,
+    query: Query<(&Experience, &Children), Changed<Experience>>,
+    mut badge_query: Query<&mut Text, With<LevelBadge>>,
+) {
+    for (experience, children) in query.iter() {
+        for &child in children {
+            if let Ok(mut text) = badge_query.get_mut(child) {
+                text.sections[0].value = locale.format("experience.level", &[&experience.level]);
+            }
+        }
+    }
+}
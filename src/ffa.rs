@@ -0,0 +1,202 @@
+//! Free-for-all between three or four sides, the player against two or three AIs. Top and bottom deploy from
+//! strips along their edges of the battlefield, and every army marches on the centre, going after whichever enemy
+//! comes close. There's no passive income and nothing to siege: each side makes do with a bigger purse and the
+//! bounties it earns, and a side without an army for long enough is out. The last side left wins.
+
+use bevy::prelude::*;
+
+use crate::{
+    ai::spawn_banner,
+    economy::Economy,
+    locale::Locale,
+    orders::{FallingBack, Waypoint},
+    recall::Recalling,
+    status::StatusEffects,
+    stance::Stance,
+    terrain::River,
+    weather::Weather,
+    Battlefield, CombatTarget, GameState, MatchConfig, MatchOverEvent, Materials, MovementState, PerSide, Side, Skills,
+    Team, UnitType, AGGRO_RANGE,
+};
+
+/// How many times the map's starting money each side gets, as none comes in by itself
+const MONEY_FACTOR: u32 = 4;
+/// Seconds a side can go without an army before it's out, which is also how long it has to deploy one at the start
+const ELIMINATION_TIME: f32 = 10.;
+/// How close to the centre units gather while there's no enemy about
+const CENTRE_RADIUS: f32 = 40.;
+
+/// Which sides are out of the free-for-all, and for how long the rest have been without an army
+#[derive(Debug, Default, Clone, Copy, Resource)]
+pub struct FreeForAll {
+    pub out: PerSide<bool>,
+    without_army: PerSide<f32>,
+}
+
+/// Hands out the bigger purses and turns off interest, after the economy has been reset for the match
+pub fn start_free_for_all(config: Res<MatchConfig>, mut ffa: ResMut<FreeForAll>, mut economy: ResMut<Economy>) {
+    *ffa = FreeForAll::default();
+    if !config.free_for_all() {
+        return
+    }
+    for &side in config.sides() {
+        economy.money[side] *= MONEY_FACTOR;
+    }
+    economy.next_interest = None;
+}
+
+type FfaMovementItem<'a> = (
+    &'a mut Transform,
+    &'a Team,
+    &'a UnitType,
+    &'a CombatTarget,
+    &'a mut MovementState,
+    &'a StatusEffects,
+    &'a Stance,
+);
+
+/// Marches units on the centre of the battlefield, turning towards the closest enemy they'd fight once one is within
+/// aggro range. Takes the place of the usual march towards the far edge.
+pub fn ffa_movement_system(
+    fixed_time: Res<FixedTime>,
+    battlefield: Res<Battlefield>,
+    river: Res<River>,
+    weather: Res<Weather>,
+    mut query: Query<FfaMovementItem, (Without<Recalling>, Without<FallingBack>, Without<Waypoint>)>,
+) {
+    let delta = fixed_time.period.as_secs_f32();
+    let units: Vec<(Side, Skills, Vec2)> = query.iter()
+        .map(|(transform, &Team(side), unit, ..)| (side, unit.skills, transform.translation.truncate()))
+        .collect();
+
+    query.par_iter_mut().for_each_mut(|(mut transform, &Team(side), unit, target, mut movement, status, stance)| {
+        if target.0.is_some() || !stance.advances() || unit.skills.is_structure() {
+            return
+        }
+        let speed = 3. * unit.skills.speed as f32 * status.speed_factor() * weather.speed_factor() * delta;
+        let start = transform.translation.truncate();
+
+        let enemy = units.iter()
+            .filter(|&&(s, skills, p)| s != side && unit.skills.engages(&skills) && p.distance(start) < AGGRO_RANGE)
+            .map(|&(.., p)| p)
+            .min_by(|a, b| a.distance(start).total_cmp(&b.distance(start)));
+        let step = match enemy {
+            Some(enemy) => enemy - start,
+            None if start.length() > CENTRE_RADIUS => -start,
+            None => return,
+        }.clamp_length_max(speed);
+
+        let mut end = start + step;
+        // The river can only be crossed at a bridge
+        if let Some(bridge) = river.detour(start, end) {
+            end = Vec2::new(start.x, start.y + (bridge - start.y).clamp(-speed, speed));
+        }
+        let end = end.clamp(-battlefield.half_size, battlefield.half_size);
+        transform.translation = end.extend(transform.translation.z);
+
+        // Facing the way it's going along the x-axis
+        let turned = match (end.x - start.x) * side.direction() {
+            x if x < 0. => true,
+            x if x > 0. => false,
+            _ => movement.turned,
+        };
+        if movement.turned != turned {
+            movement.turned = turned;
+        }
+    })
+}
+
+/// Knocks out sides that have gone without an army for too long, and ends the match once only one is left
+#[allow(clippy::too_many_arguments)]
+pub fn elimination_system(
+    mut commands: Commands,
+    fixed_time: Res<FixedTime>,
+    config: Res<MatchConfig>,
+    materials: Res<Materials>,
+    locale: Res<Locale>,
+    mut ffa: ResMut<FreeForAll>,
+    query: Query<(&Team, &UnitType)>,
+    mut match_over: EventWriter<MatchOverEvent>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    // Already over, from an earlier tick this frame
+    if !config.free_for_all() || next_state.0.is_some() {
+        return
+    }
+    let delta = fixed_time.period.as_secs_f32();
+
+    // Structures can't win a free-for-all by themselves
+    let mut armies = PerSide::splat(false);
+    for (&Team(side), _) in query.iter().filter(|(_, unit)| !unit.skills.is_structure()) {
+        armies[side] = true;
+    }
+    let standing: Vec<Side> = config.sides().iter().copied().filter(|&side| !ffa.out[side]).collect();
+    let mut going = Vec::new();
+    for &side in &standing {
+        let without_army = &mut ffa.without_army[side];
+        *without_army = if armies[side] { 0. } else { *without_army + delta };
+        if *without_army >= ELIMINATION_TIME {
+            going.push(side);
+        }
+    }
+    // Someone has to win, so the last sides can't all go out together
+    if going.len() == standing.len() {
+        return
+    }
+    for &side in &going {
+        ffa.out[side] = true;
+        let text = locale.format("banner.out", &[&locale.name(&side.to_string())]);
+        spawn_banner(&mut commands, &materials, &text, materials.team[side], 3.);
+    }
+    let mut left = standing.into_iter().filter(|side| !going.contains(side));
+    if let (Some(winner), None) = (left.next(), left.next()) {
+        match_over.send(MatchOverEvent { winner });
+        next_state.set(GameState::Results);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cli::Args, match_stats::MatchStats, UnitName};
+
+    /// Wipes out the sides' armies and money and has them go without an army for long enough to be out
+    fn knock_out(app: &mut App, sides: &[Side]) {
+        let units: Vec<_> = app.world.query::<(Entity, &Team)>().iter(&app.world)
+            .filter(|(_, Team(side))| sides.contains(side))
+            .map(|(ent, _)| ent)
+            .collect();
+        for ent in units {
+            app.world.entity_mut(ent).despawn_recursive();
+        }
+        for &side in sides {
+            app.world.resource_mut::<Economy>().money[side] = 0;
+            app.world.resource_mut::<FreeForAll>().without_army[side] = ELIMINATION_TIME;
+        }
+        for _ in 0..3 {
+            app.update();
+        }
+    }
+
+    #[test]
+    fn last_side_standing_wins() {
+        let mut app = crate::app(Args::parse_from(["--headless", "--sides", "4", "--seed", "3"].map(String::from)));
+        for _ in 0..300 {
+            app.update();
+        }
+        let mut deployed = PerSide::splat(false);
+        for name in app.world.query::<&UnitName>().iter(&app.world) {
+            deployed[name.side] = true;
+        }
+        assert_eq!(deployed, PerSide::splat(true));
+
+        knock_out(&mut app, &[Side::Right, Side::Top]);
+        let out = app.world.resource::<FreeForAll>().out;
+        assert_eq!(out, PerSide { left: false, right: true, top: true, bottom: false });
+        assert_eq!(app.world.resource::<State<GameState>>().0, GameState::Playing);
+
+        knock_out(&mut app, &[Side::Bottom]);
+        assert_ne!(app.world.resource::<State<GameState>>().0, GameState::Playing);
+        assert_eq!(app.world.resource::<MatchStats>().winner(), Some(Side::Left));
+    }
+}
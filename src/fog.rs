@@ -0,0 +1,63 @@
+use bevy::prelude::*;
+
+use crate::{mutators::Mutator, Battlefield, ControlOptions, MatchConfig, Team, UnitType};
+
+/// How far into the fog the player's units can see
+const SIGHT_RANGE: f32 = 200.;
+
+/// Overlay covering the enemy's half of the battlefield
+#[derive(Debug, Clone, Copy, Component)]
+pub struct Fog;
+
+/// Hides enemy units the player's army can't see, for matches against the AI played in fog
+pub fn fog_system(
+    mut commands: Commands,
+    battlefield: Res<Battlefield>,
+    config: Res<MatchConfig>,
+    options: Res<ControlOptions>,
+    mut fog_query: Query<(&mut Transform, &mut Sprite), With<Fog>>,
+    mut query: Query<(&Transform, &Team, &mut Visibility), (With<UnitType>, Without<Fog>)>,
+) {
+    if !config.versus_ai || config.spectating || !config.mutators.contains(Mutator::FogOfWar) {
+        return
+    }
+    let player = options.player_side;
+
+    let transform = Transform::from_xyz(-player.direction() * battlefield.width() / 4., 0., 4.);
+    let size = Some(Vec2::new(battlefield.width() / 2., battlefield.height()));
+    if let Ok((mut fog_transform, mut sprite)) = fog_query.get_single_mut() {
+        *fog_transform = transform;
+        sprite.custom_size = size;
+    } else {
+        commands.spawn(SpriteBundle {
+            transform,
+            sprite: Sprite {
+                color: Color::rgba(0.1, 0.1, 0.15, 0.5),
+                custom_size: size,
+                ..default()
+            },
+            ..default()
+        }).insert(Fog);
+    }
+
+    let friendly: Vec<_> = query.iter()
+        .filter(|(_, &Team(side), _)| side == player)
+        .map(|(t, ..)| t.translation.truncate())
+        .collect();
+    for (transform, &Team(side), mut visibility) in query.iter_mut() {
+        if side == player {
+            continue
+        }
+        let pos = transform.translation.truncate();
+        // The player's half is behind midfield from where their army marches
+        let crossed = pos.x * player.direction() < 0.;
+        let seen = crossed || friendly.iter().any(|p| p.distance(pos) < SIGHT_RANGE);
+        *visibility = if seen { Visibility::Inherited } else { Visibility::Hidden };
+    }
+}
+
+pub fn cleanup_fog(mut commands: Commands, query: Query<Entity, With<Fog>>) {
+    for ent in query.iter() {
+        commands.entity(ent).despawn();
+    }
+}
@@ -0,0 +1,66 @@
+use bevy::prelude::*;
+
+use crate::{
+    bindings::Bindings, economy::{Economy, Prices}, lanes::Lanes, supply::Supply, tech::Tech, zone::SpawnZone,
+    fighter_sprite_bundle, ClickAction, ControlOptions, MatchConfig, Materials, MouseLoc, SelectedUnit, Side,
+};
+
+const GHOST_COLOR: Color = Color::rgba(1., 1., 1., 0.4);
+const UNAFFORDABLE_COLOR: Color = Color::rgba(1., 0.2, 0.2, 0.4);
+
+/// Translucent preview of the selected unit where it would be deployed
+#[derive(Debug, Clone, Copy, Component)]
+pub struct PlacementGhost;
+
+pub fn placement_ghost_system(
+    mut commands: Commands,
+    mouse_loc: Res<MouseLoc>,
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    options: Res<ControlOptions>,
+    config: Res<MatchConfig>,
+    materials: Res<Materials>,
+    zone: Res<SpawnZone>,
+    lanes: Res<Lanes>,
+    economy: Res<Economy>,
+    prices: Res<Prices>,
+    tech: Res<Tech>,
+    supply: Res<Supply>,
+    selected: Res<SelectedUnit>,
+    mut query: Query<(&mut Transform, &mut TextureAtlasSprite, &mut Handle<TextureAtlas>, &mut Visibility), With<PlacementGhost>>,
+) {
+    // Whoever's zone the cursor is in would deploy, unless the AI is deploying for them
+    let side = zone.side_at(mouse_loc.0)
+        .filter(|&side| !config.versus_ai || side == options.player_side)
+        .filter(|_| matches!(ClickAction::held(&keyboard_input, &bindings), ClickAction::Deploy));
+
+    let Ok((mut transform, mut sprite, mut sheet, mut visibility)) = query.get_single_mut() else {
+        let mut bundle = fighter_sprite_bundle(0., 0., config.mutators.unit_size(), Side::Left, &selected.skills(), &materials);
+        bundle.visibility = Visibility::Hidden;
+        commands.spawn(bundle).insert(PlacementGhost);
+        return
+    };
+    let Some(side) = side else {
+        *visibility = Visibility::Hidden;
+        return
+    };
+    *visibility = Visibility::Inherited;
+
+    let skills = selected.skills();
+    let position = Vec2::new(mouse_loc.0.x, lanes.clamp(lanes.lane_of(mouse_loc.0.y), mouse_loc.0.y));
+    let bundle = fighter_sprite_bundle(position.x, position.y, config.mutators.unit_size(), side, &skills, &materials);
+    *transform = bundle.transform;
+    *sheet = bundle.texture_atlas;
+    transform.translation.z = 0.5;
+
+    let deployable = config.mutators.allows(&skills) && tech.unlocked(side, &skills) && supply.left(side) > 0
+        && economy.money[side] >= prices.price(side, &skills);
+    sprite.color = if deployable { GHOST_COLOR } else { UNAFFORDABLE_COLOR };
+    sprite.custom_size = bundle.sprite.custom_size;
+}
+
+pub fn cleanup_ghost(mut commands: Commands, query: Query<Entity, With<PlacementGhost>>) {
+    for ent in query.iter() {
+        commands.entity(ent).despawn();
+    }
+}
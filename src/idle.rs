@@ -0,0 +1,116 @@
+use bevy::{
+    prelude::*,
+    input::mouse::MouseMotion,
+};
+
+use crate::{economy::Economy, locale::Locale, ControlOptions, MatchConfig, Materials};
+
+/// Money the player has to leave unspent before being idle counts, so someone watching their army fight isn't paused
+const UNSPENT_MONEY: u32 = 30;
+
+/// Pauses matches against the AI when the player hasn't touched any input for a while and their money piles up
+#[derive(Debug, Clone, Resource)]
+pub struct IdleDetection {
+    /// Seconds without input before the game auto-pauses, set in the settings
+    timeout: f32,
+    idle_for: f32,
+    /// Whether the game is paused because of this, rather than by the player
+    paused: bool,
+}
+
+impl IdleDetection {
+    pub fn new(timeout: f32) -> Self {
+        IdleDetection {
+            timeout,
+            idle_for: 0.,
+            paused: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct IdlePrompt;
+
+/// Runs before the speed controls, so input that pauses or steps the game again isn't undone by resuming from
+/// an idle pause
+pub fn idle_detection_system(
+    mut time: ResMut<Time>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_button: Res<Input<MouseButton>>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    config: Res<MatchConfig>,
+    options: Res<ControlOptions>,
+    economy: Res<Economy>,
+    mut idle: ResMut<IdleDetection>,
+) {
+    let active = keyboard_input.get_just_pressed().next().is_some()
+        || mouse_button.get_just_pressed().next().is_some()
+        || mouse_motion.iter().count() > 0;
+
+    // Resumed some other way, like the pause button or a new match, so it's no longer ours to lift
+    if idle.paused && !time.is_paused() {
+        idle.paused = false;
+    }
+    if active {
+        idle.idle_for = 0.;
+        if idle.paused {
+            idle.paused = false;
+            time.unpause();
+        }
+        return
+    }
+
+    // Real time, since game time stands still while paused
+    idle.idle_for += time.raw_delta_seconds();
+    let piling_up = config.versus_ai && economy.money[options.player_side] >= UNSPENT_MONEY;
+    if !idle.paused && !time.is_paused() && piling_up && idle.idle_for >= idle.timeout {
+        idle.paused = true;
+        time.pause();
+    }
+}
+
+/// Dims the screen and asks for the player while auto-paused
+pub fn idle_prompt_system(
+    mut commands: Commands,
+    materials: Res<Materials>,
+    locale: Res<Locale>,
+    idle: Res<IdleDetection>,
+    prompt_query: Query<Entity, With<IdlePrompt>>,
+) {
+    if !idle.is_changed() {
+        return
+    }
+    if !idle.paused {
+        for ent in prompt_query.iter() {
+            commands.entity(ent).despawn_recursive();
+        }
+        return
+    }
+    if !prompt_query.is_empty() {
+        return
+    }
+
+    commands.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        background_color: Color::rgba(0., 0., 0., 0.6).into(),
+        z_index: ZIndex::Global(10),
+        ..default()
+    })
+    .insert(IdlePrompt)
+    .with_children(|parent| {
+        parent.spawn(TextBundle::from_section(
+            locale.text("idle.prompt"),
+            TextStyle {
+                font: materials.font.clone(),
+                font_size: 40.,
+                color: Color::WHITE,
+            },
+        ));
+    });
+}
@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+
+use crate::{combat::DeathEvent, locale::Locale, sound::SiegeEvent, Materials, UnitName};
+
+/// How long an entry stays in the feed
+const ENTRY_TIME: f32 = 5.;
+/// Seconds at the end of its time over which an entry fades out
+const FADE_TIME: f32 = 1.;
+const MAX_ENTRIES: usize = 6;
+
+/// Column in the top right corner listing recent kills and sieges
+#[derive(Debug, Clone, Copy, Component)]
+pub struct KillFeed;
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct FeedEntry {
+    age: f32,
+}
+
+pub fn setup_kill_feed(mut commands: Commands) {
+    commands.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(30.),
+                right: Val::Px(5.),
+                ..default()
+            },
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::FlexEnd,
+            ..default()
+        },
+        ..default()
+    }).insert(KillFeed);
+}
+
+/// Adds an entry for each death and siege, dropping the oldest entries once the feed is full
+pub fn kill_feed_system(
+    mut commands: Commands,
+    materials: Res<Materials>,
+    locale: Res<Locale>,
+    mut death_events: EventReader<DeathEvent>,
+    mut siege_events: EventReader<SiegeEvent>,
+    name_query: Query<&UnitName>,
+    feed_query: Query<(Entity, Option<&Children>), With<KillFeed>>,
+) {
+    let deaths = death_events.iter().filter_map(|event| {
+        let victim = name_query.get(event.entity).ok()?;
+        Some(match event.killer_name {
+            Some(killer) => locale.format("feed.slew", &[&killer.localized(&locale), &victim.localized(&locale)]),
+            None => locale.format("feed.died", &[&victim.localized(&locale)]),
+        })
+    });
+    let sieges = siege_events.iter().map(|event| locale.format("feed.sieged", &[&event.name.localized(&locale)]));
+    let lines: Vec<String> = deaths.chain(sieges).collect();
+    if lines.is_empty() {
+        return
+    }
+    let Ok((feed, entries)) = feed_query.get_single() else {
+        return
+    };
+
+    let entries = entries.map_or(&[][..], |c| &c[..]);
+    let overflow = (entries.len() + lines.len()).saturating_sub(MAX_ENTRIES);
+    for &ent in entries.iter().take(overflow) {
+        commands.entity(ent).despawn_recursive();
+    }
+    commands.entity(feed).with_children(|feed| {
+        for line in lines.into_iter().rev().take(MAX_ENTRIES).rev() {
+            feed.spawn(TextBundle {
+                text: Text::from_section(line, TextStyle {
+                    font: materials.font.clone(),
+                    font_size: 14.,
+                    color: Color::WHITE,
+                }),
+                background_color: Color::rgba(0., 0., 0., 0.4).into(),
+                ..default()
+            }).insert(FeedEntry { age: 0. });
+        }
+    });
+}
+
+pub fn feed_entry_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut FeedEntry, &mut Text, &mut BackgroundColor)>,
+) {
+    for (ent, mut entry, mut text, mut background) in query.iter_mut() {
+        entry.age += time.delta_seconds();
+        if entry.age >= ENTRY_TIME {
+            commands.entity(ent).despawn_recursive();
+            continue
+        }
+        let fade = ((ENTRY_TIME - entry.age) / FADE_TIME).min(1.);
+        text.sections[0].style.color.set_a(fade);
+        background.0.set_a(0.4 * fade);
+    }
+}
+
+pub fn cleanup_kill_feed(mut commands: Commands, query: Query<Entity, With<FeedEntry>>) {
+    for ent in query.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+}
@@ -0,0 +1,66 @@
+use bevy::prelude::*;
+
+use crate::{ai::Difficulty, settings::{read_config, write_config}, ControlOptions, MatchConfig, MatchOverEvent};
+
+const LADDER_FILE: &str = "ladder.txt";
+const STARTING_RATING: i32 = 1000;
+/// How far a single match can move the rating
+const K_FACTOR: f32 = 32.;
+
+/// Local Elo rating of the player against the AI
+#[derive(Debug, Clone, Resource)]
+pub struct Ladder {
+    /// Rating after each ranked match, oldest first
+    pub history: Vec<i32>,
+    /// Outcome and rating change of the most recent match this session
+    pub last_result: Option<(bool, i32)>,
+}
+
+impl Default for Ladder {
+    fn default() -> Self {
+        let history = read_config(LADDER_FILE)
+            .map(|s| s.lines().filter_map(|l| l.trim().parse().ok()).collect())
+            .unwrap_or_default();
+        Ladder {
+            history,
+            last_result: None,
+        }
+    }
+}
+
+impl Ladder {
+    pub fn rating(&self) -> i32 {
+        self.history.last().copied().unwrap_or(STARTING_RATING)
+    }
+    fn record(&mut self, opponent: Difficulty, won: bool) {
+        let rating = self.rating();
+        let expected = 1. / (1. + 10f32.powf((opponent.rating() - rating as f32) / 400.));
+        let outcome = if won { 1. } else { 0. };
+        let change = (K_FACTOR * (outcome - expected)).round() as i32;
+
+        self.history.push(rating + change);
+        self.last_result = Some((won, change));
+    }
+    fn save(&self) {
+        let contents: String = self.history.iter().map(|r| format!("{r}\n")).collect();
+        if let Err(e) = write_config(LADDER_FILE, contents) {
+            error!("Could not save ladder: {e}");
+        }
+    }
+}
+
+pub fn ladder_system(
+    mut events: EventReader<MatchOverEvent>,
+    config: Res<MatchConfig>,
+    options: Res<ControlOptions>,
+    mut ladder: ResMut<Ladder>,
+) {
+    for event in events.iter() {
+        // Hot-seat and spectated matches are unranked
+        let Some(difficulty) = config.ai().filter(|_| !config.spectating) else {
+            continue
+        };
+        ladder.record(difficulty, event.winner == options.player_side);
+        ladder.save();
+    }
+}
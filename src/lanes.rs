@@ -0,0 +1,95 @@
+use bevy::prelude::*;
+
+use crate::{maps::Maps, mutators::Mutator, Battlefield, MatchConfig};
+
+/// How many lanes the lanes rule splits a map without lanes of its own into
+const LANE_COUNT: usize = 3;
+/// Keeps units far enough from the separators not to touch units in the next lane
+const LANE_MARGIN: f32 = 18.;
+
+/// The horizontal lanes units are kept in, if the battlefield is split up
+#[derive(Debug, Default, Clone, Copy, Resource)]
+pub struct Lanes {
+    /// Zero when the battlefield isn't split up
+    count: usize,
+    bottom: f32,
+    height: f32,
+}
+
+impl Lanes {
+    pub fn enabled(&self) -> bool {
+        self.count > 0
+    }
+    /// The lane `y` lies in
+    pub fn lane_of(&self, y: f32) -> usize {
+        (((y - self.bottom) / self.height).floor().max(0.) as usize).min(self.count.saturating_sub(1))
+    }
+    /// Keeps `y` inside the given lane, if lanes are enabled
+    pub fn clamp(&self, lane: usize, y: f32) -> f32 {
+        if !self.enabled() {
+            return y
+        }
+        let min = self.bottom + lane as f32 * self.height;
+        y.clamp(min + LANE_MARGIN, min + self.height - LANE_MARGIN)
+    }
+}
+
+/// Line between two lanes
+#[derive(Debug, Clone, Copy, Component)]
+pub struct LaneSeparator(usize);
+
+/// Keeps the lanes fitted to the window, for maps with lanes and matches played with the lanes rule
+pub fn lane_layout_system(
+    mut commands: Commands,
+    battlefield: Res<Battlefield>,
+    config: Res<MatchConfig>,
+    maps: Res<Maps>,
+    mut lanes: ResMut<Lanes>,
+    mut separators: Query<(&LaneSeparator, &mut Transform, &mut Sprite)>,
+) {
+    let count = match maps.0[config.map].lanes {
+        0 if config.mutators.contains(Mutator::Lanes) => LANE_COUNT,
+        0 => return,
+        count => count,
+    };
+    let new = Lanes {
+        count,
+        bottom: -battlefield.half_size.y,
+        height: battlefield.height() / count as f32,
+    };
+    if new.count == lanes.count && new.bottom == lanes.bottom && new.height == lanes.height {
+        return
+    }
+    *lanes = new;
+
+    let separator_at = |i: usize| Transform::from_translation(Vec3::new(0., new.bottom + i as f32 * new.height, -0.9));
+    let size = Some(Vec2::new(battlefield.width(), 2.));
+    if separators.is_empty() {
+        for i in 1..count {
+            commands.spawn(SpriteBundle {
+                transform: separator_at(i),
+                sprite: Sprite {
+                    color: Color::rgba(1., 1., 1., 0.2),
+                    custom_size: size,
+                    ..default()
+                },
+                ..default()
+            }).insert(LaneSeparator(i));
+        }
+    }
+    for (&LaneSeparator(i), mut transform, mut sprite) in separators.iter_mut() {
+        *transform = separator_at(i);
+        sprite.custom_size = size;
+    }
+}
+
+pub fn cleanup_lanes(
+    mut commands: Commands,
+    mut lanes: ResMut<Lanes>,
+    query: Query<Entity, With<LaneSeparator>>,
+) {
+    *lanes = Lanes::default();
+    for ent in query.iter() {
+        commands.entity(ent).despawn();
+    }
+}
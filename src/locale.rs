@@ -0,0 +1,140 @@
+//! Text shown to the player, in the language picked in the settings. Each language has a file
+//! in `assets/locale` with one text per line, its key and the text separated by a tab.
+//! `{}` in a text is filled in with the values given, in order, and `\n` starts a new line.
+//!
+//! Names the game keeps in English, like those of units, difficulties and rules, are translated
+//! under `name.` followed by the English name, so `name.Fighter`. Anything missing from a
+//! language is shown in English.
+
+use std::{collections::HashMap, fmt::Display};
+
+use bevy::prelude::*;
+
+use crate::settings::Settings;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Language {
+    #[default]
+    English,
+    Danish,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::English, Language::Danish];
+
+    /// What the language calls itself, which is also how the settings file refers to it
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::Danish => "Dansk",
+        }
+    }
+    fn source(self) -> &'static str {
+        match self {
+            Language::English => include_str!("../assets/locale/en.txt"),
+            Language::Danish => include_str!("../assets/locale/da.txt"),
+        }
+    }
+}
+
+fn parse(source: &str) -> HashMap<String, String> {
+    source.lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(key, text)| (key.to_owned(), text.replace("\\n", "\n")))
+        .collect()
+}
+
+#[derive(Debug, Clone, Resource)]
+pub struct Locale {
+    language: Language,
+    texts: HashMap<String, String>,
+    english: HashMap<String, String>,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale::new(Language::default())
+    }
+}
+
+impl Locale {
+    pub fn new(language: Language) -> Self {
+        Locale {
+            language,
+            texts: parse(language.source()),
+            english: parse(Language::English.source()),
+        }
+    }
+    /// The text for `key`, or the key itself if not even English has it
+    pub fn text<'a>(&'a self, key: &'a str) -> &'a str {
+        self.texts.get(key).or_else(|| self.english.get(key)).map_or(key, String::as_str)
+    }
+    /// The text for `key` with each `{}` replaced by the next of `args`
+    pub fn format(&self, key: &str, args: &[&dyn Display]) -> String {
+        let mut args = args.iter();
+        let mut pieces = self.text(key).split("{}");
+        let mut result = pieces.next().unwrap_or_default().to_owned();
+        for piece in pieces {
+            if let Some(arg) = args.next() {
+                result += &arg.to_string();
+            }
+            result += piece;
+        }
+        result
+    }
+    /// `name` in the current language, for the names the game keeps in English
+    pub fn name<'a>(&'a self, name: &'a str) -> &'a str {
+        self.texts.get(&format!("name.{name}")).map_or(name, String::as_str)
+    }
+}
+
+/// Switches language when it's changed in the settings
+pub fn locale_system(settings: Res<Settings>, mut locale: ResMut<Locale>) {
+    if settings.is_changed() && settings.language != locale.language {
+        *locale = Locale::new(settings.language);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_lines() {
+        let source = "# Comment\nmenu.title\tSidewars\n\nsettings.controls\tUp/Down: pick\\nEsc: back\nbroken line\n";
+        let texts = parse(source);
+        assert_eq!(texts.len(), 2);
+        assert_eq!(texts["menu.title"], "Sidewars");
+        assert_eq!(texts["settings.controls"], "Up/Down: pick\nEsc: back");
+    }
+
+    #[test]
+    fn formats_in_order() {
+        let locale = Locale {
+            language: Language::English,
+            texts: parse("score\t{} to {}"),
+            english: HashMap::new(),
+        };
+        assert_eq!(locale.format("score", &[&3, &"two"]), "3 to two");
+        assert_eq!(locale.format("score", &[&3]), "3 to ");
+        assert_eq!(locale.text("missing"), "missing");
+    }
+
+    /// Every translation fills in as many values as the English text, so none go missing or repeat
+    #[test]
+    fn translations_match_english() {
+        let english = parse(Language::English.source());
+        for language in Language::ALL {
+            for (key, text) in parse(language.source()) {
+                if key.starts_with("name.") {
+                    continue
+                }
+                let Some(original) = english.get(&key) else {
+                    panic!("{} has {key}, which English doesn't", language.name())
+                };
+                assert_eq!(text.matches("{}").count(), original.matches("{}").count(), "{} {key}", language.name());
+            }
+        }
+    }
+}
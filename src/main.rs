@@ -1,54 +1,756 @@
-use std::collections::HashMap;
-use self_compare::SliceCompareExt;
+// Bevy systems take their data as arguments, so these lints fire on idiomatic code
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
 
-use rand::Rng;
+use std::{fmt, ops::{Index, IndexMut}, time::Duration};
+
+mod ai;
+mod ai_strategy;
+mod airdrop;
+mod animation;
+mod army_value;
+mod aura;
+mod balance;
+mod bindings;
+#[cfg(feature = "bot_api")]
+mod bot_api;
+mod camera;
+mod campaign;
+mod cli;
+mod combat;
+mod corpse;
+mod cover;
+mod day_night;
+mod draft;
+#[cfg(feature = "debug_overlay")]
+mod debug;
+mod economy;
+mod editor;
+mod exhibition;
+mod experience;
+mod ffa;
+mod fog;
+mod ghost;
+mod idle;
+mod kill_feed;
+mod ladder;
+mod maps;
+mod match_rng;
+mod match_stats;
+mod lanes;
+mod locale;
+mod menu;
+mod minimap;
+mod mercenary;
+mod militia;
+mod music;
+mod mutators;
+#[cfg(feature = "online")]
+mod online;
+mod orders;
+mod palette;
+mod particles;
+mod recall;
+mod reload;
+#[cfg(feature = "scripting")]
+mod scripting;
+mod settings;
+mod sound;
+mod spectator;
+mod speed;
+mod spells;
+mod stance;
+mod stats_overlay;
+mod status;
+mod supply;
+mod tech;
+mod terrain;
+mod toolbar;
+mod tooltip;
+mod tower;
+mod traps;
+#[cfg(feature = "twitch")]
+mod twitch;
+mod unit_stats;
+mod verbose_log;
+mod weather;
+mod zone;
+
+use combat::{DamageEvent, DeathEvent, HealEvent, MissEvent};
 
 use bevy::{
     prelude::*,
     render::camera::Camera,
-    sprite::collide_aabb::{collide, Collision},
-    app::AppExit, window::PrimaryWindow,
+    app::{AppExit, ScheduleRunnerPlugin, ScheduleRunnerSettings},
+    window::{ExitCondition, PrimaryWindow},
+    diagnostic::FrameTimeDiagnosticsPlugin,
+    log::{self, LogPlugin},
+    render::{settings::WgpuSettings, RenderPlugin},
+    time::TimeSystem,
+    winit::WinitPlugin,
 };
 
 pub fn exit_on_esc_system(
     keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<bindings::Bindings>,
     mut exit: EventWriter<AppExit>,
 ) {
-    if keyboard_input.pressed(KeyCode::LShift) && keyboard_input.just_pressed(KeyCode::Escape) {
+    if keyboard_input.pressed(bindings.exit_modifier) && keyboard_input.just_pressed(bindings.exit) {
         exit.send(AppExit);
     }
 }
 
 fn main() {
-    App::new()
-        .insert_resource(ClearColor(Color::rgb(0.24, 0.5, 0.01)))
-        .insert_resource(MouseLoc(Default::default()))
-        .add_plugins(DefaultPlugins.set(WindowPlugin {
+    app(cli::Args::parse()).run();
+}
+
+/// The whole game, set up as `args` asks
+fn app(args: cli::Args) -> App {
+    let headless = args.headless;
+    let exhibition = args.exhibition;
+    let mut app = App::new();
+    let plugins = DefaultPlugins.set(LogPlugin {
+        level: args.log_level.unwrap_or(log::Level::INFO),
+        filter: "wgpu=error".to_owned(),
+    });
+    // Logging starts with the plugins, so the files that can have problems are loaded after them
+    if headless {
+        // Nothing is rendered without a window, and the app runs on a loop of its own instead of the window's events
+        app
+            .add_plugins(plugins.set(WindowPlugin {
+                primary_window: None,
+                exit_condition: ExitCondition::DontExit,
+                close_when_requested: false,
+            }).set(RenderPlugin {
+                wgpu_settings: WgpuSettings {
+                    backends: None,
+                    .. default()
+                },
+            }).disable::<WinitPlugin>())
+            .insert_resource(ScheduleRunnerSettings::run_loop(Duration::ZERO))
+            .add_plugin(ScheduleRunnerPlugin);
+    } else {
+        app.add_plugins(plugins.set(WindowPlugin {
             primary_window: Some(Window {
                 title: "Sidewars".to_owned(),
+                // On the web, the canvas follows the size of the page, see web/index.html
+                fit_canvas_to_parent: true,
                 .. default()
             }),
             .. default()
-        }))
+        }));
+    }
+    for problem in &args.problems {
+        warn!("{problem}");
+    }
+    let mut settings = settings::Settings::load();
+    settings.window_size = args.window_size;
+    settings.difficulty_override = args.difficulty;
+    app
+        .insert_resource(ClearColor(Color::rgb(0.24, 0.5, 0.01)))
+        .init_resource::<day_night::Clock>()
+        .insert_resource(MouseLoc(Default::default()))
+        .insert_resource(bindings::Bindings::load())
+        .insert_resource(locale::Locale::new(settings.language))
+        .insert_resource(settings)
+        .insert_resource(maps::Maps::load())
+        .insert_resource(balance::Balance::load())
+        .init_resource::<ControlOptions>()
+        .insert_resource(idle::IdleDetection::new(settings.idle_timeout))
+        .add_plugin(FrameTimeDiagnosticsPlugin)
+        .insert_resource(match_rng::MatchRng::new(args.seed))
+        .insert_resource(args)
+        .init_resource::<verbose_log::VerboseLog>()
         .init_resource::<Materials>()
+        .init_resource::<MatchConfig>()
+        .init_resource::<Battlefield>()
+        .init_resource::<UnitCounter>()
+        .init_resource::<match_stats::MatchStats>()
+        .init_resource::<SelectedUnit>()
+        .init_resource::<ai::AiPlans>()
+        .init_resource::<ai_strategy::AiPlayers>()
+        .init_resource::<economy::Prices>()
+        .init_resource::<stance::DefaultStance>()
+        .init_resource::<orders::ArmyOrders>()
+        .init_resource::<orders::RallyPoints>()
+        .init_resource::<spells::Mana>()
+        .init_resource::<militia::MilitiaCalled>()
+        .init_resource::<economy::Economy>()
+        .init_resource::<zone::SpawnZone>()
+        .init_resource::<ladder::Ladder>()
+        .init_resource::<campaign::Campaign>()
+        .init_resource::<draft::Draft>()
+        .init_resource::<ffa::FreeForAll>()
+        .init_resource::<Stalled>()
+        .init_resource::<unit_stats::UnitStats>()
+        .init_resource::<unit_stats::Deployments>()
+        .init_resource::<camera::CameraFocus>()
+        .init_resource::<lanes::Lanes>()
+        .init_resource::<terrain::HighGround>()
+        .init_resource::<terrain::River>()
+        .init_resource::<weather::Weather>()
+        .init_resource::<tech::Tech>()
+        .init_resource::<supply::Supply>()
+        .init_resource::<mercenary::MercenaryOffer>()
+        .init_resource::<editor::Editor>()
+        .init_resource::<maps::MapWatcher>()
+        .add_state::<GameState>()
+        .add_event::<DamageEvent>()
+        .add_event::<DeathEvent>()
+        .add_event::<HealEvent>()
+        .add_event::<MissEvent>()
+        .add_event::<MatchOverEvent>()
+        .add_event::<SpawnOrder>()
+        .add_event::<sound::SiegeEvent>()
         .add_startup_system(setup)
-        .add_system(collision_system)
-        .add_system(fighter_movement)
-        .add_system(figter_siege)
+        .add_startup_system(zone::setup_zones)
+        .add_startup_system(army_value::setup_army_value_bar)
+        .add_startup_system(toolbar::setup_toolbar)
+        .add_startup_system(aura::setup_aura_assets)
+        .add_startup_system(spells::setup_spells)
+        .add_startup_system(terrain::setup_terrain_assets)
+        .add_startup_system(tooltip::setup_tooltip)
+        .add_startup_system(kill_feed::setup_kill_feed)
+        .add_startup_system(stats_overlay::setup_stats_overlay)
+        .add_startup_system(sound::setup_sounds)
+        .add_startup_system(music::setup_music)
+        .add_system(music::intensity_system)
+        .add_system(music::crossfade_system.after(music::intensity_system))
+        .add_system(menu::setup_menu.in_schedule(OnEnter(GameState::Menu)))
+        .add_system(menu::menu_input_system.in_set(OnUpdate(GameState::Menu)))
+        .add_startup_system(cli::select_map)
+        .add_system(cli::launch_system.in_set(OnUpdate(GameState::Menu)))
+        .add_system(menu::cleanup_menu.in_schedule(OnExit(GameState::Menu)))
+        .add_system(editor::setup_editor.in_schedule(OnEnter(GameState::Editor)))
+        .add_system(editor::editor_input_system.in_set(OnUpdate(GameState::Editor)))
+        .add_system(editor::editor_draw_system.after(editor::editor_input_system).in_set(OnUpdate(GameState::Editor)))
+        .add_system(editor::cleanup_editor.in_schedule(OnExit(GameState::Editor)))
+        .add_system(terrain::cleanup_map.in_schedule(OnExit(GameState::Editor)))
+        .add_system(settings::setup_settings.in_schedule(OnEnter(GameState::Settings)))
+        .add_system(settings::settings_input_system.in_set(OnUpdate(GameState::Settings)))
+        .add_system(settings::cleanup_settings.in_schedule(OnExit(GameState::Settings)))
+        .add_system(match_stats::reset_match_stats.in_schedule(OnEnter(GameState::Playing)))
+        .add_system(match_rng::reset_match_rng.in_schedule(OnEnter(GameState::Playing)))
+        .add_system(match_stats::finish_match_stats.in_schedule(OnExit(GameState::Playing)))
+        .add_system(match_stats::setup_results.in_schedule(OnEnter(GameState::Results)))
+        .add_system(match_stats::results_input_system.in_set(OnUpdate(GameState::Results)))
+        .add_system(match_stats::cleanup_results.in_schedule(OnExit(GameState::Results)))
+        .add_system(campaign::setup_camp.in_schedule(OnEnter(GameState::Camp)))
+        .add_system(campaign::camp_input_system.in_set(OnUpdate(GameState::Camp)))
+        .add_system(campaign::cleanup_camp.in_schedule(OnExit(GameState::Camp)))
+        .add_system(draft::setup_draft.in_schedule(OnEnter(GameState::Draft)))
+        .add_system(draft::draft_input_system.in_set(OnUpdate(GameState::Draft)))
+        .add_system(draft::cleanup_draft.in_schedule(OnExit(GameState::Draft)))
+        .add_system(settings::display_hotkey_system.before(settings::apply_settings_system))
+        .add_system(settings::apply_settings_system)
+        .add_system(locale::locale_system.after(settings::apply_settings_system))
+        .add_system(start_match.in_schedule(OnEnter(GameState::Playing)))
+        .add_system(economy::reset_economy.in_schedule(OnEnter(GameState::Playing)))
+        .add_system(economy::reset_prices.in_schedule(OnEnter(GameState::Playing)))
+        .add_system(ai::reset_plans.in_schedule(OnEnter(GameState::Playing)))
+        .add_system(ai::reset_players.in_schedule(OnEnter(GameState::Playing)))
+        .add_system(stance::reset_stances.in_schedule(OnEnter(GameState::Playing)))
+        .add_system(orders::reset_orders.in_schedule(OnEnter(GameState::Playing)))
+        .add_system(spells::reset_mana.in_schedule(OnEnter(GameState::Playing)))
+        .add_system(militia::reset_militia.in_schedule(OnEnter(GameState::Playing)))
+        .add_system(unit_stats::reset_deployments.in_schedule(OnEnter(GameState::Playing)))
+        .add_system(terrain::spawn_map.in_schedule(OnEnter(GameState::Playing)))
+        .add_system(weather::reset_weather.after(match_rng::reset_match_rng).in_schedule(OnEnter(GameState::Playing)))
+        .add_system(tech::reset_tech.in_schedule(OnEnter(GameState::Playing)))
+        .add_system(mercenary::reset_offers.after(match_rng::reset_match_rng).in_schedule(OnEnter(GameState::Playing)))
+        .add_system(campaign::campaign_start_system
+            .after(start_match).after(economy::reset_economy).after(tech::reset_tech).after(stance::reset_stances)
+            .in_schedule(OnEnter(GameState::Playing)))
+        .add_system(draft::round_start_system.after(start_match).after(stance::reset_stances)
+            .in_schedule(OnEnter(GameState::Playing)))
+        .add_system(ffa::start_free_for_all.after(economy::reset_economy).in_schedule(OnEnter(GameState::Playing)))
+        .add_system(cleanup_battlefield.in_schedule(OnExit(GameState::Playing)))
+        .add_system(airdrop::cleanup_airdrops.in_schedule(OnExit(GameState::Playing)))
+        .add_system(spells::cleanup_spells.in_schedule(OnExit(GameState::Playing)))
+        .add_system(traps::cleanup_traps.in_schedule(OnExit(GameState::Playing)))
+        .add_system(orders::cleanup_rally_flags.in_schedule(OnExit(GameState::Playing)))
+        .add_system(terrain::cleanup_map.in_schedule(OnExit(GameState::Playing)))
+        .add_system(terrain::refit_map_system.in_set(OnUpdate(GameState::Playing)))
+        .add_system(day_night::reset_clock.in_schedule(OnExit(GameState::Playing)))
+        .add_system(lanes::cleanup_lanes.in_schedule(OnExit(GameState::Playing)))
+        .add_system(weather::cleanup_weather.in_schedule(OnExit(GameState::Playing)))
+        .add_system(fog::cleanup_fog.in_schedule(OnExit(GameState::Playing)))
+        .add_system(mercenary::cleanup_offers.in_schedule(OnExit(GameState::Playing)))
+        .add_system(ghost::cleanup_ghost.in_schedule(OnExit(GameState::Playing)))
+        .add_system(kill_feed::cleanup_kill_feed.in_schedule(OnExit(GameState::Playing)))
+        .add_system(minimap::setup_minimap.in_schedule(OnEnter(GameState::Playing)))
+        .add_system(minimap::cleanup_minimap.in_schedule(OnExit(GameState::Playing)))
+        .add_system(speed::reset_speed.in_schedule(OnEnter(GameState::Playing)))
+        .add_system(spectator::setup_spectator.in_schedule(OnEnter(GameState::Playing)))
+        .add_system(spectator::spectator_panel_system.in_set(OnUpdate(GameState::Playing)))
+        .add_system(spectator::cleanup_spectator.in_schedule(OnExit(GameState::Playing)))
+        .add_system(speed::setup_speed_panel.in_schedule(OnEnter(GameState::Playing)))
+        .add_system(speed::cleanup_speed_panel.in_schedule(OnExit(GameState::Playing)))
+        .insert_resource(FixedTime::new_from_secs(1. / settings.tick_rate))
+        .add_systems((
+            combat::targeting_system,
+            fighter_movement.run_if(not(free_for_all)),
+            figter_siege.run_if(not(free_for_all)),
+            combat::fighting_system,
+            combat::explosion_system,
+            combat::healing_system,
+            status::status_effect_system,
+            combat::damage_system,
+            status::on_hit_effect_system,
+            experience::experience_system,
+            economy::kill_bounty_system,
+            kill_feed::kill_feed_system,
+            match_stats::match_stats_system,
+            combat::death_system,
+        ).chain().distributive_run_if(simulating).in_schedule(CoreSchedule::FixedUpdate))
+        .add_systems((
+            tower::tower_targeting_system,
+            combat::fan_out_system,
+            aura::aura_system,
+            aura::escort_system,
+            recall::recall_system,
+            orders::fall_back_system,
+            orders::waypoint_system,
+            cover::cover_system,
+            ffa::ffa_movement_system.run_if(free_for_all),
+        ).after(combat::targeting_system).before(combat::fighting_system)
+            .distributive_run_if(simulating).in_schedule(CoreSchedule::FixedUpdate))
+        .add_systems((
+            ai::ai_spawn_system.after(combat::death_system),
+            ai::ai_wave_system,
+            day_night::clock_system,
+            economy::income_system.run_if(not(free_for_all)),
+            economy::interest_system,
+            mercenary::offer_system,
+            weather::weather_system,
+            economy::price_surge_decay_system,
+            spells::mana_regen_system,
+            spells::spell_area_system,
+            traps::trap_trigger_system,
+            unit_stats::deployment_tracking_system,
+            match_end_system,
+            ladder::ladder_system,
+            unit_stats::unit_stats_system,
+        ).chain().distributive_run_if(simulating).in_schedule(CoreSchedule::FixedUpdate))
+        .add_system(campaign::campaign_end_system.after(match_end_system)
+            .run_if(simulating).in_schedule(CoreSchedule::FixedUpdate))
+        .add_system(draft::round_end_system.after(match_end_system)
+            .run_if(simulating).in_schedule(CoreSchedule::FixedUpdate))
+        .add_system(ffa::elimination_system.after(match_end_system)
+            .run_if(simulating).in_schedule(CoreSchedule::FixedUpdate))
+        .add_systems((
+            combat::damage_popup_system,
+            combat::heal_popup_system,
+            combat::miss_popup_system,
+            weather::weather_particle_system,
+            fog::fog_system,
+            mercenary::offer_popup_system,
+            sound::sound_effect_system,
+            animation::fighter_animation_system,
+            animation::body_animation_system,
+            particles::particle_system,
+            timeout_system,
+            idle::idle_prompt_system.after(idle::idle_detection_system),
+        ).in_set(OnUpdate(GameState::Playing)))
+        .add_systems((
+            airdrop::airdrop_landing_system.before(spawn_order_system),
+            spawn_order_system.after(soldier_placement_system),
+            supply::supply_system.before(soldier_placement_system).before(airdrop::airdrop_order_system),
+            lanes::lane_layout_system.before(spawn_order_system),
+        ).in_set(OnUpdate(GameState::Playing)))
+        // Everything besides units the player puts on the battlefield, which spectators don't, nor online players
+        .add_systems((
+            airdrop::airdrop_order_system,
+            spells::spell_cast_system,
+            militia::militia_call_system.before(spawn_order_system),
+            tower::build_system,
+            traps::trap_placement_system,
+            economy::income_upgrade_system.run_if(not(free_for_all)),
+            tech::research_system,
+            mercenary::offer_response_system.before(spawn_order_system),
+        ).distributive_run_if(placing).distributive_run_if(offline).in_set(OnUpdate(GameState::Playing)))
+        // And the units
+        .add_systems((
+            soldier_placement_system,
+            ghost::placement_ghost_system.after(mouse_location_system),
+            idle::idle_detection_system.before(speed::speed_input_system).before(speed::speed_panel_system),
+        ).distributive_run_if(placing).in_set(OnUpdate(GameState::Playing)))
+        // And the orders they give the units already on it
+        .add_systems((
+            stance::stance_toggle_system,
+            stance::unit_stance_system,
+            orders::order_input_system,
+            orders::rally_placement_system,
+            recall::recall_order_system,
+        ).distributive_run_if(not_spectating).distributive_run_if(offline).in_set(OnUpdate(GameState::Playing)))
         .add_system(fighter_health_bar_system)
+        .add_system(cover::shield_icon_system)
+        .add_system(facing_system)
+        .add_system(combat::rage_tint_system)
+        .add_system(combat::hit_flash_system)
+        .add_system(combat::floating_text_system)
+        .add_system(kill_feed::feed_entry_system)
+        .add_system(stats_overlay::stats_overlay_system)
+        .add_system(verbose_log::verbose_log_toggle_system.in_set(OnUpdate(GameState::Playing)))
+        .add_system(corpse::corpse_system)
+        .add_system(day_night::sky_system)
+        .add_system(experience::level_badge_system)
+        .add_system(status::status_icon_system)
+        .add_system(army_value::army_value_bar_system)
         .add_system(exit_on_esc_system)
+        .add_system(speed::speed_input_system)
         .add_system(scoreboard_text_system)
-        .add_system(fighting_system)
         .add_system(mouse_location_system)
-        .add_system(soldier_placement_system)
-        .add_system(timeout_system)
-        .run();
+        .add_system(battlefield_size_system.in_base_set(CoreSet::PreUpdate))
+        .add_system(control_options_system)
+        .add_system(unit_selection_system)
+        .add_system(toolbar::toolbar_click_system)
+        .add_system(tooltip::tooltip_system.after(mouse_location_system))
+        .add_system(toolbar::toolbar_system.after(toolbar::toolbar_click_system))
+        .add_system(spells::mana_text_system)
+        .add_system(aura::aura_circle_system)
+        .add_system(reload::setup_reload_ring_system)
+        .add_system(reload::reload_ring_system)
+        .add_system(hud_layout_system)
+        .add_system(camera::camera_focus_input_system.in_set(OnUpdate(GameState::Playing)))
+        .add_system(camera::camera_control_system.after(camera::camera_focus_input_system))
+        .add_system(camera::camera_system.after(camera::camera_control_system))
+        .add_system(maps::map_reload_system)
+        .add_system(balance::balance_reload_system)
+        .add_system(zone::spawn_zone_system.after(maps::map_reload_system))
+        .add_system(zone::zone_sprite_system.after(zone::spawn_zone_system))
+        .add_system(minimap::minimap_zone_system.after(zone::spawn_zone_system).in_set(OnUpdate(GameState::Playing)))
+        .add_system(minimap::minimap_dot_system.in_set(OnUpdate(GameState::Playing)))
+        .add_system(speed::speed_panel_system.in_set(OnUpdate(GameState::Playing)));
+
+    if headless {
+        app
+            .add_system(cli::headless_clock_system.in_base_set(CoreSet::First).before(TimeSystem))
+            .add_system(cli::headless_speed_system.after(speed::reset_speed).in_schedule(OnEnter(GameState::Playing)));
+    }
+
+    if let Some(exhibition) = exhibition {
+        app
+            .insert_resource(exhibition)
+            .add_system(exhibition::exhibition_start_system.in_set(OnUpdate(GameState::Menu)))
+            .add_system(exhibition::exhibition_results_system.in_schedule(OnEnter(GameState::Results)));
+    } else if headless {
+        app.add_system(cli::headless_exit_system.in_schedule(OnEnter(GameState::Results)));
+    }
+
+    #[cfg(feature = "twitch")]
+    app.add_plugin(twitch::TwitchPlugin);
+    #[cfg(feature = "bot_api")]
+    app.add_plugin(bot_api::BotApiPlugin);
+    #[cfg(feature = "scripting")]
+    app.add_plugin(scripting::ScriptingPlugin);
+    #[cfg(feature = "debug_overlay")]
+    app.add_plugin(debug::DebugOverlayPlugin);
+    #[cfg(feature = "online")]
+    app.add_plugin(online::OnlinePlugin);
+
+    app
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, States)]
+enum GameState {
+    #[default]
+    Menu,
+    Settings,
+    Playing,
+    /// The statistics screen after a match
+    Results,
+    /// Placing terrain on a map, see `editor`
+    Editor,
+    /// Between the matches of a campaign run, see `campaign`
+    Camp,
+    /// Drafting units before a round, see `draft`
+    Draft,
+}
+
+#[derive(Debug, Clone, Copy, Default, Resource)]
+struct MatchConfig {
+    /// Last difficulty selected in the menu
+    difficulty: ai::Difficulty,
+    /// Whether the opponent's side is played by the AI rather than a second player
+    versus_ai: bool,
+    mutators: mutators::Mutators,
+    /// Index into [`maps::Maps`]
+    map: usize,
+    /// Index into [`supply::SUPPLY_CAPS`]
+    supply_cap: usize,
+    /// Whether the AI plays both sides while the player only watches
+    spectating: bool,
+    /// How the AI plays each side it controls
+    strategies: PerSide<ai_strategy::Strategy>,
+    /// Sides a bot has taken over from the AI, see `bot_api`
+    bots: PerSide<bool>,
+    /// Whether the match is part of a campaign run, see `campaign`
+    campaign: bool,
+    /// Whether it's played in draft rounds, see `draft`
+    draft: bool,
+    /// Sides joining the AI's besides left and right in free-for-all, see `ffa`
+    extra_sides: usize,
+    /// Whether each player plays their side from their own machine, see `online`
+    online: bool,
+}
+
+impl MatchConfig {
+    fn ai(&self) -> Option<ai::Difficulty> {
+        self.versus_ai.then_some(self.difficulty)
+    }
+    /// Whether the AI deploys for `side` when the player is on `player_side`
+    fn ai_controls(&self, side: Side, player_side: Side) -> bool {
+        !self.bots[side] && !self.draft && (self.spectating || self.versus_ai && side != player_side)
+    }
+    /// The sides in play this match
+    fn sides(&self) -> &'static [Side] {
+        &Side::ALL[..2 + self.extra_sides]
+    }
+    fn free_for_all(&self) -> bool {
+        self.extra_sides > 0
+    }
+}
+
+fn not_spectating(config: Res<MatchConfig>) -> bool {
+    !config.spectating
+}
+
+fn free_for_all(config: Res<MatchConfig>) -> bool {
+    config.free_for_all()
+}
+
+/// Whether the player can do more than place units and watch, which online they can't,
+/// as placed units are all that's sent to the other player
+fn offline(config: Res<MatchConfig>) -> bool {
+    !config.online
+}
+
+/// Set while an online match waits on the other player's orders for the next tick, see `online`
+#[derive(Debug, Default, Resource)]
+struct Stalled(bool);
+
+/// Whether the match moves on this tick
+fn simulating(state: Res<State<GameState>>, stalled: Res<Stalled>) -> bool {
+    state.0 == GameState::Playing && !stalled.0
+}
+
+/// Whether the player can put anything new on the battlefield, which they can't between drafts
+fn placing(config: Res<MatchConfig>) -> bool {
+    !config.spectating && !config.draft
+}
+
+/// Sent when a side has taken full control of the battlefield
+#[derive(Debug, Clone, Copy)]
+struct MatchOverEvent {
+    winner: Side,
+}
+
+fn start_match(mut scoreboard_query: Query<&mut Scoreboard>, mut counter: ResMut<UnitCounter>) {
+    *counter = UnitCounter::default();
+    for mut scoreboard in scoreboard_query.iter_mut() {
+        scoreboard.score = 0;
+    }
+}
+
+fn match_end_system(
+    options: Res<ControlOptions>,
+    config: Res<MatchConfig>,
+    maps: Res<maps::Maps>,
+    scoreboard_query: Query<&Scoreboard>,
+    mut match_over: EventWriter<MatchOverEvent>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    // Draft rounds are won on health instead, see `draft`, and free-for-all by the last side standing, see `ffa`
+    if config.draft || config.free_for_all() {
+        return
+    }
+    let winning_score = maps.0[config.map].winning_score;
+    for scoreboard in scoreboard_query.iter() {
+        let winner = if scoreboard.score >= winning_score {
+            options.player_side
+        } else if scoreboard.score <= -winning_score {
+            options.player_side.opponent()
+        } else {
+            continue
+        };
+        match_over.send(MatchOverEvent { winner });
+        next_state.set(GameState::Results);
+    }
+}
+
+/// Clears away the units and popups left over from a match
+fn cleanup_battlefield(
+    mut commands: Commands,
+    fighter_query: Query<Entity, With<UnitType>>,
+    timeout_query: Query<(Entity, &Timeout)>,
+) {
+    for ent in fighter_query.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+    for (ent, timeout) in timeout_query.iter() {
+        commands.entity(ent).despawn();
+        for &tied in &timeout.tied_to {
+            commands.entity(tied).despawn();
+        }
+    }
 }
 
 type Level = u8;
 
+/// The edge of the battlefield an army deploys from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Side {
+    Left,
+    Right,
+    /// Only in play in free-for-all, see `ffa`
+    Top,
+    Bottom,
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Side::Left => "Left".fmt(f),
+            Side::Right => "Right".fmt(f),
+            Side::Top => "Top".fmt(f),
+            Side::Bottom => "Bottom".fmt(f),
+        }
+    }
+}
+
+impl Side {
+    /// In the order they join a match, see [`MatchConfig::sides`]
+    const ALL: [Side; 4] = [Side::Left, Side::Right, Side::Top, Side::Bottom];
+
+    /// The side across the battlefield
+    fn opponent(self) -> Self {
+        match self {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+            Side::Top => Side::Bottom,
+            Side::Bottom => Side::Top,
+        }
+    }
+    /// Sign of the x-direction this side's units march in, or face for the sides marching along y
+    fn direction(self) -> f32 {
+        match self {
+            Side::Left | Side::Top => 1.,
+            Side::Right | Side::Bottom => -1.,
+        }
+    }
+    /// Which way this side's units march, from its edge towards the opposite one
+    fn facing(self) -> Vec2 {
+        match self {
+            Side::Left => Vec2::X,
+            Side::Right => Vec2::NEG_X,
+            Side::Top => Vec2::NEG_Y,
+            Side::Bottom => Vec2::Y,
+        }
+    }
+    /// How far `point` lies past the centre of the battlefield, the way this side marches
+    fn advance(self, point: Vec2) -> f32 {
+        point.dot(self.facing())
+    }
+}
+
+/// The side a unit, spell or trap belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+struct Team(Side);
+
+/// One value for each side of the battlefield
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct PerSide<T> {
+    left: T,
+    right: T,
+    top: T,
+    bottom: T,
+}
+
+impl<T: Clone> PerSide<T> {
+    fn splat(value: T) -> Self {
+        PerSide {
+            left: value.clone(),
+            right: value.clone(),
+            top: value.clone(),
+            bottom: value,
+        }
+    }
+}
+
+impl<T> Index<Side> for PerSide<T> {
+    type Output = T;
+    fn index(&self, side: Side) -> &T {
+        match side {
+            Side::Left => &self.left,
+            Side::Right => &self.right,
+            Side::Top => &self.top,
+            Side::Bottom => &self.bottom,
+        }
+    }
+}
+
+impl<T> IndexMut<Side> for PerSide<T> {
+    fn index_mut(&mut self, side: Side) -> &mut T {
+        match side {
+            Side::Left => &mut self.left,
+            Side::Right => &mut self.right,
+            Side::Top => &mut self.top,
+            Side::Bottom => &mut self.bottom,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Resource)]
+struct ControlOptions {
+    /// Which side the player's army deploys from
+    player_side: Side,
+    /// Whether the left mouse button places the player's units instead of the right one
+    swap_buttons: bool,
+}
+
+impl Default for ControlOptions {
+    fn default() -> Self {
+        ControlOptions {
+            player_side: Side::Left,
+            swap_buttons: false,
+        }
+    }
+}
+
+impl ControlOptions {
+    fn button_side(&self, bindings: &bindings::Bindings, button: MouseButton) -> Option<Side> {
+        let (player_button, opponent_button) = if self.swap_buttons {
+            (bindings.opponent_button, bindings.player_button)
+        } else {
+            (bindings.player_button, bindings.opponent_button)
+        };
+        match button {
+            b if b == player_button => Some(self.player_side),
+            b if b == opponent_button => Some(self.player_side.opponent()),
+            _ => None,
+        }
+    }
+}
+
+fn control_options_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<bindings::Bindings>,
+    config: Res<MatchConfig>,
+    mut options: ResMut<ControlOptions>,
+) {
+    // Online, the side is the one agreed on with the other player
+    if keyboard_input.just_pressed(bindings.switch_side) && !config.online {
+        options.player_side = options.player_side.opponent();
+    }
+    if keyboard_input.just_pressed(bindings.swap_buttons) {
+        options.swap_buttons = !options.swap_buttons;
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 struct Skills {
+    name: &'static str,
+    price: u32,
     attack: Level,
     defence: Level,
     strength: Level,
@@ -56,38 +758,497 @@ struct Skills {
     hp: Level,
     speed: Level,
     siege: Level,
+    /// How much HP it restores to allies, healers don't attack
+    heal: Level,
+    /// Status effect its hits apply
+    inflicts: Option<status::EffectKind>,
+    /// Percent chance of a hit dealing double damage
+    crit: Level,
+    /// Percent chance of avoiding a hit entirely
+    dodge: Level,
+    damage_type: combat::DamageType,
+    armor: combat::ArmorClass,
+    targeting: combat::Targeting,
+    /// How far in front of it the unit can hit enemies it isn't touching
+    reach: Level,
+    /// Radius within which it inspires allies
+    aura: Level,
+    /// Seconds between attacks
+    cooldown: f32,
+    /// Protection it gains while escorted by enough allies
+    escort_protection: Level,
+    /// Protection it gives the ally right behind it, sheltering behind its shield
+    cover: Level,
+    /// Whether it attacks faster and harder the more wounded it is
+    rages: bool,
+    /// Radius of the explosion it sets off when it touches an enemy, killing itself
+    blast_radius: Level,
+    /// Whether it fights better at night instead of worse
+    nocturnal: bool,
+    /// Tech tier that has to be researched before it can be deployed, under the tech tree rule
+    tier: u8,
+    look: Look,
+}
+
+impl Skills {
+    /// Whether it's something built rather than a unit, which never moves
+    fn is_structure(&self) -> bool {
+        self.speed == 0
+    }
+    /// Whether it stops to fight `other` when running into it
+    fn engages(&self, other: &Skills) -> bool {
+        self.targeting == combat::Targeting::Units || other.is_structure()
+    }
+}
+
+/// What a unit looks like, each with a still image and a sprite sheet in `assets/units`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Look {
+    Swordsman,
+    Medic,
+    Shieldsman,
+    Spearman,
+    Crossbowman,
+    Bomber,
+    SiegeEngine,
+}
+
+impl Look {
+    const ALL: [Look; 7] = [
+        Look::Swordsman, Look::Medic, Look::Shieldsman, Look::Spearman, Look::Crossbowman, Look::Bomber,
+        Look::SiegeEngine,
+    ];
+
+    fn file_name(self) -> &'static str {
+        match self {
+            Look::Swordsman => "swordsman",
+            Look::Medic => "medic",
+            Look::Shieldsman => "shieldsman",
+            Look::Spearman => "spearman",
+            Look::Crossbowman => "crossbowman",
+            Look::Bomber => "bomber",
+            Look::SiegeEngine => "siege_engine",
+        }
+    }
+}
+
+const FIGHTER: Skills = Skills {
+    name: "Fighter",
+    price: 5,
+    attack: 30,
+    defence: 1,
+    hp: 20,
+    strength: 5,
+    speed: 35,
+    siege: 5,
+    heal: 0,
+    inflicts: None,
+    crit: 5,
+    dodge: 5,
+    damage_type: combat::DamageType::Slashing,
+    armor: combat::ArmorClass::Padded,
+    targeting: combat::Targeting::Units,
+    reach: 0,
+    aura: 0,
+    cooldown: combat::COOLDOWN,
+    escort_protection: 0,
+    cover: 0,
+    rages: false,
+    blast_radius: 0,
+    nocturnal: false,
+    tier: 0,
+    look: Look::Swordsman,
+};
+
+const MEDIC: Skills = Skills {
+    name: "Medic",
+    price: 8,
+    attack: 0,
+    defence: 1,
+    hp: 15,
+    strength: 1,
+    speed: 30,
+    siege: 2,
+    heal: 3,
+    inflicts: None,
+    crit: 0,
+    dodge: 10,
+    damage_type: combat::DamageType::Blunt,
+    armor: combat::ArmorClass::Unarmored,
+    targeting: combat::Targeting::Units,
+    reach: 0,
+    aura: 0,
+    cooldown: combat::COOLDOWN,
+    escort_protection: 0,
+    cover: 0,
+    rages: false,
+    blast_radius: 0,
+    nocturnal: false,
+    tier: 0,
+    look: Look::Medic,
+};
+
+const POISONER: Skills = Skills {
+    name: "Poisoner",
+    price: 7,
+    attack: 25,
+    strength: 2,
+    hp: 15,
+    siege: 3,
+    inflicts: Some(status::EffectKind::Poison),
+    damage_type: combat::DamageType::Piercing,
+    armor: combat::ArmorClass::Unarmored,
+    nocturnal: true,
+    tier: 1,
+    ..FIGHTER
+};
+
+const SHIELDSMAN: Skills = Skills {
+    name: "Shieldsman",
+    price: 8,
+    attack: 20,
+    defence: 10,
+    strength: 3,
+    hp: 30,
+    speed: 25,
+    dodge: 0,
+    armor: combat::ArmorClass::Shield,
+    cover: 4,
+    tier: 1,
+    look: Look::Shieldsman,
+    ..FIGHTER
+};
+
+/// Strikes over the unit in front of it
+const SPEARMAN: Skills = Skills {
+    name: "Spearman",
+    price: 7,
+    attack: 25,
+    defence: 2,
+    strength: 4,
+    hp: 18,
+    speed: 30,
+    reach: 30,
+    damage_type: combat::DamageType::Piercing,
+    tier: 1,
+    look: Look::Spearman,
+    ..FIGHTER
+};
+
+/// Hits hard from afar, but is left defenceless while reloading
+const CROSSBOWMAN: Skills = Skills {
+    name: "Crossbowman",
+    price: 10,
+    attack: 40,
+    defence: 2,
+    strength: 8,
+    hp: 14,
+    speed: 28,
+    crit: 10,
+    reach: 150,
+    cooldown: 3.,
+    damage_type: combat::DamageType::Piercing,
+    armor: combat::ArmorClass::Unarmored,
+    tier: 2,
+    look: Look::Crossbowman,
+    ..FIGHTER
+};
+
+/// Inspires the allies around it to fight harder
+const COMMANDER: Skills = Skills {
+    name: "Commander",
+    price: 12,
+    attack: 15,
+    defence: 3,
+    strength: 2,
+    hp: 25,
+    speed: 30,
+    aura: 90,
+    tier: 2,
+    ..FIGHTER
+};
+
+/// Slow and defenceless, but ignores the enemy army and hits hard once it gets through
+const SIEGE_ENGINE: Skills = Skills {
+    name: "Siege engine",
+    price: 20,
+    attack: 0,
+    defence: 5,
+    strength: 1,
+    hp: 60,
+    speed: 12,
+    siege: 25,
+    crit: 0,
+    dodge: 0,
+    armor: combat::ArmorClass::Mail,
+    targeting: combat::Targeting::Structures,
+    tier: 3,
+    look: Look::SiegeEngine,
+    ..FIGHTER
+};
+
+/// Gets more dangerous the closer it is to dying
+const BERSERKER: Skills = Skills {
+    name: "Berserker",
+    price: 9,
+    attack: 30,
+    defence: 0,
+    strength: 4,
+    hp: 24,
+    speed: 40,
+    dodge: 0,
+    armor: combat::ArmorClass::Unarmored,
+    rages: true,
+    tier: 2,
+    ..FIGHTER
+};
+
+/// Blows itself up on the first enemy it reaches, hurting everyone around
+const BOMBER: Skills = Skills {
+    name: "Bomber",
+    price: 10,
+    attack: 0,
+    defence: 0,
+    strength: 12,
+    hp: 10,
+    speed: 45,
+    siege: 3,
+    crit: 0,
+    damage_type: combat::DamageType::Blunt,
+    armor: combat::ArmorClass::Unarmored,
+    blast_radius: 60,
+    tier: 3,
+    look: Look::Bomber,
+    ..FIGHTER
+};
+
+/// Only goes for the enemy's side, and is hard to stop while escorted
+const BATTERING_RAM: Skills = Skills {
+    name: "Battering ram",
+    price: 15,
+    attack: 0,
+    defence: 3,
+    strength: 1,
+    hp: 45,
+    speed: 15,
+    siege: 20,
+    crit: 0,
+    dodge: 0,
+    armor: combat::ArmorClass::Mail,
+    targeting: combat::Targeting::Structures,
+    escort_protection: 6,
+    tier: 3,
+    look: Look::SiegeEngine,
+    ..FIGHTER
+};
+
+/// The units that can be deployed, in the order of their number keys
+const UNITS: [Skills; 11] = [
+    FIGHTER, MEDIC, POISONER, SHIELDSMAN, SPEARMAN, CROSSBOWMAN, COMMANDER, BERSERKER, BOMBER, SIEGE_ENGINE,
+    BATTERING_RAM,
+];
+
+/// Index into [`UNITS`] of the unit the player deploys
+#[derive(Debug, Default, Clone, Copy, Resource)]
+struct SelectedUnit(usize);
+
+impl SelectedUnit {
+    fn skills(self) -> Skills {
+        UNITS[self.0]
+    }
 }
 
+fn unit_selection_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<bindings::Bindings>,
+    mut selected: ResMut<SelectedUnit>,
+) {
+    for (i, key) in bindings.units.into_iter().enumerate().take(UNITS.len()) {
+        if keyboard_input.just_pressed(key) {
+            selected.0 = i;
+        }
+    }
+    // There are more units than number keys
+    if keyboard_input.just_pressed(bindings.next_unit) {
+        selected.0 = (selected.0 + 1) % UNITS.len();
+    }
+}
+
+/// What kind of unit it is, with the skills it was deployed with
 #[derive(Debug, Clone, Copy, Component)]
-struct Fighter {
+struct UnitType {
     skills: Skills,
-    // MAYBE: gear (that gives bonuses in each)
+}
+
+impl UnitType {
+    /// Whether it takes longer than usual between attacks
+    fn reloads(&self) -> bool {
+        self.skills.cooldown > combat::COOLDOWN
+    }
+    /// From 0 at full health to 1 at death for raging units, always 0 for others
+    fn rage(&self, health: &Health) -> f32 {
+        if self.skills.rages {
+            1. - health.fraction()
+        } else {
+            0.
+        }
+    }
+    /// Seconds until it can attack again after an attack
+    fn cooldown(&self, health: &Health) -> f32 {
+        self.skills.cooldown / (1. + self.rage(health))
+    }
+}
+
+/// The skills a unit fights with, which go up as it levels up
+#[derive(Debug, Clone, Copy, Component)]
+struct CombatStats {
+    attack: Level,
+    defence: Level,
+    strength: Level,
+}
+
+impl CombatStats {
+    fn new(skills: &Skills) -> Self {
+        CombatStats {
+            attack: skills.attack,
+            defence: skills.defence,
+            strength: skills.strength,
+        }
+    }
+    /// Its highest damage roll, raised by `rage`
+    fn strength(&self, rage: f32) -> Level {
+        (self.strength as f32 * (1. + rage)).round() as Level
+    }
+}
+
+#[derive(Debug, Clone, Copy, Component)]
+struct Health {
     hp: u8,
+    max: u8,
+    // MAYBE: gear (that gives bonuses in each)
     protection: u8,
-    fighting: Option<Entity>,
-    attack_cooldown: f32,
-    waiting: bool,
 }
 
-impl Fighter {
-    pub fn new(skills: Skills) -> Self {
-        Fighter {
-            hp: skills.hp,
+impl Health {
+    fn new(max: u8) -> Self {
+        Health {
+            hp: max,
+            max,
             protection: 0,
-            skills,
-            fighting: None,
-            attack_cooldown: 0.,
-            waiting: false,
         }
     }
-    pub fn with_protection(skills: Skills, protection: u8) -> Self {
-        Fighter {
-            protection,
-            .. Fighter::new(skills)
+    /// How much of its HP it has left, from 0 to 1
+    fn fraction(&self) -> f32 {
+        self.hp as f32 / self.max as f32
+    }
+    /// Whether it's alive but could be healed
+    fn wounded(&self) -> bool {
+        self.hp > 0 && self.hp < self.max
+    }
+}
+
+/// Seconds until it can attack or heal again
+#[derive(Debug, Clone, Copy, Default, Component)]
+struct AttackCooldown(f32);
+
+impl AttackCooldown {
+    /// Slow-reloading units can't defend themselves until they're ready again
+    fn reloading(&self, unit: &UnitType) -> bool {
+        unit.reloads() && self.0 > 0.
+    }
+}
+
+/// The enemy it's fighting, if any
+#[derive(Debug, Clone, Copy, Default, Component)]
+struct CombatTarget(Option<Entity>);
+
+/// The enemies fighting it that it can hit back, which it takes turns hitting
+#[derive(Debug, Clone, Default, Component)]
+struct Attackers(Vec<Entity>);
+
+#[derive(Debug, Clone, Copy, Component)]
+struct MovementState {
+    /// Share of its full speed it's marching at, held back by allies close in front of it
+    pace: f32,
+    /// Whether it turned around to go after an enemy that got behind it
+    turned: bool,
+}
+
+impl Default for MovementState {
+    fn default() -> Self {
+        MovementState {
+            pace: 1.,
+            turned: false,
         }
     }
-    fn moving(&self) -> bool {
-        !self.waiting && self.fighting.is_none()
+}
+
+impl MovementState {
+    /// Whether it's all but stopped behind an ally
+    fn held_up(&self) -> bool {
+        self.pace < 0.25
+    }
+    /// The way it's facing along the x-axis, which is the way its side marches unless it turned around
+    fn heading(&self, side: Side) -> f32 {
+        if self.turned { -side.direction() } else { side.direction() }
+    }
+}
+
+/// Everything a unit needs to move and fight
+#[derive(Bundle)]
+struct FighterBundle {
+    unit_type: UnitType,
+    stats: CombatStats,
+    health: Health,
+    cooldown: AttackCooldown,
+    target: CombatTarget,
+    attackers: Attackers,
+    movement: MovementState,
+}
+
+impl FighterBundle {
+    pub fn new(skills: Skills) -> Self {
+        FighterBundle {
+            unit_type: UnitType { skills },
+            stats: CombatStats::new(&skills),
+            health: Health::new(skills.hp),
+            cooldown: AttackCooldown::default(),
+            target: CombatTarget::default(),
+            attackers: Attackers::default(),
+            movement: MovementState::default(),
+        }
+    }
+}
+
+/// Stable name of a unit for logs and UI, numbered per side in deployment order
+#[derive(Debug, Clone, Copy, Component)]
+struct UnitName {
+    side: Side,
+    kind: &'static str,
+    number: u32,
+}
+
+impl fmt::Display for UnitName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} #{}", self.side, self.kind, self.number)
+    }
+}
+
+impl UnitName {
+    /// The name for showing the player, in their language
+    fn localized(&self, locale: &locale::Locale) -> String {
+        format!("{} {} #{}", locale.name(&self.side.to_string()), locale.name(self.kind), self.number)
+    }
+}
+
+/// How many units each side has deployed this match
+#[derive(Debug, Default, Clone, Copy, Resource)]
+struct UnitCounter(PerSide<u32>);
+
+impl UnitCounter {
+    fn next(&mut self, side: Side) -> u32 {
+        self.0[side] += 1;
+        self.0[side]
     }
 }
 
@@ -115,26 +1276,31 @@ impl Timeout {
     }
 }
 
-fn fighter_sprite_bundle(x: f32, y: f32, flipped: bool, materials: &Materials) -> SpriteBundle {
+fn fighter_sprite_bundle(x: f32, y: f32, size: f32, side: Side, skills: &Skills, materials: &Materials) -> SpriteSheetBundle {
     let mut transform = Transform::from_translation(Vec3::new(x, y, 0.0));
-    if flipped {
-        transform.scale.x = -transform.scale.x;
-    }
-    SpriteBundle {
-        texture: materials.fighter.clone(),
+    // Facing the way it marches until it turns around, which is only for looks as its side is kept in its `Team`
+    transform.scale.x *= side.direction();
+    SpriteSheetBundle {
+        texture_atlas: materials.sheet(skills.look),
         transform,
-        sprite: Sprite {
-            custom_size: Some(Vec2::new(32.0, 32.0)),
+        sprite: TextureAtlasSprite {
+            color: materials.team[side],
+            custom_size: Some(Vec2::new(size, size)),
             .. default()
         },
         .. Default::default()
     }
 }
 
-fn spawn_fighter(cmds: &mut Commands, x: f32, y: f32, flipped: bool, materials: &Materials, skills: Skills) {
+fn spawn_fighter(cmds: &mut Commands, pos: Vec2, size: f32, side: Side, materials: &Materials, skills: Skills) -> Entity {
     cmds
-        .spawn(fighter_sprite_bundle(x, y, flipped, materials))
-        .insert(Fighter::new(skills))
+        .spawn(fighter_sprite_bundle(pos.x, pos.y, size, side, &skills, materials))
+        .insert(FighterBundle::new(skills))
+        .insert(Team(side))
+        .insert(experience::Experience::default())
+        .insert(status::StatusEffects::default())
+        .insert(animation::Animation::default())
+        .insert(cover::Brace::at(pos))
         .with_children(|parent| {
             parent
                 .spawn(SpriteBundle {
@@ -153,18 +1319,63 @@ fn spawn_fighter(cmds: &mut Commands, x: f32, y: f32, flipped: bool, materials:
                     ..Default::default()
                 })
                 .insert(HealthBar);
-        });
+            // Hidden until it's protected, see `cover::shield_icon_system`
+            parent.spawn(SpriteBundle {
+                    transform: Transform::from_translation(Vec3::new(22., 30., 1.)),
+                    sprite: Sprite {
+                        color: Color::rgb(0.7, 0.75, 0.85),
+                        custom_size: Some(Vec2::new(6.0, 8.0)), .. default() },
+                    visibility: Visibility::Hidden,
+                    ..Default::default()
+                })
+                .insert(cover::ShieldIcon);
+            // Counter the parent's flip so the text reads the right way around
+            let mut transform = Transform::from_translation(Vec3::new(0., 42., 2.));
+            transform.scale.x = side.direction();
+            parent.spawn(Text2dBundle {
+                text: Text::from_section("", TextStyle {
+                    font: materials.font.clone(),
+                    font_size: 14.,
+                    color: Color::rgb(1., 0.84, 0.),
+                }),
+                transform,
+                ..Default::default()
+            })
+            .insert(experience::LevelBadge);
+        })
+        .id()
+}
+
+/// Flips units' sprites to face the way they're heading, keeping their level badge readable
+fn facing_system(
+    mut query: Query<(&Team, &MovementState, &mut Transform, &Children), Changed<MovementState>>,
+    mut badge_query: Query<&mut Transform, (With<experience::LevelBadge>, Without<MovementState>)>,
+) {
+    for (&Team(side), movement, mut transform, children) in query.iter_mut() {
+        let heading = movement.heading(side);
+        if transform.scale.x * heading > 0. {
+            continue
+        }
+        transform.scale.x = -transform.scale.x;
+        for &child in children {
+            if let Ok(mut badge) = badge_query.get_mut(child) {
+                badge.scale.x = heading;
+            }
+        }
+    }
 }
 
 fn fighter_health_bar_system(
-    query: Query<(&Fighter, &Children)>,
+    materials: Res<Materials>,
+    query: Query<(&Health, &Children), Changed<Health>>,
     mut health_query: Query<(&mut Transform, &mut Sprite), With<HealthBar>>,
 ) {
-    for (fighter, children) in query.iter() {
+    for (health, children) in query.iter() {
         for child in &**children {
-            if let Ok((mut trans, mut spr)) = health_query.get_mut(child.clone()) {
-                let x = 32. * fighter.hp as f32 / fighter.skills.hp as f32;
+            if let Ok((mut trans, mut spr)) = health_query.get_mut(*child) {
+                let x = 32. * health.fraction();
                 spr.custom_size.as_mut().unwrap().x = x;
+                spr.color = materials.health_color(health.fraction());
                 trans.translation.x = 0.5 * x - 16.;
             }
         }
@@ -175,25 +1386,73 @@ fn fighter_health_bar_system(
 #[derive(Resource)]
 struct Materials {
     font: Handle<Font>,
-    fighter: Handle<Image>,
+    unit_images: [Handle<Image>; Look::ALL.len()],
+    unit_sheets: [Handle<TextureAtlas>; Look::ALL.len()],
+    /// The sheets as white silhouettes, for flashing units that get hit
+    unit_flash_sheets: [Handle<TextureAtlas>; Look::ALL.len()],
+    /// Tint of each side's units, which only colours what they're holding
+    team: PerSide<Color>,
+    // The rest of the colours below come from the palette picked in the settings
+    /// Health bar colour at full, half and no health
+    health: [Color; 3],
     black: Color,
     green: Color,
     red: Color,
+    player_zone: Color,
+    enemy_zone: Color,
+}
+
+impl Materials {
+    fn image(&self, look: Look) -> Handle<Image> {
+        self.unit_images[look as usize].clone()
+    }
+    fn sheet(&self, look: Look) -> Handle<TextureAtlas> {
+        self.unit_sheets[look as usize].clone()
+    }
+    fn flash_sheet(&self, look: Look) -> Handle<TextureAtlas> {
+        self.unit_flash_sheets[look as usize].clone()
+    }
+    /// Colour of a health bar with `share` of the health left, fading from full through half to none
+    fn health_color(&self, share: f32) -> Color {
+        let [full, half, none] = self.health.map(|c| Vec4::from(c.as_rgba_f32()));
+        let color = if share > 0.5 {
+            half.lerp(full, 2. * share - 1.)
+        } else {
+            none.lerp(half, 2. * share)
+        };
+        Color::rgba(color.x, color.y, color.z, color.w)
+    }
 }
 
 impl FromWorld for Materials {
     fn from_world(world: &mut World) -> Self {
         let asset_server = world.get_resource::<AssetServer>().unwrap();
         let font = asset_server.load("DroidSansMono.ttf");
-        let fighter_asset = asset_server.load("fighter.png");
+        let unit_images = Look::ALL.map(|look| asset_server.load(format!("units/{}.png", look.file_name())));
+        let sheets = Look::ALL.map(|look| asset_server.load(format!("units/{}_sheet.png", look.file_name())));
+        let flash_sheets = Look::ALL.map(|look| asset_server.load(format!("units/{}_flash.png", look.file_name())));
+        let mut atlases = world.resource_mut::<Assets<TextureAtlas>>();
+        let mut atlas = |sheet| {
+            atlases.add(TextureAtlas::from_grid(sheet, animation::FRAME_SIZE, animation::FRAMES, 3, None, None))
+        };
+        let unit_sheets = sheets.map(&mut atlas);
+        let unit_flash_sheets = flash_sheets.map(&mut atlas);
 
-        Self {
+        let mut materials = Self {
             font,
-            fighter: fighter_asset,
+            unit_images,
+            unit_sheets,
+            unit_flash_sheets,
+            team: PerSide::splat(Color::WHITE),
             black: Color::rgba(0., 0., 0., 0.33),
-            green: Color::rgba(0., 1., 0., 0.33),
-            red: Color::rgb(1., 0., 0.),
-        }
+            health: [Color::NONE; 3],
+            green: Color::NONE,
+            red: Color::NONE,
+            player_zone: Color::NONE,
+            enemy_zone: Color::NONE,
+        };
+        palette::PALETTES[0].apply(&mut materials);
+        materials
     }
 }
 
@@ -210,7 +1469,7 @@ fn setup(
         text: Text {
             sections: vec![
                 TextSection {
-                    value: "Score: ".to_string(),
+                    value: "".to_string(),
                     style: TextStyle {
                         font: materials.font.clone(),
                         color: Color::rgb(0.5, 0.5, 1.0),
@@ -224,6 +1483,22 @@ fn setup(
                         color: Color::rgb(0.5, 0.5, 1.0),
                         font_size: 40.0,
                     }
+                },
+                TextSection {
+                    value: "".to_string(),
+                    style: TextStyle {
+                        font: materials.font.clone(),
+                        color: Color::rgb(1.0, 0.84, 0.0),
+                        font_size: 40.0,
+                    }
+                },
+                TextSection {
+                    value: "".to_string(),
+                    style: TextStyle {
+                        font: materials.font.clone(),
+                        color: Color::rgb(0.7, 0.7, 0.7),
+                        font_size: 24.0,
+                    }
                 }
             ],
             .. Default::default()
@@ -245,67 +1520,238 @@ fn setup(
 #[derive(Resource)]
 pub struct MouseLoc(Vec2);
 
+/// Size of the battlefield, centred on the origin. It follows the size of the window,
+/// but keeps its last size while the window is minimised, and takes the map's size without one
+#[derive(Debug, Clone, Copy, Resource)]
+pub struct Battlefield {
+    pub half_size: Vec2,
+}
+
+impl Default for Battlefield {
+    fn default() -> Self {
+        Battlefield {
+            half_size: Vec2::new(640., 360.),
+        }
+    }
+}
+
+impl Battlefield {
+    pub fn width(&self) -> f32 {
+        2. * self.half_size.x
+    }
+    pub fn height(&self) -> f32 {
+        2. * self.half_size.y
+    }
+}
+
+fn battlefield_size_system(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    maps: Res<maps::Maps>,
+    config: Res<MatchConfig>,
+    mut battlefield: ResMut<Battlefield>,
+) {
+    // Without a window, as when running headless, the map says how big the battlefield is, and so it does online
+    // where both players' battlefields have to be the same
+    let half_size = match window_query.get_single() {
+        Ok(window) if !config.online => Vec2::new(window.width(), window.height()) / 2.,
+        _ => maps.0[config.map].size / 2.,
+    };
+    if half_size.cmpgt(Vec2::ZERO).all() && half_size != battlefield.half_size {
+        battlefield.half_size = half_size;
+    }
+}
+
 fn mouse_location_system(
     window_query: Query<&Window, With<PrimaryWindow>>,
     mut mouse_loc: ResMut<MouseLoc>,
     camera_q: Query<(&Camera, &GlobalTransform), With<MainCamera>>
 ) {
-    let (camera, camera_transform) = camera_q.single();
+    let (Ok((camera, camera_transform)), Ok(window)) = (camera_q.get_single(), window_query.get_single()) else {
+        return
+    };
+    // The cursor can leave the window, or the canvas lose focus on the web, so keep the last location then
+    let Some(loc) = window.cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor)) else {
+        return
+    };
+    mouse_loc.0 = loc;
+}
 
-    let window = window_query.single();
 
-    mouse_loc.0 = window.cursor_position().unwrap_or(Vec2::ZERO);
-    mouse_loc.0 = camera.viewport_to_world_2d(camera_transform, mouse_loc.0).unwrap();
-}
+/// How close an enemy ahead must be for an aggressive unit to steer towards it
+const AGGRO_RANGE: f32 = 150.;
+/// Gap units keep to the ally in front of them, slowing down as they close in on it
+const FORMATION_SPACING: f32 = 12.;
+/// How fast units held up behind an ally step aside, as a share of their speed
+const SIDESTEP: f32 = 0.5;
 
+type MovementItem<'a> = (
+    &'a UnitName,
+    &'a mut Transform,
+    &'a Team,
+    &'a UnitType,
+    &'a CombatTarget,
+    &'a mut MovementState,
+    &'a status::StatusEffects,
+    &'a stance::Stance,
+    &'a TextureAtlasSprite,
+);
+
+/// How much of its speed a unit at `pos` can march at without running into its allies, and which way it should
+/// step aside to get around them
+fn separation(allies: &[(u32, Vec2, Vec2)], number: u32, pos: Vec2, size: Vec2, direction: f32) -> (f32, f32) {
+    let mut pace: f32 = 1.;
+    let mut aside = 0.;
+    for &(other, other_pos, other_size) in allies {
+        let offset = other_pos - pos;
+        let overlap_y = 0.5 * (size.y + other_size.y) - offset.y.abs();
+        if other == number || offset.x * direction <= 0. || overlap_y <= 0. {
+            continue
+        }
+        let gap = offset.x * direction - 0.5 * (size.x + other_size.x);
+        if gap > FORMATION_SPACING {
+            continue
+        }
+        pace = pace.min((gap / FORMATION_SPACING).max(0.));
+        // Away from the ally, and for units right behind one another, by whichever was deployed first
+        let away = if offset.y != 0. { -offset.y.signum() } else if number < other { 1. } else { -1. };
+        aside += away * overlap_y / (0.5 * (size.y + other_size.y));
+    }
+    (pace, aside.clamp(-1., 1.))
+}
 
 fn fighter_movement(
-    time: Res<Time>,
-    window_query: Query<&Window, With<PrimaryWindow>>,
-    mut query: Query<(&mut Transform, &Fighter)>,
+    fixed_time: Res<FixedTime>,
+    battlefield: Res<Battlefield>,
+    mut query: Query<MovementItem, (Without<recall::Recalling>, Without<orders::FallingBack>, Without<orders::Waypoint>)>,
+    obstacle_query: Query<(&Transform, &terrain::Obstacle), Without<UnitType>>,
+    lanes: Res<lanes::Lanes>,
+    river: Res<terrain::River>,
+    weather: Res<weather::Weather>,
 ) {
-    let window = window_query.get_single().expect("No primary window.");
-    let height = window.height();
+    let _span = debug_span!("movement").entered();
+    let height = battlefield.height();
 
-    let delta = time.delta_seconds();
+    let delta = fixed_time.period.as_secs_f32();
 
-    query.par_iter_mut().for_each_mut(|(mut transform, fighter)| {
-        if !fighter.moving() {
+    // Both sorted along the battlefield, so each unit only looks through the units near it
+    let mut positions: Vec<_> = query.iter().map(|(_, t, &Team(side), ..)| (side, t.translation.truncate())).collect();
+    positions.sort_by(|(_, a), (_, b)| a.x.total_cmp(&b.x));
+    // Structures let their own side through
+    let allies = PerSide::<Vec<_>>::default();
+    let mut allies = query.iter()
+        .filter(|(_, _, _, unit, ..)| !unit.skills.is_structure())
+        .fold(allies, |mut allies, (name, t, &Team(side), .., sprite)| {
+            allies[side].push((name.number, t.translation.truncate(), sprite.custom_size.unwrap()));
+            allies
+        });
+    for side in Side::ALL {
+        allies[side].sort_by(|(_, a, _), (_, b, _)| a.x.total_cmp(&b.x));
+    }
+    let widest = query.iter().map(|(.., sprite)| sprite.custom_size.unwrap().x).fold(0., f32::max);
+    let obstacles: Vec<_> = obstacle_query.iter().map(|(t, o)| (t.translation.truncate(), o.radius)).collect();
+
+    query.par_iter_mut().for_each_mut(|(name, mut transform, &Team(side), unit, target, mut movement, status, &stance, sprite)| {
+        if target.0.is_some() || !stance.advances() {
             return
         }
 
-        let scale_x = transform.scale.x;
         let translation = &mut transform.translation;
+        let speed = 3. * unit.skills.speed as f32 * status.speed_factor() * weather.speed_factor() * delta;
+        let lane = lanes.lane_of(translation.y);
+        let start = translation.truncate();
+
+        // Turn around to go after enemies that got past it, as long as none are coming from ahead
+        let chasing = stance.chases() && !unit.skills.is_structure()
+            && unit.skills.targeting == combat::Targeting::Units;
+        let near = within_x(&positions, |&(_, p)| p.x, start.x - AGGRO_RANGE, start.x + AGGRO_RANGE);
+        let enemy_near = |behind: bool| near.iter()
+            .filter(|&&(s, p)| s != side && p.distance(start) < AGGRO_RANGE)
+            .any(|&(_, p)| ((p.x - start.x) * side.direction() < 0.) == behind);
+        let turned = chasing && !enemy_near(false) && enemy_near(true);
+        if movement.turned != turned {
+            movement.turned = turned;
+        }
+        let heading = movement.heading(side);
+
+        // Slow down behind allies and step aside, so units bunch up into columns rather than a queue
+        let size = sprite.custom_size.unwrap();
+        let spacing = 0.5 * (size.x + widest) + FORMATION_SPACING;
+        let close = within_x(&allies[side], |&(_, p, _)| p.x, start.x - spacing, start.x + spacing);
+        let (pace, aside) = separation(close, name.number, start, size, heading);
+        if movement.pace != pace {
+            movement.pace = pace;
+        }
+        if pace < 1. {
+            translation.y += aside * SIDESTEP * speed;
+        }
 
-        translation.x += 3. * scale_x * fighter.skills.speed as f32 * delta;
+        // Walk around obstacles rather than into them
+        let avoiding = terrain::avoidance(&obstacles, translation.truncate(), heading);
+        if let Some((away, blocked)) = avoiding {
+            translation.y += away * speed;
+            if !blocked {
+                translation.x += heading * pace * speed;
+            }
+        } else {
+            translation.x += heading * pace * speed;
+        }
 
-        // Messy code to keep inside frame
-        translation.y += height * 1.5;
-        translation.y %= height;
-        translation.y -= height * 0.5;
+        // Steer towards the closest enemy ahead, if enemy units are what it's after
+        let pos = translation.truncate();
+        let closest = within_x(&positions, |&(_, p)| p.x, pos.x - AGGRO_RANGE, pos.x + AGGRO_RANGE).iter()
+            .filter(|_| avoiding.is_none() && stance.chases() && unit.skills.targeting == combat::Targeting::Units)
+            .filter(|&&(s, p)| s != side && (p.x - pos.x) * heading > 0.)
+            .map(|&(_, p)| p)
+            .filter(|p| p.distance(pos) < AGGRO_RANGE)
+            .min_by(|a, b| a.distance(pos).total_cmp(&b.distance(pos)));
+        if let Some(enemy) = closest {
+            translation.y += (enemy.y - pos.y).clamp(-0.5 * speed, 0.5 * speed);
+        }
+
+        // The river can only be crossed at a bridge
+        if let Some(bridge) = river.detour(start, translation.truncate()) {
+            translation.x = start.x;
+            translation.y = start.y + (bridge - start.y).clamp(-speed, speed);
+        }
+
+        if lanes.enabled() {
+            translation.y = lanes.clamp(lane, translation.y);
+        } else {
+            // Messy code to keep inside frame
+            translation.y += height * 1.5;
+            translation.y %= height;
+            translation.y -= height * 0.5;
+        }
     })
 }
 
 fn figter_siege(
-    window_query: Query<&Window, With<PrimaryWindow>>,
+    battlefield: Res<Battlefield>,
+    options: Res<ControlOptions>,
     mut commands: Commands,
-    query: Query<(Entity, &Transform, &Fighter)>,
+    query: Query<(Entity, &Transform, &Team, &UnitType, &UnitName)>,
     mut scoreboard_query: Query<&mut Scoreboard>,
-    camera_q: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut economy: ResMut<economy::Economy>,
+    mut siege_events: EventWriter<sound::SiegeEvent>,
 ) {
-    let window = window_query.get_single().expect("No primary window.");
-    let width = window.width();
+    // The battlefield is centred on the origin, wherever the camera is looking
+    let half_width = battlefield.half_size.x;
 
-    let (camera, global_transform) = camera_q.single();
-
-    for (ent, transform, fighter) in query.iter() {
-        let pos = camera.world_to_viewport(global_transform, transform.translation).unwrap();
-        if pos.x > width {
-            commands.entity(ent).despawn_recursive();
-            scoreboard_query.for_each_mut(|mut s| s.score += fighter.skills.siege as i32);
-        } else if pos.x < 0. {
+    for (ent, transform, &Team(side), unit, name) in query.iter() {
+        // Only out the enemy's end, not back out its own after turning around
+        if transform.translation.x * side.direction() > half_width {
+            info!(unit = %name, "sieged");
             commands.entity(ent).despawn_recursive();
-            scoreboard_query.for_each_mut(|mut s| s.score -= fighter.skills.siege as i32);
+            economy.earn(side, unit.skills.siege as u32, economy::IncomeSource::Siege);
+            let siege = if side == options.player_side {
+                unit.skills.siege as i32
+            } else {
+                -(unit.skills.siege as i32)
+            };
+            scoreboard_query.for_each_mut(|mut s| s.score += siege);
+            siege_events.send(sound::SiegeEvent { name: *name });
+            particles::spawn_burst(&mut commands, transform.translation, particles::Burst::dust());
         }
     }
 }
@@ -315,172 +1761,271 @@ struct Scoreboard {
     score: i32,
 }
 
-fn scoreboard_text_system(mut query: Query<(&mut Text, &Scoreboard)>) {
-    for (mut text, scoreboard) in query.iter_mut() {
-        text.sections[1].value = format!("{}", scoreboard.score);
+/// Keeps the HUD anchored to the player's side of the screen
+fn hud_layout_system(
+    options: Res<ControlOptions>,
+    mut query: Query<&mut Style, With<Scoreboard>>,
+) {
+    if !options.is_changed() {
+        return
+    }
+    for mut style in query.iter_mut() {
+        let (left, right) = match options.player_side {
+            Side::Right => (Val::Undefined, Val::Px(5.0)),
+            _ => (Val::Px(5.0), Val::Undefined),
+        };
+        style.position.left = left;
+        style.position.right = right;
     }
 }
 
-fn collision_system(
-    mut query: Query<(Entity, &mut Fighter, &Transform, &Sprite)>,
+fn scoreboard_text_system(
+    economy: Res<economy::Economy>,
+    supply: Res<supply::Supply>,
+    options: Res<ControlOptions>,
+    config: Res<MatchConfig>,
+    stances: Res<stance::DefaultStance>,
+    orders: Res<orders::ArmyOrders>,
+    bindings: Res<bindings::Bindings>,
+    locale: Res<locale::Locale>,
+    mut query: Query<(&mut Text, &Scoreboard)>,
 ) {
-    let mut waiting = HashMap::new();
-    let mut ents: Vec<_> = query.iter_mut().collect();
-    
-    ents.compare_self_mut(|
-        (left_entity, left_fighter, left_trans, left_spr),
-        (right_entity, right_fighter, right_trans, right_spr)
-    | {
-        let waiting = &mut waiting;
-        let collision = collide(
-            left_trans.translation,
-            left_spr.custom_size.unwrap(),
-            right_trans.translation,
-            right_spr.custom_size.unwrap(),
-        );
-        if let Some(collision) = collision {
-            if left_trans.scale.x == right_trans.scale.x {
-                let ((left_entity, right_entity), (left_fighter, right_fighter)) = if left_trans.scale.x > 0. {
-                    ((left_entity, right_entity), (left_fighter, right_fighter))
-                } else {
-                    ((right_entity, left_entity), (right_fighter, left_fighter))
-                };
-
-                match collision {
-                    Collision::Left | Collision::Top => {
-                        left_fighter.waiting = true;
-                        waiting.insert(left_entity.clone(), true);
-                    }
-                    Collision::Right | Collision::Bottom | Collision::Inside => {
-                        right_fighter.waiting = true;
-                        waiting.insert(right_entity.clone(), true);
-                    }
-                }
-            } else {
-                left_fighter.fighting = Some(right_entity.clone());
-                right_fighter.fighting = Some(left_entity.clone());
-            }
+    let player = options.player_side;
+    for (mut text, scoreboard) in query.iter_mut() {
+        text.sections[0].value = format!("{} ", locale.text("hud.score"));
+        text.sections[1].value = format!("{}", scoreboard.score);
+        // Nothing comes in by itself in free-for-all
+        let income = !config.free_for_all();
+        let money = |side| if income {
+            format!("{}¤ +{:.2}/s", economy.money[side], economy.income_rate(side))
         } else {
-            if left_fighter.waiting {
-                waiting.entry(left_entity.clone()).or_insert(false);
-            }
-            if right_fighter.waiting {
-                waiting.entry(right_entity.clone()).or_insert(false);
-            }
+            format!("{}¤", economy.money[side])
+        };
+        let upgrade_key = format!("{:?}", bindings.income_upgrade);
+        // Nor can income be upgraded online, see `online`
+        let upgrade = if income && !config.online {
+            locale.format("hud.upgrade", &[&upgrade_key, &economy.upgrade_price(player)])
+        } else {
+            String::new()
+        };
+        text.sections[2].value = if config.spectating {
+            let sides: Vec<_> = config.sides().iter().map(|&side| money(side)).collect();
+            format!("  {}", sides.join(&format!(" {} ", locale.text("hud.versus"))))
+        } else if config.versus_ai || config.online {
+            format!("  {} {upgrade}", money(player))
+        } else {
+            format!("  {} {} {} {upgrade}", money(player), locale.text("hud.versus"), money(player.opponent()))
+        };
+        if let Some(cap) = supply.cap {
+            text.sections[2].value += &format!("  {}", locale.format("hud.supply", &[&supply.used[player], &cap]));
         }
-    });
-
-    for (ent, v) in waiting.into_iter().filter(|(_, v)| !v) {
-        query.get_mut(ent).unwrap().1.waiting = v;
+        if let Some(next_interest) = economy.next_interest {
+            text.sections[2].value += &format!("  {}", locale.format("hud.interest", &[&next_interest.ceil()]));
+        }
+        text.sections[3].value = format!(
+            "  {} ({:?})  {} ({:?})",
+            locale.name(stances.0[player].name()),
+            bindings.stance,
+            locale.name(orders.0[player].name()),
+            bindings.fall_back,
+        );
     }
 }
 
-use std::sync::mpsc::sync_channel;
+/// Whether `point` lies on the sprite
+fn hit_test(transform: &Transform, sprite: &TextureAtlasSprite, point: Vec2) -> bool {
+    let half_size = 0.5 * sprite.custom_size.unwrap() * transform.scale.truncate().abs();
+    (point - transform.translation.truncate()).abs().cmple(half_size).all()
+}
+
+/// The part of `sorted`, ordered along the x-axis by `x`, that lies between `min` and `max`. The broad phase units
+/// go through to only look at the others near them, when moving and picking targets
+fn within_x<T>(sorted: &[T], x: impl Fn(&T) -> f32, min: f32, max: f32) -> &[T] {
+    let start = sorted.partition_point(|item| x(item) < min);
+    let end = sorted.partition_point(|item| x(item) <= max).max(start);
+    &sorted[start..end]
+}
 
-const COOLDOWN: f32 = 1.;
+/// A request to deploy a unit, from whoever is in command of that side
+#[derive(Debug, Clone, Copy)]
+struct SpawnOrder {
+    side: Side,
+    position: Vec2,
+    skills: Skills,
+    /// Whether the unit was already paid for some other way than the side's money
+    prepaid: bool,
+}
 
-fn fighting_system(
+/// Deploys ordered units the side can afford
+fn spawn_order_system(
     mut commands: Commands,
-    time: Res<Time>,
     materials: Res<Materials>,
-    mut query: Query<(Entity, &mut Fighter, &Transform)>
+    mut counter: ResMut<UnitCounter>,
+    mut economy: ResMut<economy::Economy>,
+    mut prices: ResMut<economy::Prices>,
+    config: Res<MatchConfig>,
+    stances: Res<stance::DefaultStance>,
+    army_orders: Res<orders::ArmyOrders>,
+    rally_points: Res<orders::RallyPoints>,
+    zone: Res<zone::SpawnZone>,
+    lanes: Res<lanes::Lanes>,
+    mut supply: ResMut<supply::Supply>,
+    verbose: Res<verbose_log::VerboseLog>,
+    balance: Res<balance::Balance>,
+    ffa: Res<ffa::FreeForAll>,
+    mut orders: EventReader<SpawnOrder>,
 ) {
-    let (tx, rx) = sync_channel(query.iter_mut().len());
-
-    let delta = time.delta_seconds();
-
-    query
-        .par_iter_mut().for_each_mut(move |(ent, mut fighter, _)| {
-            fighter.attack_cooldown -= delta;
-            if fighter.attack_cooldown <= 0. {
-                fighter.attack_cooldown = 0.;
-                if let Some(fighting) = fighter.fighting {
-                    tx.send((ent, fighting, fighter.skills)).unwrap();
-                }
+    let mutators = config.mutators;
+    for order in orders.iter() {
+        if !mutators.allows(&order.skills) || supply.left(order.side) == 0 || ffa.out[order.side] {
+            continue
+        }
+        if !order.prepaid {
+            if !economy.spend(order.side, prices.price(order.side, &order.skills)) {
+                continue
             }
-        });
-
-    let mut rng = rand::thread_rng();
-
-    for (fighter, fought_ent, skills) in rx.into_iter() {
-        if let Ok((_, mut fought, f_trans)) = query.get_mut(fought_ent) {
-            if rng.gen_range(0..=skills.attack) > rng.gen_range(0..=fought.skills.defence) {
-                let dmg = rng.gen_range(1..=skills.strength);
-
-                let actual_dmg = dmg.saturating_sub(rng.gen_range(0..=fought.protection));
-
-                fought.hp = fought.hp.saturating_sub(actual_dmg);
-
-                let mut transform = Transform::from_translation(f_trans.translation);
-
-                transform.translation.y += 45.;
-                transform.translation.z += 1.;
+            prices.record(order.side, &order.skills);
+        }
+        let skills = mutators.apply(balance.apply(order.skills));
+        // Units go in whichever lane they're placed closest to
+        let mut position = order.position;
+        position.y = lanes.clamp(lanes.lane_of(position.y), position.y);
+        supply.used[order.side] += 1;
+        let ent = spawn_fighter(&mut commands, position, mutators.unit_size(), order.side, &materials, skills);
+        let name = UnitName {
+            side: order.side,
+            kind: order.skills.name,
+            number: counter.next(order.side),
+        };
+        if verbose.0 {
+            info!(unit = %name, x = position.x, y = position.y, prepaid = order.prepaid, "deployed");
+        }
+        commands.entity(ent).insert((name, stances.0[order.side]));
+        // Joining an army that's falling back, it stays put in the spawn zone with the rest
+        if army_orders.0[order.side] == orders::Order::FallBack && !skills.is_structure() {
+            commands.entity(ent).insert(orders::FallingBack::default());
+        }
+        // Units deployed in the spawn zone gather at the rally point first, rather than ones dropped in elsewhere
+        let rally_point = rally_points.0[order.side].filter(|_| zone.contains(order.side, position));
+        if let Some(point) = rally_point.filter(|_| !skills.is_structure()) {
+            commands.entity(ent).insert(orders::Waypoint(point));
+        }
+    }
+}
 
-                let ent = commands.spawn(Text2dBundle {
-                    text: Text {
-                        sections: vec![
-                            TextSection {
-                                value: format!("{}", actual_dmg),
-                                style: TextStyle {
-                                    font: materials.font.clone(),
-                                    font_size: 20.,
-                                    color: Color::rgb(0., 0., 0.),
-                                }
-                            }
-                        ],
-                        .. Default::default()
-                    },
-                    transform: transform.clone()*Transform::from_translation(Vec3::new(0., 0., 2.)),
-                    .. Default::default()
-                }).id();
-                commands.spawn(SpriteBundle {
-                    transform,
-                    sprite: Sprite {
-                        color: materials.red,
-                        custom_size: Some(Vec2::new(15., 15.)),
-                        .. default()
-                    },
-                    .. default()
-                }).insert(Timeout::new(1.15).tied_to(vec![ent]));
+/// What clicking on the battlefield does, depending on the keys held
+#[derive(Debug, Clone, Copy)]
+enum ClickAction {
+    /// Drag out a row of the selected unit in the spawn zone
+    Deploy,
+    /// Drop the selected unit anywhere on the own half
+    Airdrop,
+    Build(Skills),
+    Trap(traps::TrapKind),
+    /// Send one of the own units back to be sold
+    Recall,
+    /// Put up the rally flag newly deployed units gather at
+    Rally,
+    /// Switch the stance of one of the own units
+    Stance,
+}
 
-                if fought.hp <= 0 {
-                    commands.entity(fought_ent).despawn_recursive();
-                }
-            }
+impl ClickAction {
+    fn held(keyboard_input: &Input<KeyCode>, bindings: &bindings::Bindings) -> Self {
+        if keyboard_input.pressed(bindings.build_tower) {
+            ClickAction::Build(tower::ARROW_TOWER)
+        } else if keyboard_input.pressed(bindings.build_wall) {
+            ClickAction::Build(tower::WALL)
+        } else if keyboard_input.pressed(bindings.airdrop) {
+            ClickAction::Airdrop
+        } else if keyboard_input.pressed(bindings.mine) {
+            ClickAction::Trap(traps::TrapKind::Mine)
+        } else if keyboard_input.pressed(bindings.snare) {
+            ClickAction::Trap(traps::TrapKind::Snare)
+        } else if keyboard_input.pressed(bindings.recall) {
+            ClickAction::Recall
+        } else if keyboard_input.pressed(bindings.rally) {
+            ClickAction::Rally
+        } else if keyboard_input.pressed(bindings.unit_stance) {
+            ClickAction::Stance
         } else {
-            let (_, mut fighter, _) = query.get_mut(fighter).unwrap();
-            fighter.fighting = None;
+            ClickAction::Deploy
         }
-        let (_, mut fighter, _) = query.get_mut(fighter).unwrap();
-        fighter.attack_cooldown += COOLDOWN;
     }
 }
 
+/// Minimum distance between units placed in one drag
+const PLACEMENT_SPACING: f32 = 34.;
+
+/// Where a placement drag started
+#[derive(Debug, Clone, Copy)]
+struct PlacementDrag {
+    button: MouseButton,
+    side: Side,
+    start: Vec2,
+}
+
 fn soldier_placement_system(
-    mut commands: Commands,
+    mut orders: EventWriter<SpawnOrder>,
     mouse_loc: Res<MouseLoc>,
-    materials: Res<Materials>,
     mouse_button: Res<Input<MouseButton>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    options: Res<ControlOptions>,
+    bindings: Res<bindings::Bindings>,
+    config: Res<MatchConfig>,
+    zone: Res<zone::SpawnZone>,
+    economy: Res<economy::Economy>,
+    prices: Res<economy::Prices>,
+    tech: Res<tech::Tech>,
+    supply: Res<supply::Supply>,
+    selected: Res<SelectedUnit>,
+    button_query: Query<&Interaction, With<Button>>,
+    mut drag: Local<Option<PlacementDrag>>,
 ) {
-    for button in mouse_button.get_just_pressed() {
-        let flipped;
-        match button {
-            MouseButton::Right => flipped = false,
-            MouseButton::Left => flipped = true,
-            MouseButton::Middle => {
-                eprintln!("{:?}", mouse_loc.0);
-                continue
-            }
-            _ => continue,
+    for &button in mouse_button.get_just_pressed() {
+        // Either mouse button deploys the selected unit for whoever's zone it's clicked in
+        if options.button_side(&bindings, button).is_none() {
+            continue
         }
+        let Some(side) = zone.side_at(mouse_loc.0) else {
+            continue
+        };
+        // The AI commands the opponent's army, or the other player does online
+        if (config.versus_ai || config.online) && side != options.player_side {
+            continue
+        }
+        let deploying = matches!(ClickAction::held(&keyboard_input, &bindings), ClickAction::Deploy);
+        if !deploying || toolbar::hovered(&button_query) {
+            continue
+        }
+        *drag = Some(PlacementDrag { button, side, start: mouse_loc.0 });
+    }
 
-        spawn_fighter(&mut commands, mouse_loc.0.x, mouse_loc.0.y, flipped, &materials, Skills {
-            attack: 30,
-            defence: 1,
-            hp: 20,
-            strength: 5,
-            speed: 35,
-            siege: 5,
+    let Some(PlacementDrag { button, side, start }) = *drag else {
+        return
+    };
+    if !mouse_button.just_released(button) {
+        return
+    }
+    *drag = None;
+
+    // Spread units evenly along the drag, as many as fit and can be afforded
+    let end = zone.clamp(side, mouse_loc.0);
+    let skills = selected.skills();
+    if !tech.unlocked(side, &skills) {
+        return
+    }
+    let affordable = economy.money[side] / prices.price(side, &skills);
+    let fitting = (start.distance(end) / PLACEMENT_SPACING) as u32 + 1;
+    let count = fitting.min(affordable).min(supply.left(side));
+
+    for i in 0..count {
+        let t = if fitting > 1 { i as f32 / (fitting - 1) as f32 } else { 0. };
+        orders.send(SpawnOrder {
+            side,
+            position: start.lerp(end, t),
+            skills,
+            prepaid: false,
         });
     }
 }
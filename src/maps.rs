@@ -0,0 +1,387 @@
+//! Battlefields to fight over, described in map files. The maps that come with
+//! the game are built in, and any `.map` files in the `maps` folder are added after them.
+//! Those are loaded again whenever they change, even mid-match.
+//!
+//! A map file has one setting per line, its name and values separated by tabs:
+//!
+//! ```text
+//! name            Rocky pass
+//! zone_width      80              how far into the battlefield each side deploys
+//! starting_money  50
+//! lanes           3               how many lanes the battlefield is split into, up to 5
+//! winning_score   100             control needed to win the battle
+//! size            1280 720        width and height when there's no window to fit the battlefield to
+//! rock            0   0.6   40    x, y and radius, any number of these
+//! tree            0.5 -0.5  15
+//! hill            -0.7  0   60    x, y and radius, any number of these
+//! river           0   60  -0.5 0.5    x, width and the y of each bridge
+//! ```
+//!
+//! Positions are fractions of the battlefield's half-size, so `-1` is the left or bottom edge
+//! and `1` the right or top edge. Lines starting with `#` are comments. Maps without a `lanes`
+//! line aren't split up, and the zones may take up less than half of the width in `size` each.
+
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use bevy::prelude::*;
+
+use crate::terrain::ObstacleKind;
+
+/// Where players keep maps of their own
+pub const MAPS_DIR: &str = "maps";
+/// How often the maps folder is checked for changed files
+const RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+/// Most lanes a map can be split into
+pub const MAX_LANES: usize = 5;
+
+const BUILT_IN: [&str; 6] = [
+    include_str!("../assets/maps/open_field.map"),
+    include_str!("../assets/maps/rocky_pass.map"),
+    include_str!("../assets/maps/forest.map"),
+    include_str!("../assets/maps/hills.map"),
+    include_str!("../assets/maps/two_bridges.map"),
+    include_str!("../assets/maps/last_bridge.map"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ObstacleDescriptor {
+    pub kind: ObstacleKind,
+    pub position: Vec2,
+    pub radius: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HillDescriptor {
+    pub position: Vec2,
+    pub radius: f32,
+}
+
+/// A river running from top to bottom at `x`, that can only be crossed at the bridges' `y`s
+#[derive(Debug, Clone, PartialEq)]
+pub struct RiverDescriptor {
+    pub x: f32,
+    pub width: f32,
+    pub bridges: Vec<f32>,
+}
+
+/// Everything about a battlefield that's set before the match starts
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapDescriptor {
+    pub name: String,
+    /// How far into the battlefield each side may deploy units
+    pub zone_width: f32,
+    pub starting_money: u32,
+    /// How many lanes the battlefield is split into, 0 if the map doesn't say, leaving it to the lanes rule
+    pub lanes: usize,
+    /// Score at which a side has taken full control of the battlefield
+    pub winning_score: i32,
+    pub obstacles: Vec<ObstacleDescriptor>,
+    pub hills: Vec<HillDescriptor>,
+    pub river: Option<RiverDescriptor>,
+    /// Size of the battlefield in pixels when there's no window for it to fill, as when running headless
+    pub size: Vec2,
+    /// Where the map was loaded from, none for the built in maps
+    pub file: Option<PathBuf>,
+}
+
+impl Default for MapDescriptor {
+    fn default() -> Self {
+        MapDescriptor {
+            name: "Untitled".to_owned(),
+            zone_width: 80.,
+            starting_money: 50,
+            lanes: 0,
+            winning_score: 100,
+            obstacles: Vec::new(),
+            hills: Vec::new(),
+            river: None,
+            size: Vec2::new(1280., 720.),
+            file: None,
+        }
+    }
+}
+
+impl MapDescriptor {
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut map = MapDescriptor::default();
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue
+            }
+            let error = |e: &str| format!("line {}: {e}", i + 1);
+            let mut fields = line.split('\t').map(str::trim).filter(|f| !f.is_empty());
+            let name = fields.next().unwrap_or_default();
+            if name == "name" {
+                map.name = fields.collect::<Vec<_>>().join(" ");
+                continue
+            }
+            let values: Vec<f32> = fields
+                .map(|f| f.parse().ok().filter(|v: &f32| v.is_finite()))
+                .collect::<Option<_>>()
+                .ok_or_else(|| error("expected numbers"))?;
+            let exactly = |n: usize| if values.len() == n {
+                Ok(())
+            } else {
+                Err(error(&format!("{name} takes {n} numbers")))
+            };
+            match name {
+                "zone_width" => {
+                    exactly(1)?;
+                    map.zone_width = values[0];
+                }
+                "starting_money" => {
+                    exactly(1)?;
+                    map.starting_money = values[0] as u32;
+                }
+                "lanes" => {
+                    exactly(1)?;
+                    if values[0].fract() != 0. || !(1. ..=MAX_LANES as f32).contains(&values[0]) {
+                        return Err(error(&format!("lanes must be a whole number from 1 to {MAX_LANES}")))
+                    }
+                    map.lanes = values[0] as usize;
+                }
+                "winning_score" => {
+                    exactly(1)?;
+                    map.winning_score = values[0] as i32;
+                }
+                "size" => {
+                    exactly(2)?;
+                    map.size = Vec2::new(values[0], values[1]);
+                }
+                "rock" | "tree" => {
+                    exactly(3)?;
+                    map.obstacles.push(ObstacleDescriptor {
+                        kind: if name == "rock" { ObstacleKind::Rock } else { ObstacleKind::Tree },
+                        position: Vec2::new(values[0], values[1]),
+                        radius: values[2],
+                    });
+                }
+                "hill" => {
+                    exactly(3)?;
+                    map.hills.push(HillDescriptor {
+                        position: Vec2::new(values[0], values[1]),
+                        radius: values[2],
+                    });
+                }
+                "river" => {
+                    if values.len() < 2 {
+                        return Err(error("river takes an x, a width and any number of bridges"))
+                    }
+                    map.river = Some(RiverDescriptor {
+                        x: values[0],
+                        width: values[1],
+                        bridges: values[2..].to_vec(),
+                    });
+                }
+                _ => return Err(error(&format!("unknown setting {name}"))),
+            }
+        }
+        if map.zone_width <= 0. || map.winning_score <= 0 || map.size.cmple(Vec2::ZERO).any() {
+            return Err("zone_width, winning_score and size must be above 0".to_owned())
+        }
+        if map.zone_width >= map.size.x / 2. {
+            return Err("zone_width must be less than half the width in size".to_owned())
+        }
+        Ok(map)
+    }
+
+    /// The map in the format [`MapDescriptor::parse`] reads
+    pub fn to_file(&self) -> String {
+        let mut contents = format!(
+            "name\t{}\nzone_width\t{}\nstarting_money\t{}\nwinning_score\t{}\nsize\t{}\t{}\n",
+            self.name, self.zone_width, self.starting_money, self.winning_score, self.size.x, self.size.y,
+        );
+        if self.lanes > 0 {
+            let _ = writeln!(contents, "lanes\t{}", self.lanes);
+        }
+        for obstacle in &self.obstacles {
+            let kind = match obstacle.kind {
+                ObstacleKind::Rock => "rock",
+                ObstacleKind::Tree => "tree",
+            };
+            let _ = writeln!(contents, "{kind}\t{}\t{}\t{}", obstacle.position.x, obstacle.position.y, obstacle.radius);
+        }
+        for hill in &self.hills {
+            let _ = writeln!(contents, "hill\t{}\t{}\t{}", hill.position.x, hill.position.y, hill.radius);
+        }
+        if let Some(river) = &self.river {
+            let _ = write!(contents, "river\t{}\t{}", river.x, river.width);
+            for y in &river.bridges {
+                let _ = write!(contents, "\t{y}");
+            }
+            contents.push('\n');
+        }
+        contents
+    }
+
+    /// Writes the map to its file, or a new one in the maps folder named after it
+    pub fn save(&mut self) -> std::io::Result<&Path> {
+        let path = match &self.file {
+            Some(path) => path.clone(),
+            None => {
+                fs::create_dir_all(MAPS_DIR)?;
+                let stem: String = self.name.to_lowercase()
+                    .chars()
+                    .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                    .collect();
+                let mut path = Path::new(MAPS_DIR).join(format!("{stem}.map"));
+                for i in 2.. {
+                    if !path.exists() {
+                        break
+                    }
+                    path = Path::new(MAPS_DIR).join(format!("{stem}_{i}.map"));
+                }
+                path
+            }
+        };
+        fs::write(&path, self.to_file())?;
+        Ok(self.file.insert(path))
+    }
+}
+
+/// Every map to pick from, the built in ones first
+#[derive(Debug, Clone, Resource)]
+pub struct Maps(pub Vec<MapDescriptor>);
+
+impl Maps {
+    pub fn load() -> Self {
+        let mut maps: Vec<_> = BUILT_IN.iter()
+            .map(|contents| MapDescriptor::parse(contents).expect("Built in map is invalid."))
+            .collect();
+
+        maps.extend(map_files().iter().filter_map(|path| load_map(path)));
+        Maps(maps)
+    }
+}
+
+/// The map files in the maps folder, in the order they're listed in the menu
+fn map_files() -> Vec<PathBuf> {
+    let mut paths: Vec<_> = fs::read_dir(MAPS_DIR)
+        .map(|dir| dir.filter_map(|entry| entry.ok().map(|e| e.path())).collect())
+        .unwrap_or_default();
+    paths.retain(|path| path.extension().is_some_and(|ext| ext == "map"));
+    paths.sort();
+    paths
+}
+
+fn load_map(path: &Path) -> Option<MapDescriptor> {
+    let result = fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|contents| MapDescriptor::parse(&contents));
+    match result {
+        Ok(map) => Some(MapDescriptor {
+            file: Some(path.to_owned()),
+            ..map
+        }),
+        Err(e) => {
+            warn!("Could not load map {}: {e}", path.display());
+            None
+        }
+    }
+}
+
+/// When each map file was last seen changed
+#[derive(Debug, Resource)]
+pub struct MapWatcher {
+    timer: Timer,
+    modified: HashMap<PathBuf, SystemTime>,
+}
+
+impl Default for MapWatcher {
+    fn default() -> Self {
+        MapWatcher {
+            timer: Timer::new(RELOAD_INTERVAL, TimerMode::Repeating),
+            modified: HashMap::new(),
+        }
+    }
+}
+
+/// Loads map files again when they change and picks up new ones, so maps can be tweaked without restarting.
+/// The terrain and zones follow right away, the starting money from the next match on
+pub fn map_reload_system(time: Res<Time>, mut watcher: ResMut<MapWatcher>, mut maps: ResMut<Maps>) {
+    // Keeps checking while the game is paused
+    if !watcher.timer.tick(time.raw_delta()).just_finished() {
+        return
+    }
+    for path in map_files() {
+        let Ok(modified) = fs::metadata(&path).and_then(|metadata| metadata.modified()) else {
+            continue
+        };
+        if watcher.modified.insert(path.clone(), modified) == Some(modified) {
+            continue
+        }
+        let Some(map) = load_map(&path) else {
+            continue
+        };
+        match maps.0.iter().position(|m| m.file.as_ref() == Some(&path)) {
+            Some(i) if maps.0[i] == map => (),
+            Some(i) => {
+                info!("Reloaded map {}", path.display());
+                maps.0[i] = map;
+            }
+            None => maps.0.push(map),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_maps_parse() {
+        for contents in BUILT_IN {
+            assert!(MapDescriptor::parse(contents).is_ok(), "{contents}");
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let contents = "name\tTest map\nzone_width\t100\nlanes\t3\nsize\t800\t600\n# A comment\n\
+            rock\t0\t0.5\t40\ntree\t-0.5\t0.5\t15\nhill\t0.5\t-0.5\t60\nriver\t0\t60\t-0.5\t0.5\n";
+        let map = MapDescriptor::parse(contents).unwrap();
+        assert_eq!(map.name, "Test map");
+        assert_eq!(map.lanes, 3);
+        assert_eq!(map.size, Vec2::new(800., 600.));
+        assert_eq!(map.obstacles.len(), 2);
+        assert_eq!(map.river.as_ref().map(|river| river.bridges.len()), Some(2));
+        assert_eq!(MapDescriptor::parse(&map.to_file()), Ok(map));
+    }
+
+    #[test]
+    fn without_lanes() {
+        let map = MapDescriptor::parse("name\tOpen").unwrap();
+        assert_eq!(map.lanes, 0);
+        assert!(!map.to_file().contains("lanes"));
+    }
+
+    #[test]
+    fn rejects_bad_lanes() {
+        for lanes in ["0", "-1", "1.5", "6", "inf"] {
+            assert!(MapDescriptor::parse(&format!("lanes\t{lanes}")).is_err(), "{lanes}");
+        }
+    }
+
+    #[test]
+    fn rejects_wide_zones() {
+        assert!(MapDescriptor::parse("zone_width\t0").is_err());
+        assert!(MapDescriptor::parse("zone_width\t640").is_err());
+        assert!(MapDescriptor::parse("zone_width\t300\nsize\t500\t500").is_err());
+        assert!(MapDescriptor::parse("zone_width\t300\nsize\t800\t500").is_ok());
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        assert_eq!(MapDescriptor::parse("name\tA\nrock\t0\t0").unwrap_err(), "line 2: rock takes 3 numbers");
+        assert_eq!(MapDescriptor::parse("size\tbig\t500").unwrap_err(), "line 1: expected numbers");
+        assert_eq!(MapDescriptor::parse("volcano\t0").unwrap_err(), "line 1: unknown setting volcano");
+        assert!(MapDescriptor::parse("river\t0").is_err());
+    }
+}
@@ -0,0 +1,66 @@
+//! The randomness a match is played out with. Everything that decides how the battle goes, like combat rolls,
+//! healing, the AI, weather and mercenary offers, draws from [`MatchRng`] on the fixed tick rather than from
+//! `thread_rng`, so a match started from the same seed with the same orders plays out the same.
+//! Particles, popups and whatever else is purely for show stay on `thread_rng`.
+
+use bevy::prelude::*;
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+/// Seeded anew at the start of every match
+#[derive(Debug, Clone, Resource)]
+pub struct MatchRng {
+    /// Seed the next match is played with, or none to pick a fresh one every match
+    next_seed: Option<u64>,
+    /// Seed the current match was started from
+    pub seed: u64,
+    rng: StdRng,
+}
+
+impl Default for MatchRng {
+    fn default() -> Self {
+        MatchRng::new(None)
+    }
+}
+
+impl MatchRng {
+    /// Plays the first match from `seed` and every match after it from the seed after the last, if given
+    pub fn new(seed: Option<u64>) -> Self {
+        let first = seed.unwrap_or_else(rand::random);
+        MatchRng {
+            next_seed: seed,
+            seed: first,
+            rng: StdRng::seed_from_u64(first),
+        }
+    }
+    fn reseed(&mut self) {
+        self.seed = match &mut self.next_seed {
+            Some(next) => {
+                let seed = *next;
+                *next = next.wrapping_add(1);
+                seed
+            }
+            None => rand::random(),
+        };
+        self.rng = StdRng::seed_from_u64(self.seed);
+    }
+}
+
+impl RngCore for MatchRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+pub fn reset_match_rng(mut rng: ResMut<MatchRng>) {
+    rng.reseed();
+    info!(seed = rng.seed, "match started");
+}
@@ -0,0 +1,194 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    campaign::Campaign,
+    combat::{DamageEvent, DeathEvent},
+    economy::{Earnings, Economy},
+    locale::Locale,
+    sound::SiegeEvent,
+    GameState, Materials, MatchConfig, MatchOverEvent, PerSide, Side, Team, UnitName,
+};
+
+/// How one side did over a match
+#[derive(Debug, Default, Clone)]
+pub struct SideStats {
+    /// How many of each kind of unit it deployed
+    deployed: BTreeMap<&'static str, u32>,
+    kills: u32,
+    damage_dealt: u32,
+    damage_taken: u32,
+    money_earned: u32,
+    money_spent: u32,
+    sieges: u32,
+}
+
+/// What happened in the current or last match, for the screen after it
+#[derive(Debug, Default, Clone, Resource)]
+pub struct MatchStats {
+    sides: PerSide<SideStats>,
+    /// Seconds of battle
+    duration: f32,
+    winner: Option<Side>,
+}
+
+impl MatchStats {
+    pub fn winner(&self) -> Option<Side> {
+        self.winner
+    }
+    pub fn duration(&self) -> f32 {
+        self.duration
+    }
+    pub fn kills(&self, side: Side) -> u32 {
+        self.sides[side].kills
+    }
+}
+
+pub fn reset_match_stats(mut stats: ResMut<MatchStats>) {
+    *stats = MatchStats::default();
+}
+
+/// Runs before dead units are removed, so it can still tell whose they were
+pub fn match_stats_system(
+    fixed_time: Res<FixedTime>,
+    mut stats: ResMut<MatchStats>,
+    mut damage_events: EventReader<DamageEvent>,
+    mut death_events: EventReader<DeathEvent>,
+    mut siege_events: EventReader<SiegeEvent>,
+    deployed_query: Query<&UnitName, Added<UnitName>>,
+    side_query: Query<&Team>,
+) {
+    stats.duration += fixed_time.period.as_secs_f32();
+    for name in deployed_query.iter() {
+        *stats.sides[name.side].deployed.entry(name.kind).or_default() += 1;
+    }
+    for event in damage_events.iter() {
+        if let Ok(&Team(side)) = side_query.get(event.target) {
+            stats.sides[side].damage_taken += event.amount as u32;
+            stats.sides[event.attacker_side].damage_dealt += event.amount as u32;
+        }
+    }
+    for event in death_events.iter() {
+        stats.sides[event.killer_side].kills += 1;
+    }
+    for event in siege_events.iter() {
+        stats.sides[event.name.side].sieges += 1;
+    }
+}
+
+/// Takes what's left to know from the economy and who won, as the match ends
+pub fn finish_match_stats(
+    mut stats: ResMut<MatchStats>,
+    mut match_over_events: EventReader<MatchOverEvent>,
+    economy: Res<Economy>,
+) {
+    if let Some(event) = match_over_events.iter().last() {
+        stats.winner = Some(event.winner);
+    }
+    for side in Side::ALL {
+        let Earnings { passive, kills, siege, interest } = economy.earnings[side];
+        stats.sides[side].money_earned = passive + kills + siege + interest;
+        stats.sides[side].money_spent = economy.spent[side];
+    }
+}
+
+impl SideStats {
+    fn lines(&self, locale: &Locale) -> Vec<String> {
+        let mut lines = vec![
+            locale.format("results.kills", &[&self.kills]),
+            locale.format("results.damage_dealt", &[&self.damage_dealt]),
+            locale.format("results.damage_taken", &[&self.damage_taken]),
+            locale.format("results.money_earned", &[&self.money_earned]),
+            locale.format("results.money_spent", &[&self.money_spent]),
+            locale.format("results.sieges", &[&self.sieges]),
+            locale.text("results.deployed").to_owned(),
+        ];
+        lines.extend(self.deployed.iter().map(|(kind, count)| format!("  {count} {}", locale.name(kind))));
+        lines
+    }
+}
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct ResultsRoot;
+
+/// Whether the match just played was part of a campaign run that goes on
+fn campaign_goes_on(config: &MatchConfig, campaign: &Campaign) -> bool {
+    config.campaign && campaign.run.is_some()
+}
+
+pub fn setup_results(
+    mut commands: Commands,
+    materials: Res<Materials>,
+    stats: Res<MatchStats>,
+    config: Res<MatchConfig>,
+    campaign: Res<Campaign>,
+    locale: Res<Locale>,
+) {
+    let style = |font_size| TextStyle {
+        font: materials.font.clone(),
+        font_size,
+        color: Color::WHITE,
+    };
+    let minutes = stats.duration as u32 / 60;
+    let seconds = stats.duration as u32 % 60;
+
+    commands.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            gap: Size::all(Val::Px(10.)),
+            ..default()
+        },
+        background_color: Color::rgba(0., 0., 0., 0.75).into(),
+        z_index: ZIndex::Global(5),
+        ..default()
+    })
+    .insert(ResultsRoot)
+    .with_children(|parent| {
+        let title = match stats.winner {
+            Some(side) => locale.format("results.wins", &[&locale.name(&side.to_string())]),
+            None => locale.text("results.over").to_owned(),
+        };
+        parent.spawn(TextBundle::from_section(title, style(60.)));
+        let duration = locale.format("results.duration", &[&format!("{minutes}:{seconds:02}")]);
+        parent.spawn(TextBundle::from_section(duration, style(24.)));
+        parent.spawn(NodeBundle {
+            style: Style {
+                gap: Size::all(Val::Px(60.)),
+                ..default()
+            },
+            ..default()
+        }).with_children(|columns| {
+            for &side in config.sides() {
+                let mut sections = vec![TextSection::new(format!("{}\n", locale.name(&side.to_string())), style(30.))];
+                sections.extend(stats.sides[side].lines(&locale).into_iter().map(|line| TextSection::new(line + "\n", style(20.))));
+                columns.spawn(TextBundle::from_sections(sections));
+            }
+        });
+        let back = if campaign_goes_on(&config, &campaign) { "results.to_camp" } else { "results.back" };
+        parent.spawn(TextBundle::from_section(locale.text(back), style(20.)));
+    });
+}
+
+pub fn results_input_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    config: Res<MatchConfig>,
+    campaign: Res<Campaign>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Return) && campaign_goes_on(&config, &campaign) {
+        next_state.set(GameState::Camp);
+    } else if keyboard_input.any_just_pressed([KeyCode::Return, KeyCode::Escape]) {
+        next_state.set(GameState::Menu);
+    }
+}
+
+pub fn cleanup_results(mut commands: Commands, query: Query<Entity, With<ResultsRoot>>) {
+    for ent in query.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+}
@@ -0,0 +1,295 @@
+use bevy::prelude::*;
+
+use crate::{
+    campaign::Campaign, draft::Draft, ladder::Ladder, locale::Locale, maps::Maps, mutators::Mutator, settings::Settings,
+    supply::SUPPLY_CAPS, unit_stats::UnitStats, GameState, MatchConfig, Materials, Side, UNITS,
+};
+
+/// How many past ratings the menu graph shows
+const GRAPH_LENGTH: usize = 20;
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct MenuRoot;
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct DifficultyText;
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct MapText;
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct SupplyText;
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct StrategyText;
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct MutatorText(Mutator);
+
+fn difficulty_line(config: &MatchConfig, locale: &Locale) -> String {
+    locale.format("menu.difficulty", &[&locale.name(config.difficulty.name())])
+}
+
+fn map_line(config: &MatchConfig, maps: &Maps, locale: &Locale) -> String {
+    locale.format("menu.map", &[&maps.0[config.map].name, &(config.map + 1), &maps.0.len()])
+}
+
+fn supply_line(config: &MatchConfig, locale: &Locale) -> String {
+    match SUPPLY_CAPS[config.supply_cap] {
+        Some(cap) => locale.format("menu.supply_cap", &[&cap]),
+        None => locale.text("menu.no_supply_cap").to_owned(),
+    }
+}
+
+fn strategy_line(config: &MatchConfig, locale: &Locale) -> String {
+    locale.format("menu.strategies", &[
+        &locale.name(config.strategies[Side::Left].name()),
+        &locale.name(config.strategies[Side::Right].name()),
+    ])
+}
+
+fn campaign_line(campaign: &Campaign, locale: &Locale) -> String {
+    match (&campaign.run, campaign.lost_after) {
+        (Some(run), _) => locale.format("menu.campaign_run", &[&(run.won + 1), &run.veterans.len()]),
+        (None, Some(won)) => locale.format("menu.campaign_lost", &[&won]),
+        (None, None) => locale.text("menu.campaign").to_owned(),
+    }
+}
+
+fn mutator_line(mutator: Mutator, config: &MatchConfig, locale: &Locale) -> String {
+    let check = if config.mutators.contains(mutator) { 'x' } else { ' ' };
+    format!("{:?}: [{check}] {}", mutator.key(), locale.name(mutator.name()))
+}
+
+pub fn setup_menu(
+    mut commands: Commands,
+    materials: Res<Materials>,
+    config: Res<MatchConfig>,
+    maps: Res<Maps>,
+    ladder: Res<Ladder>,
+    campaign: Res<Campaign>,
+    stats: Res<UnitStats>,
+    locale: Res<Locale>,
+) {
+    let style = |font_size| TextStyle {
+        font: materials.font.clone(),
+        font_size,
+        color: Color::WHITE,
+    };
+
+    commands.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            gap: Size::all(Val::Px(10.)),
+            ..default()
+        },
+        background_color: Color::rgba(0., 0., 0., 0.75).into(),
+        z_index: ZIndex::Global(5),
+        ..default()
+    })
+    .insert(MenuRoot)
+    .with_children(|parent| {
+        parent.spawn(TextBundle::from_section(locale.text("menu.title"), style(60.)));
+
+        if let Some((won, change)) = ladder.last_result {
+            let text = locale.format(if won { "menu.victory" } else { "menu.defeat" }, &[&format!("{change:+}")]);
+            parent.spawn(TextBundle::from_section(text, style(30.)));
+        }
+
+        parent.spawn(TextBundle::from_section(locale.format("menu.rating", &[&ladder.rating()]), style(30.)));
+        rating_graph(parent, &ladder.history);
+
+        parent.spawn(TextBundle::from_section(difficulty_line(&config, &locale), style(30.))).insert(DifficultyText);
+        parent.spawn(TextBundle::from_section(map_line(&config, &maps, &locale), style(24.))).insert(MapText);
+        parent.spawn(TextBundle::from_section(supply_line(&config, &locale), style(24.))).insert(SupplyText);
+        parent.spawn(TextBundle::from_section(strategy_line(&config, &locale), style(24.))).insert(StrategyText);
+        for mutator in Mutator::ALL {
+            parent.spawn(TextBundle::from_section(mutator_line(mutator, &config, &locale), style(20.)))
+                .insert(MutatorText(mutator));
+        }
+        parent.spawn(TextBundle::from_section(meta_line(&stats, &locale), style(16.)));
+        parent.spawn(TextBundle::from_section(campaign_line(&campaign, &locale), style(20.)));
+        parent.spawn(TextBundle::from_section(locale.text("menu.free_for_all"), style(20.)));
+        parent.spawn(TextBundle::from_section(locale.text("menu.controls"), style(20.)));
+    });
+}
+
+/// How the meta prices rule would currently adjust each unit's price
+fn meta_line(stats: &UnitStats, locale: &Locale) -> String {
+    let shifts: Vec<_> = UNITS.iter()
+        .map(|s| (s.name, ((stats.price_factor(s.name) - 1.) * 100.).round()))
+        .filter(|&(_, percent)| percent != 0.)
+        .map(|(name, percent)| format!("{} {percent:+}%", locale.name(name)))
+        .collect();
+    if shifts.is_empty() {
+        locale.text("menu.no_meta").to_owned()
+    } else {
+        locale.format("menu.meta", &[&shifts.join(", ")])
+    }
+}
+
+fn rating_graph(parent: &mut ChildBuilder, history: &[i32]) {
+    let recent = &history[history.len().saturating_sub(GRAPH_LENGTH)..];
+    let min = recent.iter().copied().min().unwrap_or(0);
+    let max = recent.iter().copied().max().unwrap_or(0);
+
+    parent.spawn(NodeBundle {
+        style: Style {
+            size: Size::new(Val::Px(10. * GRAPH_LENGTH as f32), Val::Px(60.)),
+            align_items: AlignItems::FlexEnd,
+            ..default()
+        },
+        background_color: Color::rgba(1., 1., 1., 0.1).into(),
+        ..default()
+    }).with_children(|graph| {
+        for &rating in recent {
+            let fraction = if max > min { (rating - min) as f32 / (max - min) as f32 } else { 0.5 };
+            graph.spawn(NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Px(8.), Val::Percent(10. + 90. * fraction)),
+                    margin: UiRect::horizontal(Val::Px(1.)),
+                    ..default()
+                },
+                background_color: Color::rgb(0.5, 0.5, 1.0).into(),
+                ..default()
+            });
+        }
+    });
+}
+
+pub fn menu_input_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut config: ResMut<MatchConfig>,
+    maps: Res<Maps>,
+    mut settings: ResMut<Settings>,
+    mut draft: ResMut<Draft>,
+    mut next_state: ResMut<NextState<GameState>>,
+    locale: Res<Locale>,
+    mut text_query: Query<&mut Text, With<DifficultyText>>,
+    mut map_query: Query<&mut Text, (With<MapText>, Without<DifficultyText>)>,
+    mut supply_query: Query<&mut Text, (With<SupplyText>, Without<DifficultyText>, Without<MapText>)>,
+    mut strategy_query: Query<&mut Text, (With<StrategyText>, Without<DifficultyText>, Without<MapText>, Without<SupplyText>)>,
+    mut mutator_query: Query<(&mut Text, &MutatorText), (Without<DifficultyText>, Without<MapText>, Without<SupplyText>, Without<StrategyText>)>,
+) {
+    // The difficulty is a setting like those on the settings screen, so it's saved the same way
+    if keyboard_input.just_pressed(KeyCode::Left) {
+        let easier = settings.difficulty().easier();
+        settings.pick_difficulty(easier);
+        settings.save();
+    }
+    if keyboard_input.just_pressed(KeyCode::Right) {
+        let harder = settings.difficulty().harder();
+        settings.pick_difficulty(harder);
+        settings.save();
+    }
+    if keyboard_input.just_pressed(KeyCode::Down) {
+        config.map = (config.map + 1) % maps.0.len();
+    }
+    if keyboard_input.just_pressed(KeyCode::Up) {
+        config.map = (config.map + maps.0.len() - 1) % maps.0.len();
+    }
+    if keyboard_input.just_pressed(KeyCode::PageUp) {
+        config.supply_cap = (config.supply_cap + 1) % SUPPLY_CAPS.len();
+    }
+    if keyboard_input.just_pressed(KeyCode::PageDown) {
+        config.supply_cap = (config.supply_cap + SUPPLY_CAPS.len() - 1) % SUPPLY_CAPS.len();
+    }
+    if keyboard_input.just_pressed(KeyCode::Q) {
+        config.strategies[Side::Left] = config.strategies[Side::Left].next();
+    }
+    if keyboard_input.just_pressed(KeyCode::E) {
+        config.strategies[Side::Right] = config.strategies[Side::Right].next();
+    }
+    for mutator in Mutator::ALL {
+        if keyboard_input.just_pressed(mutator.key()) {
+            config.mutators.toggle(mutator);
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        config.versus_ai = true;
+        config.spectating = false;
+        config.campaign = false;
+        config.draft = false;
+        config.extra_sides = 0;
+        next_state.set(GameState::Playing);
+    }
+    if keyboard_input.just_pressed(KeyCode::H) {
+        config.versus_ai = false;
+        config.spectating = false;
+        config.campaign = false;
+        config.draft = false;
+        config.extra_sides = 0;
+        next_state.set(GameState::Playing);
+    }
+    if keyboard_input.just_pressed(KeyCode::W) {
+        config.versus_ai = true;
+        config.spectating = true;
+        config.campaign = false;
+        config.draft = false;
+        config.extra_sides = 0;
+        next_state.set(GameState::Playing);
+    }
+    // Continues the saved run, or starts a new one
+    if keyboard_input.just_pressed(KeyCode::C) {
+        config.versus_ai = true;
+        config.spectating = false;
+        config.campaign = true;
+        config.draft = false;
+        config.extra_sides = 0;
+        next_state.set(GameState::Playing);
+    }
+    if keyboard_input.just_pressed(KeyCode::R) {
+        config.versus_ai = true;
+        config.spectating = false;
+        config.campaign = false;
+        config.draft = true;
+        config.extra_sides = 0;
+        *draft = Draft::default();
+        next_state.set(GameState::Draft);
+    }
+    // Against two AIs, or three
+    for (key, extra_sides) in [(KeyCode::F, 1), (KeyCode::G, 2)] {
+        if keyboard_input.just_pressed(key) {
+            config.versus_ai = true;
+            config.spectating = false;
+            config.campaign = false;
+            config.draft = false;
+            config.extra_sides = extra_sides;
+            next_state.set(GameState::Playing);
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::S) {
+        next_state.set(GameState::Settings);
+    }
+    if keyboard_input.just_pressed(KeyCode::D) {
+        next_state.set(GameState::Editor);
+    }
+
+    if config.is_changed() {
+        for mut text in text_query.iter_mut() {
+            text.sections[0].value = difficulty_line(&config, &locale);
+        }
+        for mut text in map_query.iter_mut() {
+            text.sections[0].value = map_line(&config, &maps, &locale);
+        }
+        for mut text in supply_query.iter_mut() {
+            text.sections[0].value = supply_line(&config, &locale);
+        }
+        for mut text in strategy_query.iter_mut() {
+            text.sections[0].value = strategy_line(&config, &locale);
+        }
+        for (mut text, &MutatorText(mutator)) in mutator_query.iter_mut() {
+            text.sections[0].value = mutator_line(mutator, &config, &locale);
+        }
+    }
+}
+
+pub fn cleanup_menu(mut commands: Commands, query: Query<Entity, With<MenuRoot>>) {
+    for ent in query.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+}
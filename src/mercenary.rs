@@ -0,0 +1,187 @@
+use bevy::prelude::*;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    bindings::Bindings, combat::{ArmorClass, DamageType}, economy::Economy, locale::Locale, match_rng::MatchRng,
+    supply::Supply, zone::SpawnZone, ControlOptions, MatchConfig, Materials, Skills, SpawnOrder, FIGHTER,
+};
+
+/// Range of seconds between offers
+const OFFER_INTERVAL: std::ops::Range<f32> = 45.0..75.0;
+/// How long an offer stands
+const OFFER_TIME: f32 = 10.;
+/// Range of discounts off a mercenary's price
+const DISCOUNT: std::ops::Range<f32> = 0.3..0.5;
+
+/// Elite units only available through offers
+const MERCENARIES: [Skills; 3] = [
+    Skills {
+        name: "Sellsword",
+        price: 18,
+        attack: 45,
+        defence: 3,
+        strength: 8,
+        hp: 35,
+        crit: 15,
+        ..FIGHTER
+    },
+    Skills {
+        name: "Knight-errant",
+        price: 25,
+        attack: 40,
+        defence: 6,
+        strength: 7,
+        hp: 50,
+        speed: 45,
+        damage_type: DamageType::Blunt,
+        armor: ArmorClass::Mail,
+        ..FIGHTER
+    },
+    Skills {
+        name: "Duelist",
+        price: 16,
+        attack: 50,
+        defence: 2,
+        strength: 6,
+        hp: 25,
+        dodge: 30,
+        damage_type: DamageType::Piercing,
+        ..FIGHTER
+    },
+];
+
+#[derive(Debug, Clone, Copy)]
+struct Offer {
+    skills: Skills,
+    price: u32,
+    time_left: f32,
+}
+
+/// The mercenary currently offering their services to the player, if any
+#[derive(Debug, Default, Clone, Copy, Resource)]
+pub struct MercenaryOffer {
+    offer: Option<Offer>,
+    next_in: f32,
+}
+
+/// Popup showing the current offer
+#[derive(Debug, Clone, Copy, Component)]
+pub struct OfferPopup;
+
+pub fn reset_offers(mut rng: ResMut<MatchRng>, mut offers: ResMut<MercenaryOffer>) {
+    *offers = MercenaryOffer {
+        offer: None,
+        next_in: rng.gen_range(OFFER_INTERVAL),
+    };
+}
+
+/// Makes new offers every so often and withdraws them when they run out
+pub fn offer_system(
+    fixed_time: Res<FixedTime>,
+    config: Res<MatchConfig>,
+    mut rng: ResMut<MatchRng>,
+    mut offers: ResMut<MercenaryOffer>,
+) {
+    // Mercenaries only deal with players
+    if config.spectating {
+        return
+    }
+    let delta = fixed_time.period.as_secs_f32();
+    match &mut offers.offer {
+        Some(offer) => {
+            offer.time_left -= delta;
+            if offer.time_left <= 0. {
+                offers.offer = None;
+            }
+        }
+        None => {
+            offers.next_in -= delta;
+            if offers.next_in <= 0. {
+                let skills = *MERCENARIES.choose(&mut *rng).unwrap();
+                let price = (skills.price as f32 * (1. - rng.gen_range(DISCOUNT))).round() as u32;
+                offers.offer = Some(Offer { skills, price, time_left: OFFER_TIME });
+                offers.next_in = rng.gen_range(OFFER_INTERVAL);
+            }
+        }
+    }
+}
+
+/// J hires the mercenary on offer into the player's spawn zone, K turns them away
+pub fn offer_response_system(
+    mut orders: EventWriter<SpawnOrder>,
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    options: Res<ControlOptions>,
+    zone: Res<SpawnZone>,
+    supply: Res<Supply>,
+    mut economy: ResMut<Economy>,
+    mut offers: ResMut<MercenaryOffer>,
+) {
+    let Some(offer) = offers.offer else {
+        return
+    };
+    let side = options.player_side;
+    if keyboard_input.just_pressed(bindings.decline_mercenary) {
+        offers.offer = None;
+    } else if keyboard_input.just_pressed(bindings.hire_mercenary)
+        && supply.left(side) > 0
+        && economy.spend(side, offer.price)
+    {
+        offers.offer = None;
+        orders.send(SpawnOrder {
+            side,
+            position: zone.0[side].center(),
+            skills: offer.skills,
+            prepaid: true,
+        });
+    }
+}
+
+pub fn offer_popup_system(
+    mut commands: Commands,
+    materials: Res<Materials>,
+    locale: Res<Locale>,
+    bindings: Res<Bindings>,
+    offers: Res<MercenaryOffer>,
+    mut query: Query<(Entity, &mut Text), With<OfferPopup>>,
+) {
+    let Some(offer) = offers.offer else {
+        for (ent, _) in query.iter() {
+            commands.entity(ent).despawn();
+        }
+        return
+    };
+    let value = locale.format("mercenary.offer", &[
+        &locale.name(offer.skills.name), &offer.price, &offer.skills.price,
+        &format!("{:?}", bindings.hire_mercenary), &format!("{:?}", bindings.decline_mercenary),
+        &offer.time_left.ceil(),
+    ]);
+    if let Ok((_, mut text)) = query.get_single_mut() {
+        text.sections[0].value = value;
+        return
+    }
+    commands.spawn(TextBundle {
+        text: Text::from_section(value, TextStyle {
+            font: materials.font.clone(),
+            font_size: 22.,
+            color: Color::rgb(1.0, 0.84, 0.0),
+        }),
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                bottom: Val::Px(60.),
+                left: Val::Px(5.),
+                ..default()
+            },
+            ..default()
+        },
+        background_color: Color::rgba(0., 0., 0., 0.6).into(),
+        ..default()
+    }).insert(OfferPopup);
+}
+
+pub fn cleanup_offers(mut commands: Commands, query: Query<Entity, With<OfferPopup>>) {
+    for ent in query.iter() {
+        commands.entity(ent).despawn();
+    }
+}
@@ -0,0 +1,73 @@
+use bevy::prelude::*;
+
+use crate::{
+    ai::spawn_banner, bindings::Bindings, locale::Locale, zone::SpawnZone,
+    Battlefield, ControlOptions, Materials, Skills, SpawnOrder, Team, UnitType, FIGHTER, PLACEMENT_SPACING,
+};
+
+/// Weak levy called up in an emergency
+const MILITIA: Skills = Skills {
+    name: "Militia",
+    price: 0,
+    attack: 15,
+    defence: 0,
+    strength: 2,
+    hp: 10,
+    speed: 30,
+    siege: 1,
+    crit: 0,
+    ..FIGHTER
+};
+
+/// Whether the player has used up their militia call this match
+#[derive(Debug, Default, Clone, Copy, Resource)]
+pub struct MilitiaCalled(bool);
+
+pub fn reset_militia(mut called: ResMut<MilitiaCalled>) {
+    *called = MilitiaCalled::default();
+}
+
+/// Once per match, its key fills the player's whole spawn zone with militia for free,
+/// but only once enemies have made it into the last quarter of the battlefield
+pub fn militia_call_system(
+    mut orders: EventWriter<SpawnOrder>,
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    battlefield: Res<Battlefield>,
+    options: Res<ControlOptions>,
+    zone: Res<SpawnZone>,
+    materials: Res<Materials>,
+    locale: Res<Locale>,
+    mut called: ResMut<MilitiaCalled>,
+    query: Query<(&Transform, &Team), With<UnitType>>,
+) {
+    if called.0 || !keyboard_input.just_pressed(bindings.call_militia) {
+        return
+    }
+    let side = options.player_side;
+
+    // Distance from the centre line towards the player's edge where the last quarter starts
+    let last_quarter = battlefield.width() / 4.;
+    let threatened = query.iter()
+        .filter(|&(_, &Team(s))| s != side)
+        .any(|(t, _)| t.translation.x * side.direction() < -last_quarter);
+    if !threatened {
+        return
+    }
+    called.0 = true;
+
+    spawn_banner(&mut commands, &materials, locale.text("banner.militia"), materials.green, 3.);
+
+    let rect = zone.0[side];
+    let count = (rect.height() / PLACEMENT_SPACING) as u32;
+    for i in 0..count {
+        let y = rect.min.y + rect.height() * (i as f32 + 0.5) / count as f32;
+        orders.send(SpawnOrder {
+            side,
+            position: Vec2::new(rect.center().x, y),
+            skills: MILITIA,
+            prepaid: true,
+        });
+    }
+}
@@ -0,0 +1,137 @@
+use bevy::prelude::*;
+
+use crate::{zone::SpawnZone, Battlefield, ControlOptions, Materials, Side, Team, UnitType};
+
+/// Width of the minimap, its height follows the shape of the battlefield
+const WIDTH: f32 = 160.;
+const DOT_SIZE: f32 = 3.;
+
+/// Bottom right overview of the whole battlefield
+#[derive(Debug, Clone, Copy, Component)]
+pub struct Minimap;
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct MinimapZone(Side);
+
+/// Stands for one fighter, though not any particular one from frame to frame
+#[derive(Debug, Clone, Copy, Component)]
+pub struct MinimapDot;
+
+pub fn setup_minimap(mut commands: Commands) {
+    commands.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                bottom: Val::Px(5.),
+                right: Val::Px(5.),
+                ..default()
+            },
+            size: Size::new(Val::Px(WIDTH), Val::Px(WIDTH / 2.)),
+            ..default()
+        },
+        background_color: Color::rgba(0., 0., 0., 0.6).into(),
+        ..default()
+    })
+    .insert(Minimap)
+    .with_children(|minimap| {
+        for side in Side::ALL {
+            minimap.spawn(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    ..default()
+                },
+                ..default()
+            }).insert(MinimapZone(side));
+        }
+    });
+}
+
+/// Where `point` on the battlefield is on the minimap, in percent from the top left corner
+fn map_position(point: Vec2, battlefield: Vec2) -> UiRect {
+    UiRect {
+        left: Val::Percent(100. * (point.x / battlefield.x + 0.5)),
+        top: Val::Percent(100. * (0.5 - point.y / battlefield.y)),
+        ..default()
+    }
+}
+
+pub fn minimap_zone_system(
+    battlefield: Res<Battlefield>,
+    zone: Res<SpawnZone>,
+    options: Res<ControlOptions>,
+    materials: Res<Materials>,
+    new_minimap: Query<(), Added<Minimap>>,
+    mut minimap_query: Query<&mut Style, With<Minimap>>,
+    mut zone_query: Query<(&MinimapZone, &mut Style, &mut BackgroundColor), Without<Minimap>>,
+) {
+    if !zone.is_changed() && !options.is_changed() && !materials.is_changed() && new_minimap.is_empty() {
+        return
+    }
+    let battlefield = 2. * battlefield.half_size;
+    for mut style in minimap_query.iter_mut() {
+        style.size.height = Val::Px(WIDTH * battlefield.y / battlefield.x);
+    }
+    for (&MinimapZone(side), mut style, mut background) in zone_query.iter_mut() {
+        let rect = zone.0[side];
+        style.position = map_position(Vec2::new(rect.min.x, rect.max.y), battlefield);
+        let size = 100. * rect.size() / battlefield;
+        style.size = Size::new(Val::Percent(size.x), Val::Percent(size.y));
+        background.0 = if side == options.player_side {
+            materials.player_zone
+        } else {
+            materials.enemy_zone
+        };
+    }
+}
+
+/// Moves a dot onto every fighter in sight, adding and removing dots as armies grow and shrink
+pub fn minimap_dot_system(
+    mut commands: Commands,
+    battlefield: Res<Battlefield>,
+    materials: Res<Materials>,
+    fighter_query: Query<(&Transform, &Team, &Visibility), With<UnitType>>,
+    minimap_query: Query<Entity, With<Minimap>>,
+    mut dot_query: Query<(Entity, &mut Style, &mut BackgroundColor), With<MinimapDot>>,
+) {
+    let Ok(minimap) = minimap_query.get_single() else {
+        return
+    };
+    let battlefield = 2. * battlefield.half_size;
+
+    // Fog of war hides units from the minimap as well
+    let mut fighters = fighter_query.iter()
+        .filter(|(.., visibility)| **visibility != Visibility::Hidden)
+        .map(|(transform, &Team(side), _)| (
+            map_position(transform.translation.truncate(), battlefield),
+            materials.team[side],
+        ));
+
+    for (ent, mut style, mut background) in dot_query.iter_mut() {
+        if let Some((position, color)) = fighters.next() {
+            style.position = position;
+            background.0 = color;
+        } else {
+            commands.entity(ent).despawn_recursive();
+        }
+    }
+    commands.entity(minimap).with_children(|minimap| {
+        for (position, color) in fighters {
+            minimap.spawn(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position,
+                    size: Size::all(Val::Px(DOT_SIZE)),
+                    ..default()
+                },
+                background_color: color.into(),
+                ..default()
+            }).insert(MinimapDot);
+        }
+    });
+}
+
+pub fn cleanup_minimap(mut commands: Commands, query: Query<Entity, With<Minimap>>) {
+    for ent in query.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+}
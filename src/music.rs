@@ -0,0 +1,52 @@
+//! Looping battle music that gets heavier the more units are fighting.
+//! Expects `calm.ogg` and `battle.ogg` in `assets/music`
+
+use bevy::prelude::*;
+
+use crate::{settings::Settings, sound::SoundCategory, CombatTarget};
+
+/// Number of units fighting at once for the heavy track to play at full volume
+const FULL_INTENSITY: f32 = 16.;
+/// How fast the intensity follows the fighting, per second
+const FADE_RATE: f32 = 0.4;
+
+#[derive(Debug, Resource)]
+pub struct AudioManager {
+    /// From 0 when calm to 1 in the thick of battle
+    pub intensity: f32,
+    calm: Handle<AudioSink>,
+    battle: Handle<AudioSink>,
+}
+
+pub fn setup_music(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    audio: Res<Audio>,
+    sinks: Res<Assets<AudioSink>>,
+) {
+    let calm = audio.play_with_settings(asset_server.load("music/calm.ogg"), PlaybackSettings::LOOP);
+    let battle = audio.play_with_settings(asset_server.load("music/battle.ogg"), PlaybackSettings::LOOP.with_volume(0.));
+    commands.insert_resource(AudioManager {
+        intensity: 0.,
+        calm: sinks.get_handle(calm),
+        battle: sinks.get_handle(battle),
+    });
+}
+
+pub fn intensity_system(time: Res<Time>, mut manager: ResMut<AudioManager>, query: Query<&CombatTarget>) {
+    let fighting = query.iter().filter(|target| target.0.is_some()).count();
+    let target = (fighting as f32 / FULL_INTENSITY).min(1.);
+    let step = FADE_RATE * time.raw_delta_seconds();
+    manager.intensity += (target - manager.intensity).clamp(-step, step);
+}
+
+/// Crossfades between the calm and the heavy track
+pub fn crossfade_system(manager: Res<AudioManager>, settings: Res<Settings>, sinks: Res<Assets<AudioSink>>) {
+    let volume = settings.volume(SoundCategory::Music);
+    if let Some(calm) = sinks.get(&manager.calm) {
+        calm.set_volume(volume * (1. - manager.intensity));
+    }
+    if let Some(battle) = sinks.get(&manager.battle) {
+        battle.set_volume(volume * manager.intensity);
+    }
+}
@@ -0,0 +1,105 @@
+use bevy::prelude::*;
+
+use crate::Skills;
+
+/// Rule tweaks that can be combined for a match
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mutator {
+    DoubleSpeed,
+    /// Every unit dies to a single hit
+    SuddenDeath,
+    TinyUnits,
+    NoMedics,
+    /// Buying many of the same unit quickly makes it pricier
+    PriceSurge,
+    /// Experimental: units that win a lot, across all matches played, get pricier
+    MetaPrices,
+    /// The battlefield is split into lanes units can't leave
+    Lanes,
+    /// Enemy units are hidden until they're close or cross midfield, against the AI
+    FogOfWar,
+    /// Unspent money earns interest every so often
+    Interest,
+    /// Only basic units are available until better ones are researched
+    TechTree,
+}
+
+impl Mutator {
+    pub const ALL: [Mutator; 10] = [
+        Mutator::DoubleSpeed, Mutator::SuddenDeath, Mutator::TinyUnits, Mutator::NoMedics, Mutator::PriceSurge,
+        Mutator::MetaPrices, Mutator::Lanes, Mutator::FogOfWar, Mutator::Interest,
+        Mutator::TechTree,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Mutator::DoubleSpeed => "Double speed",
+            Mutator::SuddenDeath => "Sudden death",
+            Mutator::TinyUnits => "Tiny units",
+            Mutator::NoMedics => "No medics",
+            Mutator::PriceSurge => "Price surge",
+            Mutator::MetaPrices => "Meta prices (experimental)",
+            Mutator::Lanes => "Lanes",
+            Mutator::FogOfWar => "Fog of war",
+            Mutator::Interest => "Interest",
+            Mutator::TechTree => "Tech tree",
+        }
+    }
+    /// Key toggling it in the menu
+    pub fn key(self) -> KeyCode {
+        match self {
+            Mutator::DoubleSpeed => KeyCode::F1,
+            Mutator::SuddenDeath => KeyCode::F2,
+            Mutator::TinyUnits => KeyCode::F3,
+            Mutator::NoMedics => KeyCode::F4,
+            Mutator::PriceSurge => KeyCode::F5,
+            Mutator::MetaPrices => KeyCode::F6,
+            Mutator::Lanes => KeyCode::F7,
+            Mutator::FogOfWar => KeyCode::F8,
+            Mutator::Interest => KeyCode::F9,
+            Mutator::TechTree => KeyCode::F10,
+        }
+    }
+    fn bit(self) -> u16 {
+        1 << Self::ALL.iter().position(|&m| m == self).unwrap()
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Mutators(u16);
+
+impl Mutators {
+    pub fn contains(self, mutator: Mutator) -> bool {
+        self.0 & mutator.bit() != 0
+    }
+    pub fn toggle(&mut self, mutator: Mutator) {
+        self.0 ^= mutator.bit();
+    }
+    /// One bit per mutator, in the order of [`Mutator::ALL`]
+    #[cfg(feature = "online")]
+    pub fn bits(self) -> u16 {
+        self.0
+    }
+    /// The mutators from [`Mutators::bits`], ignoring bits no mutator has
+    #[cfg(feature = "online")]
+    pub fn from_bits(bits: u16) -> Self {
+        Mutators(bits & ((1 << Mutator::ALL.len()) - 1))
+    }
+    /// Whether units like these may be deployed at all
+    pub fn allows(self, skills: &Skills) -> bool {
+        !(self.contains(Mutator::NoMedics) && skills.heal > 0)
+    }
+    pub fn apply(self, mut skills: Skills) -> Skills {
+        if self.contains(Mutator::DoubleSpeed) {
+            skills.speed = skills.speed.saturating_mul(2);
+        }
+        if self.contains(Mutator::SuddenDeath) {
+            skills.hp = 1;
+        }
+        skills
+    }
+    /// Side length of a unit's sprite
+    pub fn unit_size(self) -> f32 {
+        if self.contains(Mutator::TinyUnits) { 16. } else { 32. }
+    }
+}
@@ -0,0 +1,630 @@
+//! Versus over the network, each player commanding one side from their own machine. One player hosts with
+//! `--host <port>` and the other joins with `--connect <address>`, and the match starts as soon as they're
+//! connected, on the host's map, supply cap and mutators. The host plays left.
+//!
+//! The match is simulated in lockstep: both games run the same simulation from the same seed, and all the players
+//! send each other are the units they place, each one to be deployed a few ticks later on both sides at once. A game
+//! that hasn't heard from the other player for a tick waits for them before simulating it. Only placing units is
+//! sent, so airdrops, spells, towers, orders and the rest of what the player can do besides are off online.
+//!
+//! Each message is a line of tab-separated fields. The host starts with
+//! `hello, version, seed, tick period in nanoseconds, map name, supply cap, mutator bits`,
+//! and from then on each game sends `tick, number` followed by `unit, x, y` for every unit placed for that tick.
+//!
+//! Both players need the same build of the game and the same `balance.txt`, or the matches they see drift apart.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    io::{self, BufRead, BufReader, Write},
+    mem,
+    net::{Shutdown, TcpListener, TcpStream},
+    str::FromStr,
+    sync::{mpsc::{channel, Receiver, Sender}, Mutex},
+    thread,
+    time::Duration,
+};
+
+use bevy::{prelude::*, render::camera::ScalingMode};
+
+use crate::{
+    cli::Args,
+    combat,
+    locale::Locale,
+    maps::Maps,
+    match_rng::MatchRng,
+    mutators::{Mutator, Mutators},
+    supply::{self, SUPPLY_CAPS},
+    tech::Tech,
+    zone::SpawnZone,
+    ControlOptions, GameState, MainCamera, MatchConfig, MatchOverEvent, Materials, PerSide, Side, SpawnOrder, Stalled,
+    UNITS,
+};
+
+/// Ticks between placing a unit and it being deployed, for the other player to hear of it in time
+const INPUT_DELAY: u64 = 6;
+
+pub struct OnlinePlugin;
+
+impl Plugin for OnlinePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_startup_system(connect_system)
+            .add_system(receive_system)
+            .add_system(start_system.after(receive_system).in_set(OnUpdate(GameState::Menu)))
+            .add_system(capture_orders_system
+                .after(crate::soldier_placement_system)
+                .before(crate::spawn_order_system)
+                .run_if(online)
+                .in_set(OnUpdate(GameState::Playing)))
+            .add_system(status_text_system)
+            .add_system(fit_camera_system.in_schedule(OnEnter(GameState::Playing)))
+            .add_system(end_session_system.in_schedule(OnExit(GameState::Results)))
+            // Units are deployed on the fixed tick instead, once both players' orders for it are in
+            .add_systems((
+                lockstep_system.run_if(in_state(GameState::Playing)),
+                supply::supply_system.run_if(online).run_if(crate::simulating),
+                crate::spawn_order_system.run_if(online).run_if(crate::simulating),
+                clear_orders_system,
+            ).chain().before(combat::targeting_system).in_schedule(CoreSchedule::FixedUpdate));
+    }
+}
+
+fn online(config: Res<MatchConfig>) -> bool {
+    config.online
+}
+
+/// A unit placed by a player, by name and where it was placed
+type Placed = (&'static str, Vec2);
+
+/// The host's setup for the match
+#[derive(Debug, Clone, PartialEq)]
+struct Hello {
+    version: String,
+    seed: u64,
+    /// Time between simulation ticks
+    period: Duration,
+    map: String,
+    /// Index into [`SUPPLY_CAPS`]
+    supply_cap: usize,
+    mutators: Mutators,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Message {
+    /// Sent by the host once the other player has connected
+    Hello(Hello),
+    /// The units a player placed, to be deployed on the given tick
+    Tick(u64, Vec<Placed>),
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Message::Hello(Hello { version, seed, period, map, supply_cap, mutators }) => {
+                write!(f, "hello\t{version}\t{seed}\t{}\t{map}\t{supply_cap}\t{}", period.as_nanos(), mutators.bits())
+            }
+            Message::Tick(tick, placed) => {
+                write!(f, "tick\t{tick}")?;
+                // Floats print as the shortest text that reads back as the same number
+                for (name, position) in placed {
+                    write!(f, "\t{name}\t{}\t{}", position.x, position.y)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn number<T: FromStr>(field: &str) -> Result<T, String> {
+    field.parse().map_err(|_| format!("expected a number, got {field}"))
+}
+
+fn parse(line: &str) -> Result<Message, String> {
+    let fields: Vec<_> = line.split('\t').collect();
+    match fields[..] {
+        ["hello", version, seed, period, map, supply_cap, mutators] => Ok(Message::Hello(Hello {
+            version: version.to_owned(),
+            seed: number(seed)?,
+            period: Duration::from_nanos(number(period)?),
+            map: map.to_owned(),
+            supply_cap: number(supply_cap)?,
+            mutators: Mutators::from_bits(number(mutators)?),
+        })),
+        ["tick", tick, ref placed @ ..] if placed.len() % 3 == 0 => {
+            let placed = placed.chunks(3).map(|unit| {
+                let name = UNITS.iter()
+                    .find(|skills| skills.name == unit[0])
+                    .ok_or_else(|| format!("no unit called {}", unit[0]))?
+                    .name;
+                let position = Vec2::new(number(unit[1])?, number(unit[2])?);
+                if !position.is_finite() {
+                    return Err(format!("{} placed nowhere", unit[0]))
+                }
+                Ok((name, position))
+            }).collect::<Result<_, String>>()?;
+            Ok(Message::Tick(number(tick)?, placed))
+        }
+        _ => Err(format!("unexpected message {line}")),
+    }
+}
+
+enum NetEvent {
+    Connected(TcpStream, Sender<String>),
+    Line(String),
+    Closed(String),
+}
+
+/// Hands the other player's lines to the game as they come in, with a thread of its own writing what's sent to them
+fn run(stream: io::Result<TcpStream>, tx: Sender<NetEvent>) {
+    let result = stream.and_then(|stream| {
+        // Orders are small and late ones hold up both games
+        stream.set_nodelay(true)?;
+        let mut writer = stream.try_clone()?;
+        let (outbox, outgoing) = channel::<String>();
+        thread::spawn(move || {
+            for line in outgoing {
+                if writeln!(writer, "{line}").is_err() {
+                    break
+                }
+            }
+        });
+        if tx.send(NetEvent::Connected(stream.try_clone()?, outbox)).is_err() {
+            return Ok(())
+        }
+        for line in BufReader::new(stream).lines() {
+            if tx.send(NetEvent::Line(line?)).is_err() {
+                return Ok(())
+            }
+        }
+        Err(io::ErrorKind::ConnectionAborted.into())
+    });
+    if let Err(e) = result {
+        let _ = tx.send(NetEvent::Closed(e.to_string()));
+    }
+}
+
+enum Connection {
+    Waiting,
+    Open(TcpStream, Sender<String>),
+    Closed(String),
+}
+
+/// Where the match is at in lockstep
+#[derive(Debug, Default)]
+struct Lockstep {
+    /// The next tick to be simulated
+    tick: u64,
+    /// Units placed on each side, by the tick they're deployed on
+    orders: PerSide<BTreeMap<u64, Vec<Placed>>>,
+    /// The last tick the other player has sent their orders for
+    remote_through: Option<u64>,
+    /// Units placed here since the last tick, sent with the next one
+    placed: Vec<Placed>,
+}
+
+/// The connection to the other player, for one match
+#[derive(Resource)]
+struct Session {
+    events: Mutex<Receiver<NetEvent>>,
+    /// The side played from here, left for the host
+    side: Side,
+    /// Port hosted on or address connected to
+    address: String,
+    connection: Connection,
+    /// The host's setup, until the match is started from it
+    hello: Option<Hello>,
+    started: bool,
+    lockstep: Lockstep,
+    /// The tick period from the settings, put back after the match
+    period: Duration,
+}
+
+impl Session {
+    fn new(side: Side, address: String, events: Receiver<NetEvent>) -> Self {
+        Session {
+            events: Mutex::new(events),
+            side,
+            address,
+            connection: Connection::Waiting,
+            hello: None,
+            started: false,
+            lockstep: Lockstep::default(),
+            period: Duration::ZERO,
+        }
+    }
+    fn close(&mut self, reason: String) {
+        warn!("Online match closed: {reason}");
+        if let Connection::Open(stream, _) = mem::replace(&mut self.connection, Connection::Closed(reason)) {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+    fn send(&self, message: Message) {
+        // A closed connection is noticed by the thread reading from it
+        if let Connection::Open(_, outbox) = &self.connection {
+            let _ = outbox.send(message.to_string());
+        }
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        // Stops the reading thread, and lets the other player know
+        if let Connection::Open(stream, _) = &self.connection {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+#[derive(Debug, Component)]
+struct OnlineStatusText;
+
+fn connect_system(mut commands: Commands, args: Res<Args>, materials: Res<Materials>) {
+    let (tx, rx) = channel();
+    let session = if let Some(port) = args.host {
+        thread::spawn(move || {
+            let stream = TcpListener::bind(("0.0.0.0", port))
+                .and_then(|listener| listener.accept())
+                .map(|(stream, _)| stream);
+            run(stream, tx);
+        });
+        Session::new(Side::Left, port.to_string(), rx)
+    } else if let Some(address) = args.connect.clone() {
+        let connecting = address.clone();
+        thread::spawn(move || run(TcpStream::connect(connecting), tx));
+        Session::new(Side::Right, address, rx)
+    } else {
+        return
+    };
+    commands.insert_resource(session);
+
+    commands.spawn(TextBundle {
+        text: Text::from_section("", TextStyle {
+            font: materials.font.clone(),
+            color: Color::WHITE,
+            font_size: 20.0,
+        }),
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                bottom: Val::Px(5.0),
+                right: Val::Px(5.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        z_index: ZIndex::Global(10),
+        ..Default::default()
+    }).insert(OnlineStatusText);
+}
+
+/// Takes in what the other player sends, keeping their orders until the ticks they're for
+fn receive_system(session: Option<ResMut<Session>>) {
+    let Some(mut session) = session else {
+        return
+    };
+    let events: Vec<_> = session.events.lock().unwrap().try_iter().collect();
+    for event in events {
+        match event {
+            NetEvent::Connected(stream, outbox) => {
+                info!("Online match connected to {}", stream.peer_addr().map_or("?".to_owned(), |a| a.to_string()));
+                session.connection = Connection::Open(stream, outbox);
+            }
+            NetEvent::Line(line) => match parse(&line) {
+                Ok(Message::Hello(hello)) if session.side == Side::Right && !session.started => {
+                    session.hello = Some(hello);
+                }
+                // The host's first ticks can come along with its hello
+                Ok(Message::Tick(tick, placed)) => {
+                    let remote = session.side.opponent();
+                    session.lockstep.orders[remote].insert(tick, placed);
+                    session.lockstep.remote_through = Some(tick);
+                }
+                Ok(_) => session.close(format!("unexpected message {line}")),
+                Err(e) => session.close(e),
+            },
+            NetEvent::Closed(reason) => session.close(reason),
+        }
+    }
+}
+
+/// Starts the match once the players are connected, as the host set it up
+fn start_system(
+    session: Option<ResMut<Session>>,
+    maps: Res<Maps>,
+    mut config: ResMut<MatchConfig>,
+    mut options: ResMut<ControlOptions>,
+    mut rng: ResMut<MatchRng>,
+    mut fixed_time: ResMut<FixedTime>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Some(mut session) = session.filter(|session| !session.started) else {
+        return
+    };
+    if !matches!(session.connection, Connection::Open(..)) {
+        return
+    }
+    let hello = if session.side == Side::Left {
+        let mut mutators = config.mutators;
+        // The meta prices come from the matches played on this machine, which the other player hasn't
+        if mutators.contains(Mutator::MetaPrices) {
+            mutators.toggle(Mutator::MetaPrices);
+        }
+        let hello = Hello {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            seed: rand::random(),
+            period: fixed_time.period,
+            map: maps.0[config.map].name.clone(),
+            supply_cap: config.supply_cap,
+            mutators,
+        };
+        session.send(Message::Hello(hello.clone()));
+        hello
+    } else if let Some(hello) = session.hello.take() {
+        hello
+    } else {
+        return
+    };
+
+    if hello.version != env!("CARGO_PKG_VERSION") {
+        session.close(format!("the host plays version {}", hello.version));
+        return
+    }
+    let Some(map) = maps.0.iter().position(|map| map.name == hello.map) else {
+        session.close(format!("no map called {}", hello.map));
+        return
+    };
+    if hello.supply_cap >= SUPPLY_CAPS.len() || hello.period.is_zero() {
+        session.close("unexpected setup from the host".to_owned());
+        return
+    }
+    config.map = map;
+    config.supply_cap = hello.supply_cap;
+    config.mutators = hello.mutators;
+    config.versus_ai = false;
+    config.spectating = false;
+    config.campaign = false;
+    config.draft = false;
+    config.extra_sides = 0;
+    config.online = true;
+    options.player_side = session.side;
+    *rng = MatchRng::new(Some(hello.seed));
+    session.period = mem::replace(&mut fixed_time.period, hello.period);
+    session.started = true;
+    info!(seed = hello.seed, map = hello.map, side = %session.side, "online match starting");
+    next_state.set(GameState::Playing);
+}
+
+/// Holds back the units the player places, to be deployed once they've been sent to the other player
+fn capture_orders_system(session: Option<ResMut<Session>>, mut orders: ResMut<Events<SpawnOrder>>) {
+    let Some(mut session) = session else {
+        return
+    };
+    let side = session.side;
+    let placed = orders.drain()
+        .filter(|order| order.side == side && !order.prepaid)
+        .map(|order| (order.skills.name, order.position));
+    session.lockstep.placed.extend(placed);
+}
+
+/// Sends the player's orders for a later tick and hands on both players' orders for this one, holding the match up
+/// until the other player's are in. If they leave, the player wins.
+fn lockstep_system(
+    session: Option<ResMut<Session>>,
+    config: Res<MatchConfig>,
+    zone: Res<SpawnZone>,
+    tech: Res<Tech>,
+    mut stalled: ResMut<Stalled>,
+    mut orders: EventWriter<SpawnOrder>,
+    mut match_over: EventWriter<MatchOverEvent>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Some(mut session) = session.filter(|_| config.online) else {
+        return
+    };
+    // Already over, from an earlier tick this frame
+    if next_state.0.is_some() {
+        return
+    }
+    if let Connection::Closed(_) = session.connection {
+        stalled.0 = true;
+        match_over.send(MatchOverEvent { winner: session.side });
+        next_state.set(GameState::Results);
+        return
+    }
+
+    let lockstep = &session.lockstep;
+    let tick = lockstep.tick;
+    stalled.0 = tick >= INPUT_DELAY && lockstep.remote_through < Some(tick);
+    if stalled.0 {
+        return
+    }
+    let placed = mem::take(&mut session.lockstep.placed);
+    session.send(Message::Tick(tick + INPUT_DELAY, placed.clone()));
+    let side = session.side;
+    let lockstep = &mut session.lockstep;
+    lockstep.orders[side].insert(tick + INPUT_DELAY, placed);
+
+    // In the same order on both sides, as that's the order they get their numbers in
+    for side in [Side::Left, Side::Right] {
+        for (name, position) in lockstep.orders[side].remove(&tick).unwrap_or_default() {
+            let skills = *UNITS.iter().find(|skills| skills.name == name).unwrap();
+            if !tech.unlocked(side, &skills) {
+                continue
+            }
+            orders.send(SpawnOrder {
+                side,
+                position: zone.clamp(side, position),
+                skills,
+                prepaid: false,
+            });
+        }
+    }
+    lockstep.tick += 1;
+}
+
+/// Drops the orders deployed this tick, which would otherwise be held back again as newly placed
+fn clear_orders_system(mut orders: ResMut<Events<SpawnOrder>>) {
+    orders.clear();
+}
+
+/// Shows the whole battlefield online, which is as big as the map rather than the window
+fn fit_camera_system(
+    config: Res<MatchConfig>,
+    maps: Res<Maps>,
+    mut query: Query<&mut OrthographicProjection, With<MainCamera>>,
+) {
+    let scaling_mode = if config.online {
+        let size = maps.0[config.map].size;
+        ScalingMode::AutoMin { min_width: size.x, min_height: size.y }
+    } else {
+        ScalingMode::WindowSize(1.)
+    };
+    for mut projection in query.iter_mut() {
+        projection.scaling_mode = scaling_mode.clone();
+    }
+}
+
+/// Hangs up once the match is over, as a session lasts one match
+fn end_session_system(
+    mut commands: Commands,
+    session: Option<Res<Session>>,
+    mut config: ResMut<MatchConfig>,
+    mut stalled: ResMut<Stalled>,
+    mut rng: ResMut<MatchRng>,
+    mut fixed_time: ResMut<FixedTime>,
+) {
+    let Some(session) = session.filter(|_| config.online) else {
+        return
+    };
+    fixed_time.period = session.period;
+    *rng = MatchRng::default();
+    config.online = false;
+    stalled.0 = false;
+    commands.remove_resource::<Session>();
+}
+
+fn status_text_system(
+    session: Option<Res<Session>>,
+    stalled: Res<Stalled>,
+    locale: Res<Locale>,
+    mut query: Query<&mut Text, With<OnlineStatusText>>,
+) {
+    let status = match session.as_deref() {
+        None => String::new(),
+        Some(Session { connection: Connection::Waiting, side: Side::Left, address, .. }) => {
+            locale.format("online.hosting", &[address])
+        }
+        Some(Session { connection: Connection::Waiting, address, .. }) => {
+            locale.format("online.connecting", &[address])
+        }
+        Some(Session { connection: Connection::Open(..), .. }) if stalled.0 => locale.text("online.waiting").to_owned(),
+        Some(Session { connection: Connection::Open(..), side, .. }) => {
+            locale.format("online.playing", &[&locale.name(&side.to_string())])
+        }
+        Some(Session { connection: Connection::Closed(reason), .. }) => locale.format("online.closed", &[reason]),
+    };
+    for mut text in query.iter_mut() {
+        if text.sections[0].value != status {
+            text.sections[0].value = status.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Health, UnitName};
+
+    #[test]
+    fn messages_read_back() {
+        let hello = Message::Hello(Hello {
+            version: "0.1.0".to_owned(),
+            seed: u64::MAX,
+            period: Duration::from_nanos(16_666_667),
+            map: "Rocky pass".to_owned(),
+            supply_cap: 2,
+            mutators: Mutators::from_bits(0b101),
+        });
+        let placed = vec![(UNITS[0].name, Vec2::new(-600.1, 0.3)), (UNITS[1].name, Vec2::new(1e-7, -2.))];
+        let tick = Message::Tick(42, placed);
+        for message in [hello, tick, Message::Tick(0, Vec::new())] {
+            assert_eq!(parse(&message.to_string()), Ok(message));
+        }
+    }
+
+    #[test]
+    fn rejects_bad_messages() {
+        for line in [
+            "",
+            "hi",
+            "hello\t0.1.0\t1\t100\tRocky pass\t0",
+            "hello\t0.1.0\tone\t100\tRocky pass\t0\t0",
+            "tick\t1\tFighter\t0",
+            "tick\t-1",
+            "tick\t1\tDragon\t0\t0",
+            "tick\t1\tFighter\tNaN\t0",
+            "tick\t1\tFighter\tinf\t0",
+        ] {
+            assert!(parse(line).is_err(), "{line}");
+        }
+    }
+
+    /// Two games on this machine, each placing a few units, should see the same match
+    #[test]
+    fn both_players_see_the_same_match() {
+        let args = |args: &[&str]| Args::parse_from(["--headless"].iter().chain(args).map(|&arg| arg.to_owned()));
+        let mut host = crate::app(args(&["--host", "47813"]));
+        // The host has to be listening before the other player connects
+        host.update();
+        let mut guest = crate::app(args(&["--connect", "127.0.0.1:47813"]));
+        let playing = |app: &App| app.world.resource::<State<GameState>>().0 == GameState::Playing;
+        for _ in 0..500 {
+            if playing(&host) && playing(&guest) {
+                break
+            }
+            host.update();
+            guest.update();
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert!(playing(&host) && playing(&guest));
+
+        let place = |app: &mut App, side: Side, y: f32| {
+            let position = app.world.resource::<SpawnZone>().0[side].center() + Vec2::new(0., y);
+            app.world.resource_mut::<Session>().lockstep.placed.push((UNITS[0].name, position));
+        };
+        for frame in 0..600 {
+            if frame % 50 == 0 {
+                place(&mut host, Side::Left, frame as f32 / 10.);
+                place(&mut guest, Side::Right, -(frame as f32) / 10.);
+            }
+            host.update();
+            guest.update();
+        }
+
+        // One may be a few ticks ahead of the other, which catches up with what it's already been sent
+        let tick = |app: &App| app.world.resource::<Session>().lockstep.tick;
+        for _ in 0..100 {
+            if tick(&host) == tick(&guest) {
+                break
+            }
+            let behind = if tick(&host) < tick(&guest) { &mut host } else { &mut guest };
+            behind.update();
+        }
+        assert_eq!(tick(&host), tick(&guest));
+
+        let units = |app: &mut App| {
+            let mut units: Vec<_> = app.world.query::<(&UnitName, &Transform, &Health)>()
+                .iter(&app.world)
+                .map(|(name, transform, health)| (name.side, name.number, transform.translation, health.hp))
+                .collect();
+            units.sort_by_key(|&(side, number, ..)| (side, number));
+            units
+        };
+        let host_units = units(&mut host);
+        for side in [Side::Left, Side::Right] {
+            assert!(host_units.iter().any(|unit| unit.0 == side), "{side} deployed nothing");
+        }
+        assert_eq!(host_units, units(&mut guest));
+    }
+}
@@ -0,0 +1,203 @@
+use bevy::prelude::*;
+
+use crate::{
+    bindings::Bindings, status::StatusEffects, zone::SpawnZone,
+    ClickAction, CombatTarget, ControlOptions, MatchConfig, MouseLoc, PerSide, Team, UnitType,
+};
+
+/// How long units falling back turn their backs on the enemy, unable to defend themselves
+const EXPOSED_TIME: f32 = 1.5;
+/// How close units have to get to their side's rally point before they advance
+const RALLY_RADIUS: f32 = 30.;
+
+/// What a side's army has been told to do, on top of what its units do on their own
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    /// Marches on and fights whatever it meets
+    #[default]
+    Advance,
+    /// Breaks off from fighting and heads back to the spawn zone
+    FallBack,
+}
+
+impl Order {
+    pub fn name(self) -> &'static str {
+        match self {
+            Order::Advance => "Advancing",
+            Order::FallBack => "Falling back",
+        }
+    }
+    fn toggled(self) -> Self {
+        match self {
+            Order::Advance => Order::FallBack,
+            Order::FallBack => Order::Advance,
+        }
+    }
+}
+
+/// The order each side's army is under
+#[derive(Debug, Default, Clone, Copy, Resource)]
+pub struct ArmyOrders(pub PerSide<Order>);
+
+/// Where each side's newly deployed units gather before they advance, if anywhere
+#[derive(Debug, Default, Clone, Copy, Resource)]
+pub struct RallyPoints(pub PerSide<Option<Vec2>>);
+
+pub fn reset_orders(mut orders: ResMut<ArmyOrders>, mut rally_points: ResMut<RallyPoints>) {
+    *orders = ArmyOrders::default();
+    *rally_points = RallyPoints::default();
+}
+
+/// Heading back to its spawn zone without fighting, until its side advances again
+#[derive(Debug, Default, Clone, Copy, Component)]
+pub struct FallingBack {
+    /// Seconds left until it's clear of the enemy it broke off from
+    exposed: f32,
+}
+
+impl FallingBack {
+    /// Falling back from the middle of a fight
+    fn breaking_off() -> Self {
+        FallingBack {
+            exposed: EXPOSED_TIME,
+        }
+    }
+    /// Whether it's still turning away from the fight, and can't defend itself
+    pub fn exposed(&self) -> bool {
+        self.exposed > 0.
+    }
+}
+
+/// Orders the player's whole army to fall back, or to advance again if it already is
+pub fn order_input_system(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    options: Res<ControlOptions>,
+    mut orders: ResMut<ArmyOrders>,
+    query: Query<(Entity, &Team, &UnitType, &CombatTarget)>,
+) {
+    if !keyboard_input.just_pressed(bindings.fall_back) {
+        return
+    }
+    let side = options.player_side;
+    let order = orders.0[side].toggled();
+    orders.0[side] = order;
+
+    // Structures hold their ground either way
+    let army = query.iter().filter(|&(_, &Team(s), unit, _)| s == side && !unit.skills.is_structure());
+    for (ent, .., target) in army {
+        match order {
+            Order::FallBack if target.0.is_some() => commands.entity(ent).insert(FallingBack::breaking_off()),
+            Order::FallBack => commands.entity(ent).insert(FallingBack::default()),
+            Order::Advance => commands.entity(ent).remove::<FallingBack>(),
+        };
+    }
+}
+
+/// Walks units that are falling back to their spawn zone and holds them there
+pub fn fall_back_system(
+    fixed_time: Res<FixedTime>,
+    zone: Res<SpawnZone>,
+    mut query: Query<(&mut Transform, &Team, &UnitType, &mut CombatTarget, &StatusEffects, &mut FallingBack)>,
+) {
+    let delta = fixed_time.period.as_secs_f32();
+    for (mut transform, &Team(side), unit, mut target, status, mut falling_back) in query.iter_mut() {
+        if target.0.is_some() {
+            target.0 = None;
+        }
+        if falling_back.exposed() {
+            falling_back.exposed -= delta;
+        }
+        if zone.contains(side, transform.translation.truncate()) {
+            continue
+        }
+        let speed = 3. * unit.skills.speed as f32 * status.speed_factor() * delta;
+        transform.translation -= (side.facing() * speed).extend(0.);
+    }
+}
+
+/// Marks a side's rally point on the battlefield
+#[derive(Debug, Clone, Copy, Component)]
+pub struct RallyFlag;
+
+/// A point a unit walks to before it starts advancing
+#[derive(Debug, Clone, Copy, Component)]
+pub struct Waypoint(pub Vec2);
+
+/// Clicking on your half with the rally key held puts your rally flag there, or takes it down if clicking the flag
+pub fn rally_placement_system(
+    mut commands: Commands,
+    mouse_loc: Res<MouseLoc>,
+    mouse_button: Res<Input<MouseButton>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    options: Res<ControlOptions>,
+    bindings: Res<Bindings>,
+    config: Res<MatchConfig>,
+    mut rally_points: ResMut<RallyPoints>,
+    flag_query: Query<(Entity, &Team), With<RallyFlag>>,
+) {
+    if !matches!(ClickAction::held(&keyboard_input, &bindings), ClickAction::Rally) {
+        return
+    }
+    for &button in mouse_button.get_just_pressed() {
+        let Some(side) = options.button_side(&bindings, button) else {
+            continue
+        };
+        if config.versus_ai && side != options.player_side {
+            continue
+        }
+        let position = mouse_loc.0;
+        if side.advance(position) > 0. {
+            continue
+        }
+        for (ent, _) in flag_query.iter().filter(|&(_, &Team(s))| s == side) {
+            commands.entity(ent).despawn();
+        }
+        let taken_down = rally_points.0[side].is_some_and(|point| point.distance(position) < RALLY_RADIUS);
+        if taken_down {
+            rally_points.0[side] = None;
+            continue
+        }
+        rally_points.0[side] = Some(position);
+        commands.spawn(SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgba(1., 0.84, 0., 0.6),
+                custom_size: Some(Vec2::new(4., 24.)),
+                ..default()
+            },
+            transform: Transform::from_translation(position.extend(-0.4)),
+            ..default()
+        }).insert((RallyFlag, Team(side)));
+    }
+}
+
+/// Walks units to their waypoint, unless they're caught in a fight on the way, and lets them advance once there
+pub fn waypoint_system(
+    mut commands: Commands,
+    fixed_time: Res<FixedTime>,
+    mut query: Query<
+        (Entity, &mut Transform, &UnitType, &CombatTarget, &StatusEffects, &Waypoint),
+        Without<FallingBack>,
+    >,
+) {
+    let delta = fixed_time.period.as_secs_f32();
+    for (ent, mut transform, unit, target, status, &Waypoint(point)) in query.iter_mut() {
+        if target.0.is_some() {
+            continue
+        }
+        let offset = point - transform.translation.truncate();
+        if offset.length() < RALLY_RADIUS {
+            commands.entity(ent).remove::<Waypoint>();
+            continue
+        }
+        let speed = 3. * unit.skills.speed as f32 * status.speed_factor() * delta;
+        transform.translation += offset.clamp_length_max(speed).extend(0.);
+    }
+}
+
+pub fn cleanup_rally_flags(mut commands: Commands, query: Query<Entity, With<RallyFlag>>) {
+    for ent in query.iter() {
+        commands.entity(ent).despawn();
+    }
+}
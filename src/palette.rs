@@ -0,0 +1,69 @@
+//! Sets of colours players can pick between, for telling sides and health apart whatever their colour vision
+
+use bevy::prelude::*;
+
+use crate::{Materials, PerSide};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Palette {
+    pub name: &'static str,
+    health: [Color; 3],
+    damage: Color,
+    player_zone: Color,
+    enemy_zone: Color,
+    team: PerSide<Color>,
+}
+
+pub const PALETTES: [Palette; 3] = [
+    Palette {
+        name: "Standard",
+        health: [Color::rgba(0., 1., 0., 0.33), Color::rgba(1., 1., 0., 0.33), Color::rgba(1., 0., 0., 0.33)],
+        damage: Color::rgb(1., 0., 0.),
+        player_zone: Color::rgba(1., 1., 0., 0.15),
+        enemy_zone: Color::rgba(1., 0., 0., 0.15),
+        team: PerSide {
+            left: Color::rgb(0.35, 0.6, 1.),
+            right: Color::rgb(1., 0.3, 0.25),
+            top: Color::rgb(0.4, 0.85, 0.35),
+            bottom: Color::rgb(0.95, 0.8, 0.2),
+        },
+    },
+    // Blue against orange, which stay apart for red-green colour blindness
+    Palette {
+        name: "Deuteranopia",
+        health: [Color::rgba(0., 0.45, 0.7, 0.33), Color::rgba(0.95, 0.9, 0.25, 0.33), Color::rgba(0.9, 0.6, 0., 0.33)],
+        damage: Color::rgb(0.9, 0.6, 0.),
+        player_zone: Color::rgba(0., 0.45, 0.7, 0.15),
+        enemy_zone: Color::rgba(0.9, 0.6, 0., 0.15),
+        team: PerSide {
+            left: Color::rgb(0.35, 0.6, 1.),
+            right: Color::rgb(0.9, 0.6, 0.),
+            top: Color::rgb(0.9, 0.9, 0.9),
+            bottom: Color::rgb(0.8, 0.45, 0.7),
+        },
+    },
+    Palette {
+        name: "High contrast",
+        health: [Color::rgba(1., 1., 1., 0.8), Color::rgba(1., 1., 0., 0.8), Color::rgba(1., 0., 1., 0.8)],
+        damage: Color::rgb(1., 0., 1.),
+        player_zone: Color::rgba(0., 1., 1., 0.25),
+        enemy_zone: Color::rgba(1., 0., 1., 0.25),
+        team: PerSide {
+            left: Color::rgb(0., 1., 1.),
+            right: Color::rgb(1., 0., 1.),
+            top: Color::rgb(1., 1., 0.),
+            bottom: Color::rgb(1., 1., 1.),
+        },
+    },
+];
+
+impl Palette {
+    pub fn apply(&self, materials: &mut Materials) {
+        materials.health = self.health;
+        materials.green = self.health[0];
+        materials.red = self.damage;
+        materials.player_zone = self.player_zone;
+        materials.enemy_zone = self.enemy_zone;
+        materials.team = self.team;
+    }
+}
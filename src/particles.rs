@@ -0,0 +1,99 @@
+//! Short-lived specks flying off from hits, deaths and sieges
+
+use std::{f32::consts::{PI, TAU}, ops::Range};
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::Timeout;
+
+const GRAVITY: f32 = 400.;
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct Particle {
+    velocity: Vec2,
+    /// How strongly gravity pulls it down
+    weight: f32,
+    lifetime: f32,
+}
+
+/// How a burst of particles flies off
+#[derive(Debug, Clone)]
+pub struct Burst {
+    count: usize,
+    color: Color,
+    size: f32,
+    /// Angles in radians the particles can fly off at, 0 being to the right
+    angle: Range<f32>,
+    speed: Range<f32>,
+    weight: f32,
+    lifetime: f32,
+}
+
+impl Burst {
+    /// Blood spurting up and backwards off a unit hit while facing towards `direction`
+    pub fn blood(direction: f32) -> Self {
+        Burst {
+            count: 6,
+            color: Color::rgb(0.6, 0., 0.),
+            size: 3.,
+            angle: if direction > 0. { PI - 0.9..PI + 0.2 } else { -0.2..0.9 },
+            speed: 60.0..140.,
+            weight: 1.,
+            lifetime: 0.5,
+        }
+    }
+    pub fn death() -> Self {
+        Burst {
+            count: 16,
+            color: Color::rgb(0.45, 0., 0.),
+            size: 4.,
+            angle: 0.0..TAU,
+            speed: 40.0..160.,
+            weight: 0.6,
+            lifetime: 0.8,
+        }
+    }
+    pub fn dust() -> Self {
+        Burst {
+            count: 12,
+            color: Color::rgba(0.6, 0.5, 0.35, 0.8),
+            size: 6.,
+            angle: 0.3..PI - 0.3,
+            speed: 20.0..70.,
+            weight: -0.05,
+            lifetime: 1.2,
+        }
+    }
+}
+
+pub fn spawn_burst(commands: &mut Commands, at: Vec3, burst: Burst) {
+    let mut rng = rand::thread_rng();
+    for _ in 0..burst.count {
+        let angle = rng.gen_range(burst.angle.clone());
+        let velocity = rng.gen_range(burst.speed.clone()) * Vec2::new(angle.cos(), angle.sin());
+        commands.spawn(SpriteBundle {
+            sprite: Sprite {
+                color: burst.color,
+                custom_size: Some(Vec2::splat(burst.size)),
+                ..default()
+            },
+            transform: Transform::from_translation(at + Vec3::Z),
+            ..default()
+        }).insert((
+            Particle { velocity, weight: burst.weight, lifetime: burst.lifetime },
+            Timeout::new(burst.lifetime),
+        ));
+    }
+}
+
+/// Moves particles along, fading them out before they disappear
+pub fn particle_system(time: Res<Time>, mut query: Query<(&mut Particle, &mut Transform, &mut Sprite, &Timeout)>) {
+    let delta = time.delta_seconds();
+    for (mut particle, mut transform, mut sprite, timeout) in query.iter_mut() {
+        particle.velocity.y -= particle.weight * GRAVITY * delta;
+        transform.translation += (particle.velocity * delta).extend(0.);
+        let alpha = sprite.color.a();
+        sprite.color.set_a(alpha.min(timeout.time_left / particle.lifetime));
+    }
+}
@@ -0,0 +1,67 @@
+use bevy::prelude::*;
+
+use crate::{
+    bindings::Bindings, economy::Economy, status::StatusEffects, zone::SpawnZone,
+    hit_test, ClickAction, CombatTarget, ControlOptions, Health, MatchConfig, MouseLoc, Team, UnitType,
+};
+
+/// Share of a unit's price refunded when it makes it back at full health
+const REFUND_SHARE: f32 = 0.5;
+
+/// Marching back to its spawn zone to be sold off
+#[derive(Debug, Clone, Copy, Component)]
+pub struct Recalling;
+
+/// R-clicking one of your own units sends it back to be sold
+pub fn recall_order_system(
+    mut commands: Commands,
+    mouse_loc: Res<MouseLoc>,
+    mouse_button: Res<Input<MouseButton>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    options: Res<ControlOptions>,
+    bindings: Res<Bindings>,
+    config: Res<MatchConfig>,
+    query: Query<(Entity, &Transform, &TextureAtlasSprite, &Team, &UnitType), Without<Recalling>>,
+) {
+    if !matches!(ClickAction::held(&keyboard_input, &bindings), ClickAction::Recall) {
+        return
+    }
+    for &button in mouse_button.get_just_pressed() {
+        let Some(side) = options.button_side(&bindings, button) else {
+            continue
+        };
+        if config.versus_ai && side != options.player_side {
+            continue
+        }
+        let clicked = query.iter()
+            .filter(|&(.., &Team(s), unit)| s == side && !unit.skills.is_structure())
+            .find(|(_, t, s, ..)| hit_test(t, s, mouse_loc.0));
+        if let Some((ent, ..)) = clicked {
+            commands.entity(ent).insert(Recalling);
+        }
+    }
+}
+
+/// Walks recalled units back without fighting, and refunds them once they're home
+pub fn recall_system(
+    mut commands: Commands,
+    fixed_time: Res<FixedTime>,
+    zone: Res<SpawnZone>,
+    mut economy: ResMut<Economy>,
+    mut query: Query<
+        (Entity, &mut Transform, &Team, &UnitType, &Health, &mut CombatTarget, &StatusEffects),
+        With<Recalling>,
+    >,
+) {
+    let delta = fixed_time.period.as_secs_f32();
+    for (ent, mut transform, &Team(side), unit, health, mut target, status) in query.iter_mut() {
+        target.0 = None;
+        if zone.contains(side, transform.translation.truncate()) {
+            economy.money[side] += (unit.skills.price as f32 * REFUND_SHARE * health.fraction()).round() as u32;
+            commands.entity(ent).despawn_recursive();
+            continue
+        }
+        let speed = 3. * unit.skills.speed as f32 * status.speed_factor() * delta;
+        transform.translation -= (side.facing() * speed).extend(0.);
+    }
+}
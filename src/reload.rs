@@ -0,0 +1,57 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+
+use crate::{AttackCooldown, UnitType};
+
+const SEGMENTS: usize = 12;
+const RING_RADIUS: f32 = 7.;
+const RING_HEIGHT: f32 = 66.;
+
+/// One piece of the ring above a slow-reloading unit, shown once the reload has progressed past it
+#[derive(Debug, Clone, Copy, Component)]
+pub struct ReloadSegment(usize);
+
+/// Gives newly deployed slow-reloading units their reload ring
+pub fn setup_reload_ring_system(
+    mut commands: Commands,
+    query: Query<(Entity, &UnitType), Added<UnitType>>,
+) {
+    for (ent, unit) in query.iter() {
+        if !unit.reloads() {
+            continue
+        }
+        commands.entity(ent).with_children(|parent| {
+            for i in 0..SEGMENTS {
+                // Clockwise from the top
+                let angle = TAU * i as f32 / SEGMENTS as f32;
+                let offset = RING_RADIUS * Vec2::new(angle.sin(), angle.cos());
+                parent.spawn(SpriteBundle {
+                    transform: Transform::from_translation(Vec3::new(offset.x, RING_HEIGHT + offset.y, 2.))
+                        .with_rotation(Quat::from_rotation_z(-angle)),
+                    sprite: Sprite {
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                        custom_size: Some(Vec2::new(3., 2.)),
+                        .. default()
+                    },
+                    .. default()
+                }).insert(ReloadSegment(i));
+            }
+        });
+    }
+}
+
+pub fn reload_ring_system(
+    query: Query<(&UnitType, &AttackCooldown, &Children), Changed<AttackCooldown>>,
+    mut segment_query: Query<(&ReloadSegment, &mut Visibility)>,
+) {
+    for (unit, cooldown, children) in query.iter() {
+        let progress = 1. - cooldown.0 / unit.skills.cooldown;
+        let shown = (progress * SEGMENTS as f32) as usize;
+        for &child in children {
+            if let Ok((segment, mut visibility)) = segment_query.get_mut(child) {
+                *visibility = if segment.0 < shown { Visibility::Inherited } else { Visibility::Hidden };
+            }
+        }
+    }
+}
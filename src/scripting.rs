@@ -0,0 +1,272 @@
+//! Runs [rhai](https://rhai.rs) scripts from the `scripts` folder on match events,
+//! so scenarios and mods can add unit abilities, waves and win conditions without recompiling.
+//!
+//! Scripts define any of these functions, which are called while a match is on:
+//!
+//! - `on_match_start(map)` with the name of the map,
+//! - `on_tick(time)` every simulation tick, with the seconds since the match started,
+//! - `on_spawn(unit)` when a unit is deployed,
+//! - `on_damage(attacker, target, amount)` when a unit is hit,
+//! - `on_death(victim, killer)` when a unit dies, the killer being `()` if it's gone,
+//! - `winner(state)` every tick, ending the match if it returns the name of a side in play.
+//!
+//! Sides are `"left"` and `"right"`, and in free-for-all also `"top"` and `"bottom"`.
+//!
+//! Units are maps like `#{side: "left", name: "Fighter", hp: 10, x: -300.0, y: 25.0}`, and
+//! `state` is `#{time: 12.5, score: 30, map: "Hills", left: #{money: 20, units: 4}, right: ...}`,
+//! with the score counting towards the left side. Within a match, `this` is a map
+//! the script can keep anything it likes in.
+//!
+//! Scripts can call
+//!
+//! - `spawn(side, unit, y)` to deploy a unit free of charge in the side's zone, `y` going
+//!   from -1 at the bottom to 1 at the top,
+//! - `give_money(side, amount)`,
+//! - `announce(text)` to show a banner across the battlefield.
+
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use bevy::prelude::*;
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, Map, Scope, AST};
+
+use crate::{
+    ai::spawn_banner, combat::{self, DamageEvent, DeathEvent}, economy::Economy, maps::Maps, match_stats::MatchStats,
+    zone::SpawnZone, ControlOptions, GameState, Health, MatchConfig, MatchOverEvent, Materials, PerSide, Scoreboard,
+    Side, SpawnOrder, Team, UnitType, UNITS,
+};
+
+const SCRIPTS_DIR: &str = "scripts";
+/// How long a script's announcements stay up
+const ANNOUNCEMENT_TIME: f32 = 4.;
+
+pub struct ScriptingPlugin;
+
+impl Plugin for ScriptingPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(Scripts::load())
+            .add_system(start_scripts_system.in_schedule(OnEnter(GameState::Playing)))
+            .add_system(script_hook_system
+                .after(combat::damage_system)
+                .before(combat::death_system)
+                .run_if(in_state(GameState::Playing))
+                .in_schedule(CoreSchedule::FixedUpdate));
+    }
+}
+
+/// What scripts have asked for since it was last carried out
+enum Action {
+    Spawn { side: Side, name: &'static str, y: f32 },
+    GiveMoney(Side, u32),
+    Announce(String),
+}
+
+struct Script {
+    path: PathBuf,
+    ast: AST,
+    /// What the script keeps in `this` during a match
+    memory: Dynamic,
+}
+
+#[derive(Resource)]
+struct Scripts {
+    engine: Engine,
+    scripts: Vec<Script>,
+    actions: Arc<Mutex<Vec<Action>>>,
+}
+
+fn parse_side(side: &str) -> Result<Side, Box<EvalAltResult>> {
+    match side {
+        "left" => Ok(Side::Left),
+        "right" => Ok(Side::Right),
+        "top" => Ok(Side::Top),
+        "bottom" => Ok(Side::Bottom),
+        _ => Err(format!("no side called \"{side}\"").into()),
+    }
+}
+
+fn side_name(side: Side) -> &'static str {
+    match side {
+        Side::Left => "left",
+        Side::Right => "right",
+        Side::Top => "top",
+        Side::Bottom => "bottom",
+    }
+}
+
+impl Scripts {
+    fn load() -> Self {
+        let actions = Arc::new(Mutex::new(Vec::new()));
+        let mut engine = Engine::new();
+
+        let queue = actions.clone();
+        engine.register_fn("spawn", move |side: &str, name: &str, y: Dynamic| -> Result<(), Box<EvalAltResult>> {
+            let side = parse_side(side)?;
+            let skills = UNITS.iter()
+                .find(|skills| skills.name.eq_ignore_ascii_case(name))
+                .ok_or_else(|| format!("no unit called \"{name}\""))?;
+            let y = y.as_float().or_else(|_| y.as_int().map(|y| y as f64))?;
+            queue.lock().unwrap().push(Action::Spawn { side, name: skills.name, y: (y as f32).clamp(-1., 1.) });
+            Ok(())
+        });
+        let queue = actions.clone();
+        engine.register_fn("give_money", move |side: &str, amount: i64| -> Result<(), Box<EvalAltResult>> {
+            let side = parse_side(side)?;
+            queue.lock().unwrap().push(Action::GiveMoney(side, amount.clamp(0, u32::MAX as i64) as u32));
+            Ok(())
+        });
+        let queue = actions.clone();
+        engine.register_fn("announce", move |text: &str| {
+            queue.lock().unwrap().push(Action::Announce(text.to_owned()));
+        });
+
+        let mut paths: Vec<_> = fs::read_dir(SCRIPTS_DIR)
+            .map(|dir| dir.filter_map(|entry| entry.ok().map(|e| e.path())).collect())
+            .unwrap_or_default();
+        paths.retain(|path| path.extension().is_some_and(|ext| ext == "rhai"));
+        paths.sort();
+        let scripts = paths.into_iter().filter_map(|path| {
+            let compiled = fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|source| engine.compile(source).map_err(|e| e.to_string()));
+            match compiled {
+                Ok(ast) => Some(Script { path, ast, memory: Map::new().into() }),
+                Err(e) => {
+                    warn!("Could not load script {}: {e}", path.display());
+                    None
+                }
+            }
+        }).collect();
+
+        Scripts { engine, scripts, actions }
+    }
+
+    /// Calls `name` in every script defining it, returning what each returned
+    fn call(&mut self, name: &str, args: Vec<Dynamic>) -> Vec<Dynamic> {
+        let mut results = Vec::new();
+        for script in &mut self.scripts {
+            let defined = script.ast.iter_functions().any(|f| f.name == name && f.params.len() == args.len());
+            if !defined {
+                continue
+            }
+            let options = CallFnOptions::new().bind_this_ptr(&mut script.memory);
+            match self.engine.call_fn_with_options(options, &mut Scope::new(), &script.ast, name, args.clone()) {
+                Ok(result) => results.push(result),
+                Err(e) => warn!("Script {} failed in {name}: {e}", script.path.display()),
+            }
+        }
+        results
+    }
+}
+
+fn unit_map(transform: &Transform, (&Team(side), unit_type, health): (&Team, &UnitType, &Health)) -> Dynamic {
+    let mut unit = Map::new();
+    unit.insert("side".into(), side_name(side).into());
+    unit.insert("name".into(), unit_type.skills.name.into());
+    unit.insert("hp".into(), (health.hp as i64).into());
+    unit.insert("x".into(), (transform.translation.x as f64).into());
+    unit.insert("y".into(), (transform.translation.y as f64).into());
+    unit.into()
+}
+
+fn start_scripts_system(config: Res<MatchConfig>, maps: Res<Maps>, mut scripts: ResMut<Scripts>) {
+    if scripts.scripts.is_empty() {
+        return
+    }
+    for script in &mut scripts.scripts {
+        script.memory = Map::new().into();
+    }
+    scripts.call("on_match_start", vec![maps.0[config.map].name.clone().into()]);
+}
+
+/// Tells the scripts what happened this tick and carries out what they ask for
+fn script_hook_system(
+    mut commands: Commands,
+    mut scripts: ResMut<Scripts>,
+    mut damage_events: EventReader<DamageEvent>,
+    mut death_events: EventReader<DeathEvent>,
+    spawned_query: Query<(&Transform, (&Team, &UnitType, &Health)), Added<UnitType>>,
+    fighter_query: Query<(&Transform, (&Team, &UnitType, &Health))>,
+    scoreboard_query: Query<&Scoreboard>,
+    (config, maps, options, stats, zone, materials): (
+        Res<MatchConfig>, Res<Maps>, Res<ControlOptions>, Res<MatchStats>, Res<SpawnZone>, Res<Materials>,
+    ),
+    mut economy: ResMut<Economy>,
+    mut orders: EventWriter<SpawnOrder>,
+    mut match_over: EventWriter<MatchOverEvent>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if scripts.scripts.is_empty() {
+        return
+    }
+    let unit = |ent: Entity| fighter_query.get(ent).map_or(Dynamic::UNIT, |(transform, fighter)| unit_map(transform, fighter));
+
+    for (transform, fighter) in spawned_query.iter() {
+        scripts.call("on_spawn", vec![unit_map(transform, fighter)]);
+    }
+    for event in damage_events.iter() {
+        scripts.call("on_damage", vec![unit(event.attacker), unit(event.target), (event.amount as i64).into()]);
+    }
+    for event in death_events.iter() {
+        scripts.call("on_death", vec![unit(event.entity), unit(event.killer)]);
+    }
+    scripts.call("on_tick", vec![(stats.duration() as f64).into()]);
+
+    let mut units = PerSide::splat(0);
+    for (_, (&Team(side), ..)) in fighter_query.iter() {
+        units[side] += 1;
+    }
+    // The scoreboard counts towards the player's side
+    let score = scoreboard_query.iter().next().map_or(0, |scoreboard| match options.player_side {
+        Side::Left => scoreboard.score,
+        Side::Right => -scoreboard.score,
+        Side::Top | Side::Bottom => 0,
+    });
+    let mut state = Map::new();
+    state.insert("time".into(), (stats.duration() as f64).into());
+    state.insert("score".into(), (score as i64).into());
+    state.insert("map".into(), maps.0[config.map].name.clone().into());
+    for &side in config.sides() {
+        let mut info = Map::new();
+        info.insert("money".into(), (economy.money[side] as i64).into());
+        info.insert("units".into(), (units[side] as i64).into());
+        state.insert(side_name(side).into(), info.into());
+    }
+    let winner = scripts.call("winner", vec![state.into()]).into_iter()
+        .find_map(|result| match result.into_immutable_string().ok()?.as_str() {
+            "left" => Some(Side::Left),
+            "right" => Some(Side::Right),
+            "top" => Some(Side::Top),
+            "bottom" => Some(Side::Bottom),
+            _ => None,
+        })
+        .filter(|side| config.sides().contains(side));
+
+    let actions: Vec<Action> = scripts.actions.lock().unwrap().drain(..).collect();
+    for action in actions {
+        match action {
+            // The side has no zone to deploy in
+            Action::Spawn { side, .. } if !config.sides().contains(&side) => (),
+            Action::Spawn { side, name, y } => {
+                let rect = zone.0[side];
+                let skills = *UNITS.iter().find(|skills| skills.name == name).unwrap();
+                orders.send(SpawnOrder {
+                    side,
+                    position: Vec2::new(rect.center().x, rect.center().y + y * rect.height() / 2.),
+                    skills,
+                    prepaid: true,
+                });
+            }
+            Action::GiveMoney(side, amount) => economy.money[side] += amount,
+            Action::Announce(text) => spawn_banner(&mut commands, &materials, &text, Color::WHITE, ANNOUNCEMENT_TIME),
+        }
+    }
+    if let Some(winner) = winner {
+        match_over.send(MatchOverEvent { winner });
+        next_state.set(GameState::Results);
+    }
+}
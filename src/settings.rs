@@ -0,0 +1,459 @@
+use std::{fmt::Display, fs, io, path::PathBuf};
+
+use bevy::{
+    prelude::*,
+    window::{PresentMode, PrimaryWindow, WindowMode},
+};
+use toml::{Table, Value};
+
+use crate::{
+    ai::Difficulty, bindings::Bindings, locale::{Language, Locale}, palette::PALETTES, sound::SoundCategory, GameState,
+    MatchConfig, Materials,
+};
+
+const CONFIG_FILE: &str = "config.toml";
+/// Where older versions kept the settings, read until they're saved to [`CONFIG_FILE`]
+const LEGACY_SETTINGS_FILE: &str = "settings.txt";
+const RESOLUTIONS: [(f32, f32); 4] = [(1280., 720.), (1366., 768.), (1600., 900.), (1920., 1080.)];
+const GAME_SPEEDS: [f32; 4] = [0.5, 1., 1.5, 2.];
+const MAX_VOLUME: u8 = 10;
+/// Slowest and fastest the battle simulation can be set to tick, in ticks per second
+const TICK_RATES: (f32, f32) = (10., 240.);
+
+/// Where the file `file` is kept between sessions: in the platform's config folder, like
+/// `~/.config/sidewars` on Linux, or the working directory if there's no such folder
+pub fn config_path(file: &str) -> PathBuf {
+    dirs::config_dir().map_or_else(|| PathBuf::from(file), |base| base.join("sidewars").join(file))
+}
+
+/// Reads a file kept between sessions, or the one older versions left in the working directory if it hasn't been
+/// saved since
+pub fn read_config(file: &str) -> io::Result<String> {
+    fs::read_to_string(config_path(file)).or_else(|_| fs::read_to_string(file))
+}
+
+pub fn write_config(file: &str, contents: String) -> io::Result<()> {
+    let path = config_path(file);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, contents)
+}
+
+pub fn remove_config(file: &str) -> io::Result<()> {
+    fs::remove_file(config_path(file))
+}
+
+/// The keys and values of `[table]` in the TOML `contents`, with the values written out as text
+fn table_entries(contents: &str, table: &str) -> Option<Vec<(String, String)>> {
+    let mut tables: Table = contents.parse()
+        .map_err(|e| warn!("Ignoring {CONFIG_FILE} as it isn't valid TOML: {e}"))
+        .ok()?;
+    let Value::Table(entries) = tables.remove(table)? else {
+        return None
+    };
+    let text = |value| match value {
+        Value::String(s) => s,
+        value => value.to_string(),
+    };
+    Some(entries.into_iter().map(|(key, value)| (key, text(value))).collect())
+}
+
+/// The TOML `contents` with `[table]` holding just `entries`, leaving its other tables be. Values that read as
+/// booleans or numbers are written as such, and the rest as strings
+fn with_table(contents: &str, table: &str, entries: Vec<(String, String)>) -> Result<String, toml::ser::Error> {
+    let mut tables: Table = contents.parse().unwrap_or_default();
+    let value = |text: String| {
+        if let Ok(flag) = text.parse() {
+            Value::Boolean(flag)
+        } else if let Ok(int) = text.parse() {
+            Value::Integer(int)
+        } else if let Some(float) = text.parse().ok().filter(|float: &f64| float.is_finite()) {
+            Value::Float(float)
+        } else {
+            Value::String(text)
+        }
+    };
+    let entries = entries.into_iter().map(|(key, text)| (key, value(text))).collect();
+    tables.insert(table.to_owned(), Value::Table(entries));
+    toml::to_string(&tables)
+}
+
+/// Reads the `[table]` of `config.toml` as keys and values, or the tab-separated `legacy_file` that older versions
+/// kept them in if the table hasn't been saved yet
+pub fn read_config_table(table: &str, legacy_file: &str) -> Option<Vec<(String, String)>> {
+    let saved = read_config(CONFIG_FILE).ok().and_then(|contents| table_entries(&contents, table));
+    saved.or_else(|| {
+        let contents = read_config(legacy_file).ok()?;
+        let entries = contents.lines()
+            .filter_map(|line| line.split_once('\t'))
+            .map(|(key, value)| (key.to_owned(), value.trim().to_owned()))
+            .collect();
+        Some(entries)
+    })
+}
+
+/// Saves `entries` as the `[table]` of `config.toml`, leaving its other tables be
+pub fn write_config_table(table: &str, entries: Vec<(String, String)>) -> io::Result<()> {
+    let contents = read_config(CONFIG_FILE).unwrap_or_default();
+    let contents = with_table(&contents, table, entries).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    write_config(CONFIG_FILE, contents)
+}
+
+/// Options kept between sessions
+#[derive(Debug, Clone, Copy, PartialEq, Resource)]
+pub struct Settings {
+    /// Out of [`MAX_VOLUME`], for scaling all sound
+    volume: u8,
+    /// Out of [`MAX_VOLUME`], for each [`SoundCategory`]
+    sound_volumes: [u8; SoundCategory::ALL.len()],
+    /// Index into [`RESOLUTIONS`]
+    resolution: usize,
+    fullscreen: bool,
+    vsync: bool,
+    /// Index into [`GAME_SPEEDS`]
+    game_speed: usize,
+    /// Index into [`PALETTES`]
+    palette: usize,
+    /// Last difficulty selected in the menu
+    difficulty: Difficulty,
+    pub language: Language,
+    /// Window size asked for on the command line, used instead of the resolution until another one is picked
+    pub window_size: Option<(f32, f32)>,
+    /// Difficulty asked for on the command line, played instead of the saved one until another one is picked
+    pub difficulty_override: Option<Difficulty>,
+    /// Seconds without input before a match against the AI pauses itself, see `idle`
+    pub idle_timeout: f32,
+    /// Ticks per second the battle simulation runs at, independently of the frame rate
+    pub tick_rate: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            volume: 7,
+            sound_volumes: [MAX_VOLUME; SoundCategory::ALL.len()],
+            resolution: 0,
+            fullscreen: false,
+            vsync: true,
+            game_speed: 1,
+            palette: 0,
+            difficulty: Difficulty::default(),
+            language: Language::default(),
+            window_size: None,
+            difficulty_override: None,
+            idle_timeout: 60.,
+            tick_rate: 60.,
+        }
+    }
+}
+
+impl Settings {
+    /// Volume sounds of the given category play at
+    pub fn volume(&self, category: SoundCategory) -> f32 {
+        (self.volume * self.sound_volumes[category.index()]) as f32 / (MAX_VOLUME * MAX_VOLUME) as f32
+    }
+    /// Relative speed matches start at
+    pub fn game_speed(&self) -> f32 {
+        GAME_SPEEDS[self.game_speed]
+    }
+    pub fn load() -> Self {
+        let mut settings = Settings::default();
+        let Some(entries) = read_config_table("settings", LEGACY_SETTINGS_FILE) else {
+            return settings
+        };
+        for (name, value) in &entries {
+            let (name, value) = (name.as_str(), value.as_str());
+            let flag = value == "true" || value == "on";
+            match name {
+                "volume" => settings.volume = value.parse().unwrap_or(settings.volume).min(MAX_VOLUME),
+                "resolution" => {
+                    let found = RESOLUTIONS.iter().position(|&(w, h)| format!("{w}x{h}") == value);
+                    settings.resolution = found.unwrap_or(settings.resolution);
+                }
+                "fullscreen" => settings.fullscreen = flag,
+                "vsync" => settings.vsync = flag,
+                "game_speed" => {
+                    let found = GAME_SPEEDS.iter().position(|s| s.to_string() == value);
+                    settings.game_speed = found.unwrap_or(settings.game_speed);
+                }
+                "palette" => settings.palette = PALETTES.iter().position(|p| p.name == value).unwrap_or(settings.palette),
+                "difficulty" => {
+                    let found = Difficulty::ALL.into_iter().find(|d| d.name() == value);
+                    settings.difficulty = found.unwrap_or(settings.difficulty);
+                }
+                "language" => {
+                    let found = Language::ALL.into_iter().find(|l| l.name() == value);
+                    settings.language = found.unwrap_or(settings.language);
+                }
+                "idle_timeout" => {
+                    let found = value.parse().ok().filter(|&timeout: &f32| timeout > 0.);
+                    settings.idle_timeout = found.unwrap_or(settings.idle_timeout);
+                }
+                "tick_rate" => {
+                    let (slowest, fastest) = TICK_RATES;
+                    let found = value.parse().ok().filter(|rate: &f32| (slowest..=fastest).contains(rate));
+                    settings.tick_rate = found.unwrap_or(settings.tick_rate);
+                }
+                _ => {
+                    let category = SoundCategory::ALL.into_iter().find(|c| format!("{} volume", c.name()) == name);
+                    match category {
+                        Some(c) => {
+                            let volume = &mut settings.sound_volumes[c.index()];
+                            *volume = value.parse().unwrap_or(*volume).min(MAX_VOLUME);
+                        }
+                        None => warn!("Ignoring unknown setting {name}"),
+                    }
+                }
+            }
+        }
+        settings
+    }
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty_override.unwrap_or(self.difficulty)
+    }
+    /// Makes `difficulty` the one to play and remember
+    pub fn pick_difficulty(&mut self, difficulty: Difficulty) {
+        self.difficulty = difficulty;
+        self.difficulty_override = None;
+    }
+    pub fn save(&self) {
+        let (width, height) = RESOLUTIONS[self.resolution];
+        let mut entries = Vec::new();
+        let mut entry = |name: &str, value: &dyn Display| entries.push((name.to_owned(), value.to_string()));
+        entry("volume", &self.volume);
+        entry("resolution", &format!("{width}x{height}"));
+        entry("fullscreen", &self.fullscreen);
+        entry("vsync", &self.vsync);
+        entry("game_speed", &GAME_SPEEDS[self.game_speed]);
+        entry("palette", &PALETTES[self.palette].name);
+        entry("difficulty", &self.difficulty.name());
+        entry("language", &self.language.name());
+        entry("idle_timeout", &self.idle_timeout);
+        entry("tick_rate", &self.tick_rate);
+        for category in SoundCategory::ALL {
+            entry(&format!("{} volume", category.name()), &self.sound_volumes[category.index()]);
+        }
+        if let Err(e) = write_config_table("settings", entries) {
+            error!("Could not save settings: {e}");
+        }
+    }
+    fn lines(&self, locale: &Locale) -> Vec<String> {
+        let flag = |on| locale.text(if on { "settings.on" } else { "settings.off" });
+        let (width, height) = self.window_size.unwrap_or(RESOLUTIONS[self.resolution]);
+        let mut lines = vec![
+            locale.format("settings.volume", &[&self.volume, &MAX_VOLUME]),
+            locale.format("settings.resolution", &[&width, &height]),
+            locale.format("settings.fullscreen", &[&flag(self.fullscreen)]),
+            locale.format("settings.vsync", &[&flag(self.vsync)]),
+            locale.format("settings.game_speed", &[&GAME_SPEEDS[self.game_speed]]),
+            locale.format("settings.difficulty", &[&locale.name(self.difficulty().name())]),
+            locale.format("settings.colours", &[&locale.name(PALETTES[self.palette].name)]),
+            locale.format("settings.language", &[&self.language.name()]),
+        ];
+        for category in SoundCategory::ALL {
+            let volume = self.sound_volumes[category.index()];
+            lines.push(locale.format("settings.sound_volume", &[&locale.name(category.name()), &volume, &MAX_VOLUME]));
+        }
+        lines
+    }
+    /// Steps the setting on the given line of the screen one notch up or down
+    fn adjust(&mut self, line: usize, up: bool) {
+        let step = |i: usize, len: usize| if up { (i + 1) % len } else { (i + len - 1) % len };
+        let notch = |volume: u8| if up { (volume + 1).min(MAX_VOLUME) } else { volume.saturating_sub(1) };
+        match line {
+            0 => self.volume = notch(self.volume),
+            1 => {
+                self.resolution = step(self.resolution, RESOLUTIONS.len());
+                self.window_size = None;
+            }
+            2 => self.fullscreen = !self.fullscreen,
+            3 => self.vsync = !self.vsync,
+            4 => self.game_speed = step(self.game_speed, GAME_SPEEDS.len()),
+            5 => {
+                let difficulty = self.difficulty();
+                self.pick_difficulty(if up { difficulty.harder() } else { difficulty.easier() });
+            }
+            6 => self.palette = step(self.palette, PALETTES.len()),
+            7 => {
+                let i = Language::ALL.iter().position(|&l| l == self.language).unwrap();
+                self.language = Language::ALL[step(i, Language::ALL.len())];
+            }
+            sound => {
+                let volume = &mut self.sound_volumes[sound - 8];
+                *volume = notch(*volume);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct SettingsRoot;
+
+/// One line of the settings screen
+#[derive(Debug, Clone, Copy, Component)]
+pub struct SettingText(usize);
+
+/// Line of the settings screen being changed
+#[derive(Debug, Default, Clone, Copy, Resource)]
+pub struct SettingCursor(usize);
+
+/// Text on the settings screen other than the settings, by its key in the [`Locale`],
+/// so it follows along when the language is changed
+#[derive(Debug, Clone, Copy, Component)]
+pub struct SettingsLabel(&'static str);
+
+pub fn setup_settings(mut commands: Commands, materials: Res<Materials>, settings: Res<Settings>, locale: Res<Locale>) {
+    let style = |font_size| TextStyle {
+        font: materials.font.clone(),
+        font_size,
+        color: Color::WHITE,
+    };
+    commands.insert_resource(SettingCursor::default());
+    commands.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            gap: Size::all(Val::Px(10.)),
+            ..default()
+        },
+        background_color: Color::rgba(0., 0., 0., 0.75).into(),
+        z_index: ZIndex::Global(5),
+        ..default()
+    })
+    .insert(SettingsRoot)
+    .with_children(|parent| {
+        parent.spawn(TextBundle::from_section(locale.text("settings.title"), style(60.)))
+            .insert(SettingsLabel("settings.title"));
+        for (i, line) in settings.lines(&locale).into_iter().enumerate() {
+            parent.spawn(TextBundle::from_section(line, style(24.))).insert(SettingText(i));
+        }
+        parent.spawn(TextBundle::from_section(locale.text("settings.controls"), style(20.)))
+            .insert(SettingsLabel("settings.controls"));
+    });
+}
+
+pub fn settings_input_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut settings: ResMut<Settings>,
+    mut cursor: ResMut<SettingCursor>,
+    mut next_state: ResMut<NextState<GameState>>,
+    locale: Res<Locale>,
+    mut query: Query<(&mut Text, &SettingText)>,
+    mut label_query: Query<(&mut Text, &SettingsLabel), Without<SettingText>>,
+) {
+    let count = settings.lines(&locale).len();
+    if keyboard_input.just_pressed(KeyCode::Down) {
+        cursor.0 = (cursor.0 + 1) % count;
+    }
+    if keyboard_input.just_pressed(KeyCode::Up) {
+        cursor.0 = (cursor.0 + count - 1) % count;
+    }
+    // Only what's changed here is saved, not the display hotkeys or what was asked for on the command line
+    for (key, up) in [(KeyCode::Right, true), (KeyCode::Left, false)] {
+        if keyboard_input.just_pressed(key) {
+            settings.adjust(cursor.0, up);
+            settings.save();
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_state.set(GameState::Menu);
+    }
+
+    let lines = settings.lines(&locale);
+    for (mut text, &SettingText(i)) in query.iter_mut() {
+        text.sections[0].value = lines[i].clone();
+        text.sections[0].style.color = if i == cursor.0 { Color::rgb(1.0, 0.84, 0.0) } else { Color::WHITE };
+    }
+    if locale.is_changed() {
+        for (mut text, &SettingsLabel(key)) in label_query.iter_mut() {
+            text.sections[0].value = locale.text(key).to_owned();
+        }
+    }
+}
+
+pub fn cleanup_settings(mut commands: Commands, query: Query<Entity, With<SettingsRoot>>) {
+    for ent in query.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+}
+
+/// Switches fullscreen and resolution from anywhere in the game, not just the settings screen
+pub fn display_hotkey_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    mut settings: ResMut<Settings>,
+) {
+    if keyboard_input.just_pressed(bindings.fullscreen) {
+        settings.fullscreen = !settings.fullscreen;
+    }
+    if keyboard_input.just_pressed(bindings.next_resolution) {
+        settings.resolution = (settings.resolution + 1) % RESOLUTIONS.len();
+        settings.window_size = None;
+    }
+}
+
+/// Keeps the window, colours and default difficulty in line with the settings. The game speed is only
+/// picked up when the next match starts, see `speed::reset_speed`, so it doesn't undo the speed picked mid-match
+pub fn apply_settings_system(
+    settings: Res<Settings>,
+    mut config: ResMut<MatchConfig>,
+    mut materials: ResMut<Materials>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !settings.is_changed() {
+        return
+    }
+    if let Ok(mut window) = window_query.get_single_mut() {
+        let (width, height) = settings.window_size.unwrap_or(RESOLUTIONS[settings.resolution]);
+        window.resolution.set(width, height);
+        window.mode = if settings.fullscreen { WindowMode::BorderlessFullscreen } else { WindowMode::Windowed };
+        window.present_mode = if settings.vsync { PresentMode::AutoVsync } else { PresentMode::AutoNoVsync };
+    }
+    config.difficulty = settings.difficulty();
+    PALETTES[settings.palette].apply(&mut materials);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|&(key, value)| (key.to_owned(), value.to_owned())).collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let saved = entries(&[
+            ("volume", "7"),
+            ("game_speed", "1.5"),
+            ("vsync", "true"),
+            ("Effects volume", "10"),
+            ("a = b", "quote \" backslash \\ tab \t newline \n"),
+            ("palette", "Night # not a comment"),
+        ]);
+        let contents = with_table("", "settings", saved.clone()).unwrap();
+        assert_eq!(table_entries(&contents, "settings"), Some(saved));
+    }
+
+    #[test]
+    fn other_tables_are_kept() {
+        let contents = with_table("", "bindings", entries(&[("pause", "Space")])).unwrap();
+        let contents = with_table(&contents, "settings", entries(&[("volume", "3")])).unwrap();
+        let contents = with_table(&contents, "settings", entries(&[("volume", "4")])).unwrap();
+        assert_eq!(table_entries(&contents, "bindings"), Some(entries(&[("pause", "Space")])));
+        assert_eq!(table_entries(&contents, "settings"), Some(entries(&[("volume", "4")])));
+    }
+
+    #[test]
+    fn hand_edited() {
+        let contents = "# Edited by hand\n[settings]\nvolume = 5 # louder\n\"key = with equals\" = 'literal'\n";
+        let read = table_entries(contents, "settings");
+        assert_eq!(read, Some(entries(&[("volume", "5"), ("key = with equals", "literal")])));
+        assert_eq!(table_entries(contents, "bindings"), None);
+        assert_eq!(table_entries("[settings\nvolume = 5", "settings"), None);
+    }
+}
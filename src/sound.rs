@@ -0,0 +1,100 @@
+//! Sound effects, played in response to what happens in battle.
+//! Expects `hit.ogg`, `miss.ogg`, `death.ogg`, `deploy.ogg` and `siege.ogg` in `assets/sounds`
+
+use bevy::prelude::*;
+
+use crate::{
+    combat::{DamageEvent, DeathEvent, MissEvent},
+    settings::Settings,
+    UnitName,
+};
+
+/// Kinds of sounds with their own volume
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoundCategory {
+    Combat,
+    Deaths,
+    Deployment,
+    Siege,
+    Music,
+}
+
+impl SoundCategory {
+    pub const ALL: [SoundCategory; 5] = [
+        SoundCategory::Combat, SoundCategory::Deaths, SoundCategory::Deployment, SoundCategory::Siege,
+        SoundCategory::Music,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SoundCategory::Combat => "Combat",
+            SoundCategory::Deaths => "Deaths",
+            SoundCategory::Deployment => "Deployment",
+            SoundCategory::Siege => "Siege",
+            SoundCategory::Music => "Music",
+        }
+    }
+    pub fn index(self) -> usize {
+        Self::ALL.iter().position(|&c| c == self).unwrap()
+    }
+}
+
+/// Sent when a unit makes it to the enemy's edge of the battlefield
+#[derive(Debug, Clone)]
+pub struct SiegeEvent {
+    pub name: UnitName,
+}
+
+#[derive(Debug, Clone, Resource)]
+pub struct SoundAssets {
+    hit: Handle<AudioSource>,
+    miss: Handle<AudioSource>,
+    death: Handle<AudioSource>,
+    deploy: Handle<AudioSource>,
+    siege: Handle<AudioSource>,
+}
+
+pub fn setup_sounds(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(SoundAssets {
+        hit: asset_server.load("sounds/hit.ogg"),
+        miss: asset_server.load("sounds/miss.ogg"),
+        death: asset_server.load("sounds/death.ogg"),
+        deploy: asset_server.load("sounds/deploy.ogg"),
+        siege: asset_server.load("sounds/siege.ogg"),
+    });
+}
+
+fn play(audio: &Audio, settings: &Settings, sound: &Handle<AudioSource>, category: SoundCategory) {
+    let volume = settings.volume(category);
+    if volume > 0. {
+        audio.play_with_settings(sound.clone(), PlaybackSettings::ONCE.with_volume(volume));
+    }
+}
+
+/// Plays at most one sound of each kind per frame, however much is going on
+pub fn sound_effect_system(
+    audio: Res<Audio>,
+    settings: Res<Settings>,
+    sounds: Res<SoundAssets>,
+    mut damage_events: EventReader<DamageEvent>,
+    mut miss_events: EventReader<MissEvent>,
+    mut death_events: EventReader<DeathEvent>,
+    mut siege_events: EventReader<SiegeEvent>,
+    deployed: Query<(), Added<UnitName>>,
+) {
+    if damage_events.iter().count() > 0 {
+        play(&audio, &settings, &sounds.hit, SoundCategory::Combat);
+    }
+    if miss_events.iter().count() > 0 {
+        play(&audio, &settings, &sounds.miss, SoundCategory::Combat);
+    }
+    if death_events.iter().count() > 0 {
+        play(&audio, &settings, &sounds.death, SoundCategory::Deaths);
+    }
+    if siege_events.iter().count() > 0 {
+        play(&audio, &settings, &sounds.siege, SoundCategory::Siege);
+    }
+    if !deployed.is_empty() {
+        play(&audio, &settings, &sounds.deploy, SoundCategory::Deployment);
+    }
+}
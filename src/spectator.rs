@@ -0,0 +1,114 @@
+//! Watching the AI play both sides. Player input is switched off in `main`,
+//! this adds a free camera and a panel revealing what each side is up to.
+
+use bevy::prelude::*;
+
+use crate::{
+    ai::AiPlans, ai_strategy::AiPlayers, bindings::Bindings, camera::CameraFocus, economy::Economy, locale::Locale,
+    spells::ManaText,
+    supply::Supply, toolbar::Toolbar, MatchConfig, Materials,
+};
+
+/// The player's own part of the HUD, which spectators have no use for
+type PlayerHud = Or<(With<Toolbar>, With<ManaText>)>;
+
+/// Each side's money and what its AI deploys next
+#[derive(Debug, Clone, Copy, Component)]
+pub struct SpectatorPanel;
+
+pub fn setup_spectator(
+    mut commands: Commands,
+    config: Res<MatchConfig>,
+    materials: Res<Materials>,
+    mut focus: ResMut<CameraFocus>,
+    mut hud_query: Query<&mut Visibility, PlayerHud>,
+) {
+    if !config.spectating {
+        return
+    }
+    *focus = CameraFocus::Free;
+    for mut visibility in hud_query.iter_mut() {
+        *visibility = Visibility::Hidden;
+    }
+    commands.spawn(TextBundle {
+        text: Text::from_section("", TextStyle {
+            font: materials.font.clone(),
+            font_size: 16.,
+            color: Color::WHITE,
+        }),
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                bottom: Val::Px(5.),
+                left: Val::Px(5.),
+                ..default()
+            },
+            ..default()
+        },
+        background_color: Color::rgba(0., 0., 0., 0.4).into(),
+        ..default()
+    }).insert(SpectatorPanel);
+}
+
+pub fn spectator_panel_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    config: Res<MatchConfig>,
+    economy: Res<Economy>,
+    supply: Res<Supply>,
+    plans: Res<AiPlans>,
+    players: Res<AiPlayers>,
+    locale: Res<Locale>,
+    mut query: Query<(&mut Text, &mut Visibility), With<SpectatorPanel>>,
+) {
+    let Ok((mut text, mut visibility)) = query.get_single_mut() else {
+        return
+    };
+    if keyboard_input.just_pressed(bindings.reveal_plans) {
+        *visibility = match *visibility {
+            Visibility::Hidden => Visibility::Inherited,
+            _ => Visibility::Hidden,
+        };
+    }
+
+    let lines: Vec<String> = config.sides().iter().map(|&side| {
+        let plan = &plans.0[side];
+        let mut line = locale.format("spectator.side", &[
+            &locale.name(&side.to_string()),
+            &locale.name(config.strategies[side].name()),
+            &economy.money[side],
+            &format!("{:.2}", economy.income_rate(side)),
+            &format!("{:.1}", plan.spawn_cooldown.max(0.)),
+        ]);
+        if let Some(unit) = players.0[side].saving_for() {
+            line += &locale.format("spectator.saving", &[&locale.name(unit)]);
+        }
+        if let Some(cap) = supply.cap {
+            line += &locale.format("spectator.supply", &[&supply.used[side], &cap]);
+        }
+        if let Some((wave, time_left)) = plan.incoming_wave() {
+            line += &locale.format("spectator.wave", &[&locale.name(wave), &time_left.ceil()]);
+        }
+        line
+    }).collect();
+    text.sections[0].value = lines.join("\n");
+}
+
+pub fn cleanup_spectator(
+    mut commands: Commands,
+    config: Res<MatchConfig>,
+    mut focus: ResMut<CameraFocus>,
+    query: Query<Entity, With<SpectatorPanel>>,
+    mut hud_query: Query<&mut Visibility, PlayerHud>,
+) {
+    if !config.spectating {
+        return
+    }
+    *focus = CameraFocus::default();
+    for ent in query.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+    for mut visibility in hud_query.iter_mut() {
+        *visibility = Visibility::Inherited;
+    }
+}
@@ -0,0 +1,180 @@
+//! Pausing, slowing down and speeding up the battle. Everything that ticks with
+//! game time follows [`Time`]'s relative speed, including the fixed timestep
+//! the simulation runs on, so that's all there is to change.
+
+use bevy::prelude::*;
+
+use crate::{bindings::Bindings, locale::Locale, minimap::Minimap, settings::Settings, Materials};
+
+/// Game speeds to pick from, slowest first
+const SPEEDS: [f32; 4] = [0.5, 1., 2., 4.];
+
+const BUTTON_COLOR: Color = Color::rgba(0., 0., 0., 0.5);
+const HOVERED_COLOR: Color = Color::rgba(0.2, 0.2, 0.2, 0.7);
+const SELECTED_COLOR: Color = Color::rgb(1.0, 0.84, 0.0);
+const UNSELECTED_COLOR: Color = Color::rgb(0.7, 0.7, 0.7);
+
+/// Row of speed buttons sitting on top of the minimap
+#[derive(Debug, Clone, Copy, Component)]
+pub struct SpeedPanel;
+
+#[derive(Debug, Clone, Copy, PartialEq, Component)]
+pub enum SpeedButton {
+    Pause,
+    /// Runs a single simulation tick and pauses
+    Step,
+    Speed(f32),
+}
+
+impl SpeedButton {
+    fn label(self, locale: &Locale) -> String {
+        match self {
+            SpeedButton::Pause => locale.text("speed.pause").to_owned(),
+            SpeedButton::Step => locale.text("speed.step").to_owned(),
+            SpeedButton::Speed(speed) => format!("{speed}x"),
+        }
+    }
+    fn press(self, time: &mut Time, fixed_time: &mut FixedTime) {
+        match self {
+            SpeedButton::Pause => if time.is_paused() {
+                time.unpause();
+            } else {
+                time.pause();
+            }
+            SpeedButton::Step => {
+                time.pause();
+                let period = fixed_time.period;
+                fixed_time.tick(period);
+            }
+            SpeedButton::Speed(speed) => {
+                time.set_relative_speed(speed);
+                time.unpause();
+            }
+        }
+    }
+    fn selected(self, time: &Time) -> bool {
+        match self {
+            SpeedButton::Pause => time.is_paused(),
+            SpeedButton::Step => false,
+            SpeedButton::Speed(speed) => !time.is_paused() && time.relative_speed() == speed,
+        }
+    }
+}
+
+/// The closest speed in `SPEEDS` above or below the current one, which the settings may have put between them
+fn step_speed(time: &Time, faster: bool) -> f32 {
+    let current = time.relative_speed();
+    if faster {
+        SPEEDS.into_iter().find(|&speed| speed > current).unwrap_or(SPEEDS[SPEEDS.len() - 1])
+    } else {
+        SPEEDS.into_iter().rev().find(|&speed| speed < current).unwrap_or(SPEEDS[0])
+    }
+}
+
+/// Every match starts at the speed picked in the settings
+pub fn reset_speed(settings: Res<Settings>, mut time: ResMut<Time>) {
+    time.set_relative_speed(settings.game_speed());
+    time.unpause();
+}
+
+pub fn setup_speed_panel(mut commands: Commands, materials: Res<Materials>, locale: Res<Locale>) {
+    commands.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                bottom: Val::Px(5.),
+                right: Val::Px(5.),
+                ..default()
+            },
+            gap: Size::all(Val::Px(2.)),
+            ..default()
+        },
+        ..default()
+    }).insert(SpeedPanel).with_children(|panel| {
+        let buttons = [SpeedButton::Pause, SpeedButton::Step].into_iter()
+            .chain(SPEEDS.map(SpeedButton::Speed));
+        for button in buttons {
+            panel.spawn(ButtonBundle {
+                style: Style {
+                    padding: UiRect::all(Val::Px(3.)),
+                    ..default()
+                },
+                background_color: BUTTON_COLOR.into(),
+                ..default()
+            }).insert(button).with_children(|parent| {
+                parent.spawn(TextBundle::from_section(button.label(&locale), TextStyle {
+                    font: materials.font.clone(),
+                    font_size: 14.,
+                    color: UNSELECTED_COLOR,
+                }));
+            });
+        }
+    });
+}
+
+pub fn speed_input_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    mut time: ResMut<Time>,
+    mut fixed_time: ResMut<FixedTime>,
+    query: Query<(&Interaction, &SpeedButton), Changed<Interaction>>,
+) {
+    let mut pressed: Vec<SpeedButton> = query.iter()
+        .filter(|(&interaction, _)| interaction == Interaction::Clicked)
+        .map(|(_, &button)| button)
+        .collect();
+    if keyboard_input.just_pressed(bindings.pause) {
+        pressed.push(SpeedButton::Pause);
+    }
+    if keyboard_input.just_pressed(bindings.frame_step) {
+        pressed.push(SpeedButton::Step);
+    }
+    if keyboard_input.just_pressed(bindings.slower) {
+        pressed.push(SpeedButton::Speed(step_speed(&time, false)));
+    }
+    if keyboard_input.just_pressed(bindings.faster) {
+        pressed.push(SpeedButton::Speed(step_speed(&time, true)));
+    }
+    for button in pressed {
+        button.press(&mut time, &mut fixed_time);
+    }
+}
+
+/// Highlights the current speed and keeps the panel just above the minimap
+pub fn speed_panel_system(
+    time: Res<Time>,
+    minimap_query: Query<&Node, With<Minimap>>,
+    mut panel_query: Query<&mut Style, With<SpeedPanel>>,
+    mut button_query: Query<(&SpeedButton, &Interaction, &Children, &mut BackgroundColor)>,
+    mut text_query: Query<&mut Text>,
+) {
+    if let (Ok(minimap), Ok(mut style)) = (minimap_query.get_single(), panel_query.get_single_mut()) {
+        let bottom = Val::Px(minimap.size().y + 10.);
+        if style.position.bottom != bottom {
+            style.position.bottom = bottom;
+        }
+    }
+    for (&button, interaction, children, mut background) in button_query.iter_mut() {
+        *background = if *interaction == Interaction::None {
+            BUTTON_COLOR
+        } else {
+            HOVERED_COLOR
+        }.into();
+        let color = if button.selected(&time) {
+            SELECTED_COLOR
+        } else {
+            UNSELECTED_COLOR
+        };
+        for &child in children {
+            if let Ok(mut text) = text_query.get_mut(child) {
+                text.sections[0].style.color = color;
+            }
+        }
+    }
+}
+
+pub fn cleanup_speed_panel(mut commands: Commands, query: Query<Entity, With<SpeedPanel>>) {
+    for ent in query.iter() {
+        commands.entity(ent).despawn_recursive();
+    }
+}
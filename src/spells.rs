@@ -0,0 +1,253 @@
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+
+use crate::{
+    bindings::Bindings,
+    combat::{DamageEvent, HealEvent},
+    locale::Locale,
+    status::{EffectKind, StatusEffects},
+    ControlOptions, Health, Materials, MouseLoc, Team,
+};
+
+const MAX_MANA: f32 = 100.;
+const MANA_PER_SECOND: f32 = 4.;
+/// Seconds between the pulses of lingering spells
+const PULSE_INTERVAL: f32 = 0.5;
+const FIREBALL_DAMAGE: u8 = 5;
+/// HP restored by every pulse of a heal zone
+const HEAL_PER_PULSE: u8 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Spell {
+    /// Burns every enemy in the blast
+    Fireball,
+    /// Keeps healing allies standing in it
+    HealZone,
+    /// Slows down enemies walking through it
+    SlowField,
+}
+
+impl Spell {
+    pub const ALL: [Spell; 3] = [Spell::Fireball, Spell::HealZone, Spell::SlowField];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Spell::Fireball => "Fireball",
+            Spell::HealZone => "Heal zone",
+            Spell::SlowField => "Slow field",
+        }
+    }
+    pub fn key(self, bindings: &Bindings) -> KeyCode {
+        match self {
+            Spell::Fireball => bindings.fireball,
+            Spell::HealZone => bindings.heal_zone,
+            Spell::SlowField => bindings.slow_field,
+        }
+    }
+    pub fn cost(self) -> f32 {
+        match self {
+            Spell::Fireball => 40.,
+            Spell::HealZone => 30.,
+            Spell::SlowField => 25.,
+        }
+    }
+    fn radius(self) -> f32 {
+        match self {
+            Spell::Fireball => 50.,
+            Spell::HealZone => 70.,
+            Spell::SlowField => 90.,
+        }
+    }
+    /// How long the spell lingers on the battlefield
+    fn duration(self) -> f32 {
+        match self {
+            Spell::Fireball => 0.3,
+            Spell::HealZone => 5.,
+            Spell::SlowField => 6.,
+        }
+    }
+    fn color(self) -> Color {
+        match self {
+            Spell::Fireball => Color::rgba(1., 0.4, 0., 0.5),
+            Spell::HealZone => Color::rgba(0., 1., 0.3, 0.2),
+            Spell::SlowField => Color::rgba(0., 0.8, 1., 0.2),
+        }
+    }
+}
+
+/// What the player has to spend on spells
+#[derive(Debug, Clone, Copy, Resource)]
+pub struct Mana(pub f32);
+
+impl Default for Mana {
+    fn default() -> Self {
+        Mana(MAX_MANA)
+    }
+}
+
+/// A spell in effect on the battlefield
+#[derive(Debug, Clone, Copy, Component)]
+pub struct SpellArea {
+    spell: Spell,
+    time_left: f32,
+    until_pulse: f32,
+}
+
+#[derive(Debug, Clone, Resource)]
+pub struct SpellAssets {
+    circle: Mesh2dHandle,
+    materials: Vec<Handle<ColorMaterial>>,
+}
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct ManaText;
+
+pub fn setup_spells(
+    mut commands: Commands,
+    materials: Res<Materials>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(SpellAssets {
+        circle: meshes.add(shape::Circle::new(1.).into()).into(),
+        materials: Spell::ALL.iter().map(|s| color_materials.add(ColorMaterial::from(s.color()))).collect(),
+    });
+    commands.spawn(TextBundle {
+        text: Text::from_section("", TextStyle {
+            font: materials.font.clone(),
+            color: Color::rgb(0.4, 0.6, 1.),
+            font_size: 24.0,
+        }),
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                bottom: Val::Px(35.0),
+                left: Val::Px(5.0),
+                ..default()
+            },
+            ..default()
+        },
+        ..default()
+    }).insert(ManaText);
+}
+
+pub fn reset_mana(mut mana: ResMut<Mana>) {
+    *mana = Mana::default();
+}
+
+pub fn mana_regen_system(fixed_time: Res<FixedTime>, mut mana: ResMut<Mana>) {
+    mana.0 = (mana.0 + MANA_PER_SECOND * fixed_time.period.as_secs_f32()).min(MAX_MANA);
+}
+
+/// Casts spells at the mouse with their hotkeys, anywhere on the battlefield
+pub fn spell_cast_system(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    mouse_loc: Res<MouseLoc>,
+    options: Res<ControlOptions>,
+    assets: Res<SpellAssets>,
+    mut mana: ResMut<Mana>,
+) {
+    for (i, spell) in Spell::ALL.into_iter().enumerate() {
+        if !keyboard_input.just_pressed(spell.key(&bindings)) || mana.0 < spell.cost() {
+            continue
+        }
+        mana.0 -= spell.cost();
+
+        commands.spawn(MaterialMesh2dBundle {
+            mesh: assets.circle.clone(),
+            material: assets.materials[i].clone(),
+            transform: Transform::from_translation(mouse_loc.0.extend(-0.5))
+                .with_scale(Vec3::splat(spell.radius())),
+            ..default()
+        }).insert((
+            SpellArea {
+                spell,
+                time_left: spell.duration(),
+                until_pulse: 0.,
+            },
+            Team(options.player_side),
+        ));
+    }
+}
+
+/// Makes spells affect the units within them
+pub fn spell_area_system(
+    mut commands: Commands,
+    fixed_time: Res<FixedTime>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut heal_events: EventWriter<HealEvent>,
+    mut areas: Query<(Entity, &mut SpellArea, &Team, &Transform)>,
+    mut query: Query<(Entity, &Team, &mut Health, &mut StatusEffects, &Transform)>,
+) {
+    let delta = fixed_time.period.as_secs_f32();
+
+    for (area_ent, mut area, &area_team, area_trans) in areas.iter_mut() {
+        area.until_pulse -= delta;
+        if area.until_pulse <= 0. {
+            area.until_pulse += PULSE_INTERVAL;
+
+            let center = area_trans.translation.truncate();
+            let radius = area.spell.radius();
+            for (ent, &team, mut health, mut status, transform) in query.iter_mut() {
+                if transform.translation.truncate().distance(center) > radius {
+                    continue
+                }
+                let ally = team == area_team;
+                match (area.spell, ally) {
+                    (Spell::Fireball, false) => {
+                        damage_events.send(DamageEvent {
+                            attacker: area_ent,
+                            attacker_side: area_team.0,
+                            target: ent,
+                            amount: FIREBALL_DAMAGE,
+                            effect: None,
+                            critical: false,
+                        });
+                        status.apply(EffectKind::Burn, area_ent, area_team.0);
+                    }
+                    (Spell::HealZone, true) if health.wounded() => {
+                        let amount = HEAL_PER_PULSE.min(health.max - health.hp);
+                        health.hp += amount;
+                        heal_events.send(HealEvent { healer: area_ent, target: ent, amount });
+                    }
+                    (Spell::SlowField, false) => status.apply(EffectKind::Slow, area_ent, area_team.0),
+                    _ => (),
+                }
+            }
+            // A fireball hits only once
+            if area.spell == Spell::Fireball {
+                area.until_pulse = f32::INFINITY;
+            }
+        }
+
+        area.time_left -= delta;
+        if area.time_left <= 0. {
+            commands.entity(area_ent).despawn();
+        }
+    }
+}
+
+pub fn mana_text_system(
+    mana: Res<Mana>,
+    locale: Res<Locale>,
+    bindings: Res<Bindings>,
+    mut query: Query<&mut Text, With<ManaText>>,
+) {
+    for mut text in query.iter_mut() {
+        let spells: Vec<_> = Spell::ALL.iter()
+            .map(|s| format!("[{:?}] {} {}", s.key(&bindings), locale.name(s.name()), s.cost()))
+            .collect();
+        let mana = locale.format("spells.mana", &[&format!("{:.0}", mana.0), &MAX_MANA]);
+        text.sections[0].value = format!("{mana}   {}", spells.join("  "));
+    }
+}
+
+pub fn cleanup_spells(mut commands: Commands, query: Query<Entity, With<SpellArea>>) {
+    for ent in query.iter() {
+        commands.entity(ent).despawn();
+    }
+}
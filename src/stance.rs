@@ -0,0 +1,100 @@
+use bevy::prelude::*;
+
+use crate::{
+    bindings::Bindings, hit_test, ClickAction, ControlOptions, MatchConfig, MouseLoc, PerSide, Team, UnitType,
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
+pub enum Stance {
+    /// Advances, steers towards enemies close by and turns around for ones that got past it
+    #[default]
+    Aggressive,
+    /// Advances along its row and only fights what gets in its way
+    Defensive,
+    /// Holds its position and only fights what reaches it
+    HoldPosition,
+}
+
+impl Stance {
+    pub fn name(self) -> &'static str {
+        match self {
+            Stance::Aggressive => "Aggressive",
+            Stance::Defensive => "Defensive",
+            Stance::HoldPosition => "Hold position",
+        }
+    }
+    fn next(self) -> Self {
+        match self {
+            Stance::Aggressive => Stance::Defensive,
+            Stance::Defensive => Stance::HoldPosition,
+            Stance::HoldPosition => Stance::Aggressive,
+        }
+    }
+    /// Whether it marches on towards the enemy's edge
+    pub fn advances(self) -> bool {
+        self != Stance::HoldPosition
+    }
+    /// Whether it goes after enemies close by rather than waiting for them to come to it
+    pub fn chases(self) -> bool {
+        self == Stance::Aggressive
+    }
+}
+
+/// The stance each side's units take
+#[derive(Debug, Default, Clone, Copy, Resource)]
+pub struct DefaultStance(pub PerSide<Stance>);
+
+pub fn reset_stances(mut stances: ResMut<DefaultStance>) {
+    *stances = DefaultStance::default();
+}
+
+/// Switches the stance of the player's whole army, and of the units they deploy from then on
+pub fn stance_toggle_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    options: Res<ControlOptions>,
+    mut stances: ResMut<DefaultStance>,
+    mut query: Query<(&mut Stance, &Team), With<UnitType>>,
+) {
+    if !keyboard_input.just_pressed(bindings.stance) {
+        return
+    }
+    let side = options.player_side;
+    let stance = stances.0[side].next();
+    stances.0[side] = stance;
+
+    for (mut unit_stance, &Team(unit_side)) in query.iter_mut() {
+        if unit_side == side {
+            *unit_stance = stance;
+        }
+    }
+}
+
+/// Clicking one of your own units with the unit stance key held switches the stance of just that unit
+pub fn unit_stance_system(
+    mouse_loc: Res<MouseLoc>,
+    mouse_button: Res<Input<MouseButton>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    options: Res<ControlOptions>,
+    bindings: Res<Bindings>,
+    config: Res<MatchConfig>,
+    mut query: Query<(&Transform, &TextureAtlasSprite, &Team, &UnitType, &mut Stance)>,
+) {
+    if !matches!(ClickAction::held(&keyboard_input, &bindings), ClickAction::Stance) {
+        return
+    }
+    for &button in mouse_button.get_just_pressed() {
+        let Some(side) = options.button_side(&bindings, button) else {
+            continue
+        };
+        if config.versus_ai && side != options.player_side {
+            continue
+        }
+        let clicked = query.iter_mut()
+            .filter(|(_, _, &Team(s), unit, _)| s == side && !unit.skills.is_structure())
+            .find(|(t, s, ..)| hit_test(t, s, mouse_loc.0));
+        if let Some((.., mut stance)) = clicked {
+            *stance = stance.next();
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! Corner overlay with the frame rate, how many fighters are on the field and
+//! what each side has to spend, for spotting slowdowns in big battles.
+
+use bevy::{
+    diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+};
+
+use crate::{
+    bindings::Bindings, economy::Economy, locale::Locale, GameState, MatchConfig, Materials, PerSide, Team, UnitType,
+};
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct StatsOverlay;
+
+pub fn setup_stats_overlay(mut commands: Commands, materials: Res<Materials>) {
+    commands.spawn(TextBundle {
+        text: Text::from_section("", TextStyle {
+            font: materials.font.clone(),
+            font_size: 14.,
+            color: Color::WHITE,
+        }),
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(30.),
+                left: Val::Px(5.),
+                ..default()
+            },
+            ..default()
+        },
+        background_color: Color::rgba(0., 0., 0., 0.6).into(),
+        z_index: ZIndex::Global(10),
+        visibility: Visibility::Hidden,
+        ..default()
+    }).insert(StatsOverlay);
+}
+
+/// Toggles the overlay and keeps its numbers current while shown
+pub fn stats_overlay_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    state: Res<State<GameState>>,
+    diagnostics: Res<Diagnostics>,
+    locale: Res<Locale>,
+    economy: Res<Economy>,
+    config: Res<MatchConfig>,
+    fighter_query: Query<&Team, With<UnitType>>,
+    mut overlay_query: Query<(&mut Text, &mut Visibility), With<StatsOverlay>>,
+) {
+    let Ok((mut text, mut visibility)) = overlay_query.get_single_mut() else {
+        return
+    };
+    // Only toggled during matches, as the menu toggles mutators with the F keys
+    if state.0 == GameState::Playing && keyboard_input.just_pressed(bindings.stats_overlay) {
+        *visibility = match *visibility {
+            Visibility::Hidden => Visibility::Inherited,
+            _ => Visibility::Hidden,
+        };
+    }
+    if *visibility == Visibility::Hidden {
+        return
+    }
+
+    let fps = diagnostics.get(FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.smoothed())
+        .unwrap_or_default();
+    let mut fighters = PerSide::splat(0);
+    for &Team(side) in fighter_query.iter() {
+        fighters[side] += 1;
+    }
+
+    let mut contents = locale.format("overlay.performance", &[&format!("{fps:.0}")]);
+    for &side in config.sides() {
+        contents += &locale.format("overlay.side", &[
+            &locale.name(&side.to_string()),
+            &fighters[side],
+            &economy.money[side],
+            &format!("{:.2}", economy.income_rate(side)),
+        ]);
+    }
+    text.sections[0].value = contents;
+}
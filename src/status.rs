@@ -0,0 +1,173 @@
+use bevy::prelude::*;
+
+use crate::{combat::DamageEvent, Side, UnitType};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EffectKind {
+    /// Damage over time that also weakens the victim's attacks
+    Poison,
+    Slow,
+    /// Can neither move nor attack
+    Stun,
+    /// Heavy damage over time
+    Burn,
+}
+
+impl EffectKind {
+    /// How long the effect lasts once applied
+    pub fn duration(self) -> f32 {
+        match self {
+            EffectKind::Poison => 5.,
+            EffectKind::Slow => 3.,
+            EffectKind::Stun => 1.,
+            EffectKind::Burn => 3.,
+        }
+    }
+    fn damage_per_second(self) -> f32 {
+        match self {
+            EffectKind::Poison => 1.,
+            EffectKind::Burn => 2.,
+            EffectKind::Slow | EffectKind::Stun => 0.,
+        }
+    }
+    fn icon_color(self) -> Color {
+        match self {
+            EffectKind::Poison => Color::PURPLE,
+            EffectKind::Slow => Color::CYAN,
+            EffectKind::Stun => Color::YELLOW,
+            EffectKind::Burn => Color::ORANGE_RED,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Effect {
+    kind: EffectKind,
+    time_left: f32,
+    /// Who inflicted it, credited for the damage it deals
+    source: Entity,
+    /// Side of whoever inflicted it, which still gets the kill after they're gone
+    source_side: Side,
+    /// Damage accumulated but not yet dealt, as it is dealt in whole points
+    pending_damage: f32,
+}
+
+#[derive(Debug, Clone, Default, Component)]
+pub struct StatusEffects {
+    effects: Vec<Effect>,
+}
+
+impl StatusEffects {
+    /// Applies an effect, refreshing its duration if it's already active
+    pub fn apply(&mut self, kind: EffectKind, source: Entity, source_side: Side) {
+        let time_left = kind.duration();
+        if let Some(effect) = self.effects.iter_mut().find(|e| e.kind == kind) {
+            effect.time_left = effect.time_left.max(time_left);
+            effect.source = source;
+            effect.source_side = source_side;
+        } else {
+            self.effects.push(Effect { kind, time_left, source, source_side, pending_damage: 0. });
+        }
+    }
+    pub fn has(&self, kind: EffectKind) -> bool {
+        self.effects.iter().any(|e| e.kind == kind)
+    }
+    pub fn speed_factor(&self) -> f32 {
+        if self.has(EffectKind::Stun) {
+            0.
+        } else if self.has(EffectKind::Slow) {
+            0.5
+        } else {
+            1.
+        }
+    }
+    pub fn damage_factor(&self) -> f32 {
+        if self.has(EffectKind::Poison) { 0.75 } else { 1. }
+    }
+    pub fn can_act(&self) -> bool {
+        !self.has(EffectKind::Stun)
+    }
+}
+
+/// Shows an active effect of the fighter it is a child of
+#[derive(Debug, Clone, Copy, Component)]
+pub struct StatusIcon(EffectKind);
+
+/// Applies on-hit effects of attackers
+pub fn on_hit_effect_system(
+    mut damage_events: EventReader<DamageEvent>,
+    attacker_query: Query<&UnitType>,
+    mut query: Query<&mut StatusEffects>,
+) {
+    for event in damage_events.iter().filter(|e| e.effect.is_none()) {
+        let Some(kind) = attacker_query.get(event.attacker).ok().and_then(|unit| unit.skills.inflicts) else {
+            continue
+        };
+        if let Ok(mut effects) = query.get_mut(event.target) {
+            effects.apply(kind, event.attacker, event.attacker_side);
+        }
+    }
+}
+
+/// Counts down effects and deals their damage over time
+pub fn status_effect_system(
+    fixed_time: Res<FixedTime>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut query: Query<(Entity, &mut StatusEffects)>,
+) {
+    let delta = fixed_time.period.as_secs_f32();
+
+    for (ent, mut status) in query.iter_mut() {
+        if status.effects.is_empty() {
+            continue
+        }
+        for effect in &mut status.effects {
+            effect.time_left -= delta;
+            effect.pending_damage += effect.kind.damage_per_second() * delta;
+            if effect.pending_damage >= 1. {
+                let amount = effect.pending_damage as u8;
+                effect.pending_damage -= amount as f32;
+                damage_events.send(DamageEvent {
+                    attacker: effect.source,
+                    attacker_side: effect.source_side,
+                    target: ent,
+                    amount,
+                    effect: Some(effect.kind),
+                    critical: false,
+                });
+            }
+        }
+        status.effects.retain(|e| e.time_left > 0.);
+    }
+}
+
+pub fn status_icon_system(
+    mut commands: Commands,
+    query: Query<(Entity, &StatusEffects, &Children), Changed<StatusEffects>>,
+    icon_query: Query<&StatusIcon>,
+) {
+    for (ent, status, children) in query.iter() {
+        let kinds: Vec<_> = status.effects.iter().map(|e| e.kind).collect();
+        let icons: Vec<_> = children.iter().filter_map(|&c| icon_query.get(c).ok().map(|i| (c, i.0))).collect();
+        if icons.iter().map(|&(_, kind)| kind).eq(kinds.iter().copied()) {
+            continue
+        }
+
+        for &(icon, _) in &icons {
+            commands.entity(icon).despawn_recursive();
+        }
+        commands.entity(ent).with_children(|parent| {
+            for (i, kind) in kinds.into_iter().enumerate() {
+                parent.spawn(SpriteBundle {
+                    transform: Transform::from_translation(Vec3::new(-12. + 8. * i as f32, 52., 2.)),
+                    sprite: Sprite {
+                        color: kind.icon_color(),
+                        custom_size: Some(Vec2::splat(6.)),
+                        .. default()
+                    },
+                    .. default()
+                }).insert(StatusIcon(kind));
+            }
+        });
+    }
+}
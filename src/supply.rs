@@ -0,0 +1,38 @@
+use bevy::prelude::*;
+
+use crate::{MatchConfig, PerSide, Side, Team, UnitType};
+
+/// Supply caps to pick from in the menu
+pub const SUPPLY_CAPS: [Option<u32>; 4] = [None, Some(30), Some(60), Some(100)];
+
+/// How many units each side has on the field, out of how many it may have
+#[derive(Debug, Default, Clone, Copy, Resource)]
+pub struct Supply {
+    pub used: PerSide<u32>,
+    pub cap: Option<u32>,
+}
+
+impl Supply {
+    /// How many more units `side` may deploy
+    pub fn left(&self, side: Side) -> u32 {
+        self.cap.map_or(u32::MAX, |cap| cap.saturating_sub(self.used[side]))
+    }
+}
+
+/// Counts the units on each side, leaving out structures
+pub fn supply_system(
+    config: Res<MatchConfig>,
+    mut supply: ResMut<Supply>,
+    query: Query<(&Team, &UnitType)>,
+) {
+    let mut used = PerSide::splat(0);
+    for (&Team(side), unit) in query.iter() {
+        if !unit.skills.is_structure() {
+            used[side] += 1;
+        }
+    }
+    *supply = Supply {
+        used,
+        cap: SUPPLY_CAPS[config.supply_cap],
+    };
+}
@@ -0,0 +1,58 @@
+use bevy::prelude::*;
+
+use crate::{ai::spawn_banner, bindings::Bindings, economy::Economy, locale::Locale, mutators::Mutator, ControlOptions, MatchConfig, Materials, PerSide, Side, Skills};
+
+/// Price of researching each tier after the first
+const TIER_PRICES: [u32; 3] = [25, 50, 90];
+
+/// How far each side has researched, for matches played with the tech tree
+#[derive(Debug, Default, Clone, Copy, Resource)]
+pub struct Tech {
+    enabled: bool,
+    pub tiers: PerSide<u8>,
+}
+
+impl Tech {
+    /// Whether `side` may deploy units like these
+    pub fn unlocked(&self, side: Side, skills: &Skills) -> bool {
+        !self.enabled || skills.tier <= self.tiers[side]
+    }
+    /// Price of the next tier for `side`, if there's any left to research
+    pub fn next_price(&self, side: Side) -> Option<u32> {
+        if !self.enabled {
+            return None
+        }
+        TIER_PRICES.get(self.tiers[side] as usize).copied()
+    }
+}
+
+pub fn reset_tech(config: Res<MatchConfig>, mut tech: ResMut<Tech>) {
+    *tech = Tech {
+        enabled: config.mutators.contains(Mutator::TechTree),
+        tiers: PerSide::splat(0),
+    };
+}
+
+/// Researches the player's next tier
+pub fn research_system(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    options: Res<ControlOptions>,
+    materials: Res<Materials>,
+    locale: Res<Locale>,
+    mut economy: ResMut<Economy>,
+    mut tech: ResMut<Tech>,
+) {
+    if !keyboard_input.just_pressed(bindings.research) {
+        return
+    }
+    let side = options.player_side;
+    let Some(price) = tech.next_price(side) else {
+        return
+    };
+    if economy.spend(side, price) {
+        tech.tiers[side] += 1;
+        spawn_banner(&mut commands, &materials, &locale.format("banner.researched", &[&tech.tiers[side]]), materials.green, 2.);
+    }
+}
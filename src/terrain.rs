@@ -0,0 +1,220 @@
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+
+use crate::{maps::{MapDescriptor, Maps}, Battlefield, Level, MatchConfig};
+
+/// How far ahead units start steering around obstacles
+const AVOID_DISTANCE: f32 = 40.;
+/// Roughly half the size of a unit
+const UNIT_RADIUS: f32 = 16.;
+/// Attack bonus for fighting from a hill
+pub const HILL_ATTACK: Level = 8;
+/// Defence bonus for being attacked on a hill
+pub const HILL_DEFENCE: Level = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObstacleKind {
+    Rock,
+    Tree,
+}
+
+impl ObstacleKind {
+    fn color(self) -> Color {
+        match self {
+            ObstacleKind::Rock => Color::rgb(0.45, 0.45, 0.45),
+            ObstacleKind::Tree => Color::rgb(0.1, 0.45, 0.15),
+        }
+    }
+}
+
+/// Half the height of a bridge
+pub const BRIDGE_HALF_HEIGHT: f32 = 30.;
+
+/// Impassable terrain units have to walk around
+#[derive(Debug, Clone, Copy, Component)]
+pub struct Obstacle {
+    pub radius: f32,
+}
+
+/// Drawn high ground, see [`HighGround`]
+#[derive(Debug, Clone, Copy, Component)]
+pub struct Hill;
+
+/// The hills of the current map, for looking up who stands on high ground
+#[derive(Debug, Clone, Default, Resource)]
+pub struct HighGround {
+    hills: Vec<(Vec2, f32)>,
+}
+
+impl HighGround {
+    pub fn contains(&self, pos: Vec2) -> bool {
+        self.hills.iter().any(|&(centre, radius)| centre.distance_squared(pos) < radius * radius)
+    }
+}
+
+/// Drawn water and bridges, see [`River`]
+#[derive(Debug, Clone, Copy, Component)]
+pub struct RiverPart;
+
+/// The river of the current map, if it has one
+#[derive(Debug, Clone, Default, Resource)]
+pub struct River {
+    banks: Option<(f32, f32)>,
+    bridges: Vec<f32>,
+}
+
+impl River {
+    fn is_water(&self, pos: Vec2) -> bool {
+        self.banks.is_some_and(|(left, right)| pos.x > left && pos.x < right)
+            && !self.bridges.iter().any(|&y| (pos.y - y).abs() < BRIDGE_HALF_HEIGHT - UNIT_RADIUS / 2.)
+    }
+
+    /// The `y` of the bridge to head for instead, if stepping from `from` to `to` walks into the water
+    pub fn detour(&self, from: Vec2, to: Vec2) -> Option<f32> {
+        if !self.is_water(to) || self.is_water(from) {
+            return None
+        }
+        self.bridges.iter()
+            .copied()
+            .min_by(|a, b| (a - from.y).abs().total_cmp(&(b - from.y).abs()))
+    }
+}
+
+/// Anything drawn for the map's terrain
+pub type MapPart = Or<(With<Obstacle>, With<Hill>, With<RiverPart>)>;
+
+#[derive(Debug, Clone, Resource)]
+pub struct TerrainAssets {
+    circle: Mesh2dHandle,
+    rock: Handle<ColorMaterial>,
+    tree: Handle<ColorMaterial>,
+    hill: Handle<ColorMaterial>,
+}
+
+pub fn setup_terrain_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(TerrainAssets {
+        circle: meshes.add(shape::Circle::new(1.).into()).into(),
+        rock: color_materials.add(ColorMaterial::from(ObstacleKind::Rock.color())),
+        tree: color_materials.add(ColorMaterial::from(ObstacleKind::Tree.color())),
+        hill: color_materials.add(ColorMaterial::from(Color::rgba(0.6, 0.5, 0.3, 0.4))),
+    });
+}
+
+pub fn spawn_map(
+    mut commands: Commands,
+    battlefield: Res<Battlefield>,
+    config: Res<MatchConfig>,
+    maps: Res<Maps>,
+    assets: Res<TerrainAssets>,
+) {
+    lay_out_map(&mut commands, battlefield.half_size, &maps.0[config.map], &assets);
+}
+
+/// Lays the map out again when the battlefield changes size, as it's placed relative to it,
+/// or when its file has been changed
+pub fn refit_map_system(
+    mut commands: Commands,
+    battlefield: Res<Battlefield>,
+    config: Res<MatchConfig>,
+    maps: Res<Maps>,
+    assets: Res<TerrainAssets>,
+    query: Query<Entity, MapPart>,
+) {
+    if !battlefield.is_changed() && !maps.is_changed() {
+        return
+    }
+    for ent in query.iter() {
+        commands.entity(ent).despawn();
+    }
+    lay_out_map(&mut commands, battlefield.half_size, &maps.0[config.map], &assets);
+}
+
+pub fn lay_out_map(commands: &mut Commands, half_size: Vec2, map: &MapDescriptor, assets: &TerrainAssets) {
+    for obstacle in &map.obstacles {
+        let radius = obstacle.radius;
+        let material = match obstacle.kind {
+            ObstacleKind::Rock => assets.rock.clone(),
+            ObstacleKind::Tree => assets.tree.clone(),
+        };
+        commands.spawn(MaterialMesh2dBundle {
+            mesh: assets.circle.clone(),
+            material,
+            transform: Transform::from_translation((obstacle.position * half_size).extend(-0.8))
+                .with_scale(Vec3::splat(radius)),
+            ..default()
+        }).insert(Obstacle { radius });
+    }
+
+    let mut hills = Vec::new();
+    for hill in &map.hills {
+        let (centre, radius) = (hill.position * half_size, hill.radius);
+        commands.spawn(MaterialMesh2dBundle {
+            mesh: assets.circle.clone(),
+            material: assets.hill.clone(),
+            transform: Transform::from_translation(centre.extend(-0.95))
+                .with_scale(Vec3::splat(radius)),
+            ..default()
+        }).insert(Hill);
+        hills.push((centre, radius));
+    }
+    commands.insert_resource(HighGround { hills });
+
+    let mut river = River::default();
+    if let Some(descriptor) = &map.river {
+        let (x, width) = (descriptor.x * half_size.x, descriptor.width);
+        commands.spawn(SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgb(0.15, 0.35, 0.7),
+                custom_size: Some(Vec2::new(width, 2. * half_size.y)),
+                ..default()
+            },
+            transform: Transform::from_xyz(x, 0., -0.9),
+            ..default()
+        }).insert(RiverPart);
+        for &y in &descriptor.bridges {
+            let y = y * half_size.y;
+            commands.spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgb(0.5, 0.35, 0.2),
+                    custom_size: Some(Vec2::new(width + 20., 2. * BRIDGE_HALF_HEIGHT)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(x, y, -0.85),
+                ..default()
+            }).insert(RiverPart);
+            river.bridges.push(y);
+        }
+        river.banks = Some((x - width / 2. - UNIT_RADIUS, x + width / 2. + UNIT_RADIUS));
+    }
+    commands.insert_resource(river);
+}
+
+pub fn cleanup_map(mut commands: Commands, query: Query<Entity, MapPart>) {
+    for ent in query.iter() {
+        commands.entity(ent).despawn();
+    }
+    commands.insert_resource(HighGround::default());
+    commands.insert_resource(River::default());
+}
+
+/// Which way to sidestep the first obstacle in the way of a unit at `pos` heading in `direction`,
+/// and whether it's too close to keep walking forward
+pub fn avoidance(obstacles: &[(Vec2, f32)], pos: Vec2, direction: f32) -> Option<(f32, bool)> {
+    obstacles.iter()
+        .filter_map(|&(centre, radius)| {
+            let ahead = (centre.x - pos.x) * direction;
+            let in_path = (centre.y - pos.y).abs() < radius + UNIT_RADIUS;
+            (in_path && ahead > -radius && ahead < radius + AVOID_DISTANCE).then_some((centre, radius, ahead))
+        })
+        .min_by(|(_, _, a), (_, _, b)| a.total_cmp(b))
+        .map(|(centre, radius, ahead)| {
+            let away = if pos.y >= centre.y { 1. } else { -1. };
+            (away, ahead < radius + UNIT_RADIUS)
+        })
+}
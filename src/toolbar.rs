@@ -0,0 +1,138 @@
+use bevy::prelude::*;
+
+use crate::{economy::Prices, locale::Locale, tech::Tech, ControlOptions, Materials, SelectedUnit, Skills, UNITS};
+
+const SELECTED_COLOR: Color = Color::rgb(1.0, 0.84, 0.0);
+const UNSELECTED_COLOR: Color = Color::rgb(0.7, 0.7, 0.7);
+const LOCKED_COLOR: Color = Color::rgb(0.35, 0.35, 0.35);
+const BUTTON_COLOR: Color = Color::rgba(0., 0., 0., 0.5);
+const HOVERED_COLOR: Color = Color::rgba(0.2, 0.2, 0.2, 0.7);
+
+/// Row of deployable units along the bottom of the screen, with their current prices,
+/// followed by the next tech tier to research
+#[derive(Debug, Clone, Copy, Component)]
+pub struct Toolbar;
+
+/// Selects the unit in [`UNITS`] at this index when clicked
+#[derive(Debug, Clone, Copy, Component)]
+pub struct UnitButton(usize);
+
+/// Label of a [`UnitButton`]
+#[derive(Debug, Clone, Copy, Component)]
+pub struct UnitLabel(usize);
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct ResearchText;
+
+fn stats_line(skills: &Skills, locale: &Locale) -> String {
+    locale.format("toolbar.stats", &[&skills.attack, &skills.defence, &skills.strength, &skills.hp])
+}
+
+pub fn setup_toolbar(mut commands: Commands, materials: Res<Materials>) {
+    let style = |font_size| TextStyle {
+        font: materials.font.clone(),
+        color: UNSELECTED_COLOR,
+        font_size,
+    };
+    commands.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                bottom: Val::Px(5.0),
+                left: Val::Px(5.0),
+                ..default()
+            },
+            align_items: AlignItems::Center,
+            gap: Size::all(Val::Px(4.)),
+            ..default()
+        },
+        ..default()
+    }).insert(Toolbar).with_children(|toolbar| {
+        for (i, skills) in UNITS.iter().enumerate() {
+            toolbar.spawn(ButtonBundle {
+                style: Style {
+                    align_items: AlignItems::Center,
+                    padding: UiRect::all(Val::Px(3.)),
+                    gap: Size::all(Val::Px(3.)),
+                    ..default()
+                },
+                background_color: BUTTON_COLOR.into(),
+                ..default()
+            }).insert(UnitButton(i)).with_children(|button| {
+                button.spawn(ImageBundle {
+                    style: Style {
+                        size: Size::all(Val::Px(24.)),
+                        ..default()
+                    },
+                    image: UiImage::new(materials.image(skills.look)),
+                    ..default()
+                });
+                button.spawn(TextBundle::from_sections([
+                    TextSection::new("", style(14.)),
+                    TextSection::new("", style(11.)),
+                ])).insert(UnitLabel(i));
+            });
+        }
+        toolbar.spawn(TextBundle::from_section("", style(18.))).insert(ResearchText);
+    });
+}
+
+/// Clicking a unit's button selects it, just like its number key
+pub fn toolbar_click_system(
+    mut selected: ResMut<SelectedUnit>,
+    query: Query<(&Interaction, &UnitButton), Changed<Interaction>>,
+) {
+    for (interaction, &UnitButton(i)) in query.iter() {
+        if *interaction == Interaction::Clicked {
+            selected.0 = i;
+        }
+    }
+}
+
+pub fn toolbar_system(
+    options: Res<ControlOptions>,
+    selected: Res<SelectedUnit>,
+    prices: Res<Prices>,
+    tech: Res<Tech>,
+    locale: Res<Locale>,
+    mut button_query: Query<(&UnitButton, &Interaction, &mut BackgroundColor)>,
+    mut label_query: Query<(&UnitLabel, &mut Text), Without<ResearchText>>,
+    mut research_query: Query<&mut Text, With<ResearchText>>,
+) {
+    let side = options.player_side;
+    for (&UnitButton(i), interaction, mut background) in button_query.iter_mut() {
+        *background = if i == selected.0 || *interaction != Interaction::None {
+            HOVERED_COLOR
+        } else {
+            BUTTON_COLOR
+        }.into();
+    }
+    for (&UnitLabel(i), mut text) in label_query.iter_mut() {
+        let skills = &UNITS[i];
+        text.sections[0].value = format!("[{}] {} {}¤", (i + 1) % 10, locale.name(skills.name), prices.price(side, skills));
+        if locale.is_changed() {
+            text.sections[1].value = format!("\n{}", stats_line(skills, &locale));
+        }
+        let color = if !tech.unlocked(side, skills) {
+            LOCKED_COLOR
+        } else if i == selected.0 {
+            SELECTED_COLOR
+        } else {
+            UNSELECTED_COLOR
+        };
+        for section in &mut text.sections {
+            section.style.color = color;
+        }
+    }
+    for mut text in research_query.iter_mut() {
+        text.sections[0].value = match tech.next_price(side) {
+            Some(price) => locale.format("toolbar.research", &[&price]),
+            None => String::new(),
+        };
+    }
+}
+
+/// Whether the cursor is over the toolbar or another button, so clicks there don't also reach the battlefield
+pub fn hovered(query: &Query<&Interaction, With<Button>>) -> bool {
+    query.iter().any(|&interaction| interaction != Interaction::None)
+}
@@ -0,0 +1,88 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    locale::Locale, orders::FallingBack, recall::Recalling, stance::Stance, hit_test, CombatStats, CombatTarget, Health,
+    Materials, MouseLoc, MovementState, UnitName, UnitType,
+};
+
+/// Stats of the fighter under the cursor
+#[derive(Debug, Clone, Copy, Component)]
+pub struct Tooltip;
+
+pub fn setup_tooltip(mut commands: Commands, materials: Res<Materials>) {
+    commands.spawn(TextBundle {
+        text: Text::from_section("", TextStyle {
+            font: materials.font.clone(),
+            font_size: 16.,
+            color: Color::WHITE,
+        }),
+        style: Style {
+            position_type: PositionType::Absolute,
+            padding: UiRect::all(Val::Px(4.)),
+            ..default()
+        },
+        background_color: Color::rgba(0., 0., 0., 0.7).into(),
+        visibility: Visibility::Hidden,
+        z_index: ZIndex::Global(4),
+        ..default()
+    }).insert(Tooltip);
+}
+
+pub fn tooltip_system(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mouse_loc: Res<MouseLoc>,
+    locale: Res<Locale>,
+    fighter_query: Query<(
+        &Transform,
+        &TextureAtlasSprite,
+        &Visibility,
+        (&UnitType, &CombatStats, &Health, &CombatTarget, &MovementState, &Stance),
+        Option<&UnitName>,
+        Option<&Recalling>,
+        Option<&FallingBack>,
+    )>,
+    mut query: Query<(&mut Text, &mut Style, &mut Visibility), (With<Tooltip>, Without<UnitType>)>,
+) {
+    let cursor = window_query.get_single().ok().and_then(|window| window.cursor_position());
+    let hovered = fighter_query.iter()
+        .filter(|(_, _, &visibility, ..)| visibility != Visibility::Hidden)
+        .find(|(transform, sprite, ..)| hit_test(transform, sprite, mouse_loc.0));
+
+    for (mut text, mut style, mut visibility) in query.iter_mut() {
+        let Some((.., (unit, stats, health, target, movement, stance), name, recalling, falling_back)) = hovered else {
+            *visibility = Visibility::Hidden;
+            continue
+        };
+        let Some(cursor) = cursor else {
+            *visibility = Visibility::Hidden;
+            continue
+        };
+        *visibility = Visibility::Inherited;
+
+        let skills = &unit.skills;
+        let state = if recalling.is_some() {
+            "tooltip.recalling"
+        } else if falling_back.is_some() {
+            "tooltip.falling_back"
+        } else if target.0.is_some() {
+            "tooltip.fighting"
+        } else if movement.held_up() {
+            "tooltip.waiting"
+        } else if skills.is_structure() {
+            "tooltip.standing"
+        } else {
+            "tooltip.moving"
+        };
+        let name = name.map_or_else(|| locale.name(skills.name).to_owned(), |n| n.localized(&locale));
+        text.sections[0].value = locale.format("tooltip.stats", &[
+            &name, &health.hp, &health.max, &stats.attack, &stats.defence, &stats.strength, &skills.speed,
+            &format!("{}, {}", locale.text(state), locale.name(stance.name())),
+        ]);
+        // The cursor's position counts from the bottom left, the same as the style's
+        style.position = UiRect {
+            left: Val::Px(cursor.x + 16.),
+            bottom: Val::Px(cursor.y + 16.),
+            ..default()
+        };
+    }
+}
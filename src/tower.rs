@@ -0,0 +1,122 @@
+use bevy::prelude::*;
+
+use crate::{
+    bindings::Bindings,
+    combat::{ArmorClass, DamageType},
+    economy::Economy,
+    spawn_fighter, stance::DefaultStance,
+    ClickAction, CombatTarget, ControlOptions, MatchConfig, Materials, MouseLoc, Skills, Team, UnitCounter,
+    UnitName, UnitType, FIGHTER,
+};
+
+/// How far a tower can shoot
+const TOWER_RANGE: f32 = 120.;
+const TOWER_SIZE: f32 = 40.;
+
+pub const ARROW_TOWER: Skills = Skills {
+    name: "Arrow tower",
+    price: 25,
+    attack: 35,
+    defence: 5,
+    strength: 4,
+    hp: 40,
+    speed: 0,
+    siege: 0,
+    crit: 5,
+    dodge: 0,
+    cooldown: 1.5,
+    damage_type: DamageType::Piercing,
+    armor: ArmorClass::Mail,
+    ..FIGHTER
+};
+
+/// Blocks enemies until they break it down, while letting its own side through like a gate
+pub const WALL: Skills = Skills {
+    name: "Wall",
+    price: 10,
+    attack: 0,
+    defence: 0,
+    strength: 1,
+    hp: 80,
+    speed: 0,
+    siege: 0,
+    crit: 0,
+    dodge: 0,
+    armor: ArmorClass::Shield,
+    ..FIGHTER
+};
+const WALL_SIZE: Vec2 = Vec2::new(16., 48.);
+
+/// A stationary unit shooting whatever enemy comes in range
+#[derive(Debug, Clone, Copy, Component)]
+pub struct Tower;
+
+/// Clicking on your half of the battlefield with a build key held builds a tower or wall there
+pub fn build_system(
+    mut commands: Commands,
+    mouse_loc: Res<MouseLoc>,
+    mouse_button: Res<Input<MouseButton>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    options: Res<ControlOptions>,
+    bindings: Res<Bindings>,
+    config: Res<MatchConfig>,
+    materials: Res<Materials>,
+    stances: Res<DefaultStance>,
+    mut counter: ResMut<UnitCounter>,
+    mut economy: ResMut<Economy>,
+) {
+    let ClickAction::Build(skills) = ClickAction::held(&keyboard_input, &bindings) else {
+        return
+    };
+    for &button in mouse_button.get_just_pressed() {
+        let Some(side) = options.button_side(&bindings, button) else {
+            continue
+        };
+        if config.versus_ai && side != options.player_side {
+            continue
+        }
+        let position = mouse_loc.0;
+        if side.advance(position) > 0. || !economy.spend(side, skills.price) {
+            continue
+        }
+
+        let (size, color) = if skills.name == WALL.name {
+            (WALL_SIZE, Color::rgb(0.55, 0.4, 0.25))
+        } else {
+            (Vec2::splat(TOWER_SIZE), Color::rgb(0.6, 0.6, 0.6))
+        };
+        let ent = spawn_fighter(&mut commands, position, size.y, side, &materials, skills);
+        commands.entity(ent).insert((
+            TextureAtlasSprite {
+                color,
+                custom_size: Some(size),
+                ..default()
+            },
+            UnitName {
+                side,
+                kind: skills.name,
+                number: counter.next(side),
+            },
+            stances.0[side],
+        ));
+        if skills.name == ARROW_TOWER.name {
+            commands.entity(ent).insert(Tower);
+        }
+    }
+}
+
+/// Points every tower at the closest enemy in range
+pub fn tower_targeting_system(
+    mut towers: Query<(&mut CombatTarget, &Team, &Transform), With<Tower>>,
+    query: Query<(Entity, &Team, &Transform), With<UnitType>>,
+) {
+    for (mut target, &tower_team, transform) in towers.iter_mut() {
+        let pos = transform.translation.truncate();
+        target.0 = query.iter()
+            .filter(|&(_, &team, _)| team != tower_team)
+            .map(|(e, _, t)| (e, t.translation.truncate().distance(pos)))
+            .filter(|&(_, distance)| distance <= TOWER_RANGE)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(e, _)| e);
+    }
+}
@@ -0,0 +1,129 @@
+use bevy::prelude::*;
+
+use crate::{
+    bindings::Bindings,
+    combat::{spawn_blast, DamageEvent},
+    economy::Economy,
+    status::{EffectKind, StatusEffects},
+    ClickAction, ControlOptions, MatchConfig, MouseLoc, Team, UnitType,
+};
+
+/// How close an enemy must step to set off a trap
+const TRIGGER_RADIUS: f32 = 16.;
+const MINE_BLAST_RADIUS: f32 = 40.;
+const MINE_DAMAGE: u8 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrapKind {
+    /// Blows up, damaging every enemy close by
+    Mine,
+    /// Slows down whoever steps in it
+    Snare,
+}
+
+impl TrapKind {
+    fn price(self) -> u32 {
+        match self {
+            TrapKind::Mine => 6,
+            TrapKind::Snare => 4,
+        }
+    }
+    fn color(self) -> Color {
+        match self {
+            TrapKind::Mine => Color::rgba(0.8, 0.2, 0.1, 0.25),
+            TrapKind::Snare => Color::rgba(0.2, 0.6, 0.8, 0.25),
+        }
+    }
+}
+
+/// Hidden in the grass until an enemy steps on it, then used up
+#[derive(Debug, Clone, Copy, Component)]
+pub struct Trap {
+    kind: TrapKind,
+}
+
+/// Clicking on your half with the mine key held lays a mine, and with the snare key held a snare
+pub fn trap_placement_system(
+    mut commands: Commands,
+    mouse_loc: Res<MouseLoc>,
+    mouse_button: Res<Input<MouseButton>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    options: Res<ControlOptions>,
+    bindings: Res<Bindings>,
+    config: Res<MatchConfig>,
+    mut economy: ResMut<Economy>,
+) {
+    let ClickAction::Trap(kind) = ClickAction::held(&keyboard_input, &bindings) else {
+        return
+    };
+    for &button in mouse_button.get_just_pressed() {
+        let Some(side) = options.button_side(&bindings, button) else {
+            continue
+        };
+        if config.versus_ai && side != options.player_side {
+            continue
+        }
+        let position = mouse_loc.0;
+        if side.advance(position) > 0. || !economy.spend(side, kind.price()) {
+            continue
+        }
+        // Faint, so they're easily missed
+        commands.spawn(SpriteBundle {
+            sprite: Sprite {
+                color: kind.color(),
+                custom_size: Some(Vec2::splat(10.)),
+                ..default()
+            },
+            transform: Transform::from_translation(position.extend(-0.5)),
+            ..default()
+        }).insert((Trap { kind }, Team(side)));
+    }
+}
+
+pub fn trap_trigger_system(
+    mut commands: Commands,
+    mut damage_events: EventWriter<DamageEvent>,
+    traps: Query<(Entity, &Trap, &Team, &Transform)>,
+    mut query: Query<(Entity, &Team, &Transform, &mut StatusEffects), With<UnitType>>,
+) {
+    for (trap_ent, trap, &trap_team, trap_trans) in traps.iter() {
+        let pos = trap_trans.translation.truncate();
+        let enemy_within = |team: &Team, t: &Transform, radius: f32| {
+            *team != trap_team && t.translation.truncate().distance(pos) <= radius
+        };
+        if !query.iter().any(|(_, team, t, _)| enemy_within(team, t, TRIGGER_RADIUS)) {
+            continue
+        }
+
+        match trap.kind {
+            TrapKind::Mine => {
+                let targets = query.iter().filter(|(_, team, t, _)| enemy_within(team, t, MINE_BLAST_RADIUS));
+                for (target, ..) in targets {
+                    damage_events.send(DamageEvent {
+                        attacker: trap_ent,
+                        attacker_side: trap_team.0,
+                        target,
+                        amount: MINE_DAMAGE,
+                        effect: None,
+                        critical: false,
+                    });
+                }
+                spawn_blast(&mut commands, trap_trans.translation, MINE_BLAST_RADIUS);
+            }
+            TrapKind::Snare => {
+                for (_, team, t, mut status) in query.iter_mut() {
+                    if enemy_within(team, t, TRIGGER_RADIUS) {
+                        status.apply(EffectKind::Slow, trap_ent, trap_team.0);
+                    }
+                }
+            }
+        }
+        commands.entity(trap_ent).despawn();
+    }
+}
+
+pub fn cleanup_traps(mut commands: Commands, query: Query<Entity, With<Trap>>) {
+    for ent in query.iter() {
+        commands.entity(ent).despawn();
+    }
+}
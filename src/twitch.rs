@@ -0,0 +1,176 @@
+//! Lets viewers of a Twitch stream deploy units for the opponent by typing
+//! `!spawn <unit> [top|middle|bottom]` in chat, paid for out of a shared pool.
+//!
+//! Set `SIDEWARS_TWITCH_CHANNEL` to the channel to listen to.
+
+use std::{
+    env,
+    io::{BufRead, BufReader, Write},
+    net::TcpStream,
+    sync::{mpsc::{channel, Receiver, Sender}, Mutex},
+    thread,
+};
+
+use rand::Rng;
+
+use bevy::prelude::*;
+
+use crate::{locale::Locale, zone::SpawnZone, ControlOptions, GameState, Materials, Skills, SpawnOrder, UNITS};
+
+const TWITCH_IRC: &str = "irc.chat.twitch.tv:6667";
+/// Money flowing into the viewer pool each second
+const POOL_INCOME: f32 = 1.;
+/// What a chat-deployed unit costs the pool
+const SPAWN_COST: f32 = 5.;
+
+pub struct TwitchPlugin;
+
+impl Plugin for TwitchPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<ViewerPool>()
+            .add_startup_system(connect_system)
+            .add_system(reset_pool_system.in_schedule(OnEnter(GameState::Playing)))
+            .add_systems((
+                twitch_spawn_system,
+                viewer_pool_text_system,
+            ).in_set(OnUpdate(GameState::Playing)));
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ChatSpawn {
+    skills: Skills,
+    /// Fraction of the way from the bottom to the top of the field
+    height: Option<f32>,
+}
+
+#[derive(Resource)]
+struct TwitchChat {
+    commands: Mutex<Receiver<ChatSpawn>>,
+}
+
+#[derive(Debug, Default, Resource)]
+struct ViewerPool {
+    money: f32,
+}
+
+#[derive(Debug, Clone, Copy, Component)]
+struct ViewerPoolText;
+
+fn connect_system(mut commands: Commands, materials: Res<Materials>) {
+    let Ok(channel_name) = env::var("SIDEWARS_TWITCH_CHANNEL") else {
+        warn!("SIDEWARS_TWITCH_CHANNEL not set, Twitch integration disabled");
+        return
+    };
+    let (tx, rx) = channel();
+    thread::spawn(move || {
+        if let Err(e) = read_chat(&channel_name.to_lowercase(), tx) {
+            warn!("Twitch chat disconnected: {e}");
+        }
+    });
+    commands.insert_resource(TwitchChat { commands: Mutex::new(rx) });
+
+    commands.spawn(TextBundle {
+        text: Text::from_section("", TextStyle {
+            font: materials.font.clone(),
+            color: Color::rgb(0.57, 0.27, 1.0),
+            font_size: 20.0,
+        }),
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                bottom: Val::Px(5.0),
+                left: Val::Px(5.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        ..Default::default()
+    }).insert(ViewerPoolText);
+}
+
+/// Reads chat anonymously until the connection drops
+fn read_chat(channel_name: &str, tx: Sender<ChatSpawn>) -> std::io::Result<()> {
+    let mut stream = TcpStream::connect(TWITCH_IRC)?;
+    // Anonymous read-only login
+    write!(stream, "NICK justinfan{}\r\n", rand::thread_rng().gen_range(10000..99999))?;
+    write!(stream, "JOIN #{channel_name}\r\n")?;
+
+    let reader = BufReader::new(stream.try_clone()?);
+    for line in reader.lines() {
+        let line = line?;
+        if let Some(server) = line.strip_prefix("PING ") {
+            write!(stream, "PONG {server}\r\n")?;
+        } else if let Some(spawn) = parse_privmsg(&line) {
+            if tx.send(spawn).is_err() {
+                break
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_privmsg(line: &str) -> Option<ChatSpawn> {
+    let (_, rest) = line.split_once(" PRIVMSG ")?;
+    let (_, message) = rest.split_once(" :")?;
+    let mut words = message.split_whitespace();
+    if words.next()? != "!spawn" {
+        return None
+    }
+    let name = words.next()?;
+    let skills = *UNITS.iter().find(|u| u.name.eq_ignore_ascii_case(name))?;
+    let height = match words.next().map(str::to_lowercase).as_deref() {
+        Some("top") => Some(5. / 6.),
+        Some("middle" | "mid") => Some(0.5),
+        Some("bottom") => Some(1. / 6.),
+        _ => None,
+    };
+    Some(ChatSpawn { skills, height })
+}
+
+fn reset_pool_system(mut pool: ResMut<ViewerPool>) {
+    pool.money = 0.;
+}
+
+fn twitch_spawn_system(
+    time: Res<Time>,
+    chat: Option<Res<TwitchChat>>,
+    options: Res<ControlOptions>,
+    zone: Res<SpawnZone>,
+    mut pool: ResMut<ViewerPool>,
+    mut orders: EventWriter<SpawnOrder>,
+) {
+    let Some(chat) = chat else {
+        return
+    };
+    pool.money += POOL_INCOME * time.delta_seconds();
+
+    let side = options.player_side.opponent();
+    let commands = chat.commands.lock().unwrap();
+    for spawn in commands.try_iter() {
+        if pool.money < SPAWN_COST {
+            continue
+        }
+        pool.money -= SPAWN_COST;
+
+        let height = spawn.height.unwrap_or_else(|| rand::thread_rng().gen_range(0.1..0.9));
+        let rect = zone.0[side];
+        orders.send(SpawnOrder {
+            side,
+            position: Vec2::new(rect.center().x, rect.min.y + height * rect.height()),
+            skills: spawn.skills,
+            prepaid: true,
+        });
+    }
+}
+
+fn viewer_pool_text_system(
+    locale: Res<Locale>,
+    pool: Res<ViewerPool>,
+    mut query: Query<&mut Text, With<ViewerPoolText>>,
+) {
+    for mut text in query.iter_mut() {
+        text.sections[0].value = locale.format("twitch.pool", &[&pool.money.round(), &SPAWN_COST]);
+    }
+}
@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{settings::{read_config, write_config}, MatchOverEvent, PerSide, Side, UnitName};
+
+const STATS_FILE: &str = "unit_stats.txt";
+/// Deployments needed before a unit's price starts adjusting
+const MIN_SAMPLES: u32 = 20;
+/// Largest change in price, either way
+const MAX_ADJUSTMENT: f32 = 0.25;
+
+/// How often each kind of unit was deployed by the winning and the losing side, across sessions
+#[derive(Debug, Clone, Resource)]
+pub struct UnitStats {
+    records: HashMap<String, (u32, u32)>,
+}
+
+impl Default for UnitStats {
+    fn default() -> Self {
+        let records = read_config(STATS_FILE)
+            .map(|s| s.lines().filter_map(parse_line).collect())
+            .unwrap_or_default();
+        UnitStats { records }
+    }
+}
+
+fn parse_line(line: &str) -> Option<(String, (u32, u32))> {
+    let mut parts = line.split('\t');
+    let name = parts.next()?.to_owned();
+    let wins = parts.next()?.trim().parse().ok()?;
+    let losses = parts.next()?.trim().parse().ok()?;
+    Some((name, (wins, losses)))
+}
+
+impl UnitStats {
+    /// What a unit's price is multiplied by under the meta prices rule,
+    /// pricier the more often it ended up on the winning side
+    pub fn price_factor(&self, name: &str) -> f32 {
+        let Some(&(wins, losses)) = self.records.get(name) else {
+            return 1.
+        };
+        let total = wins + losses;
+        if total < MIN_SAMPLES {
+            return 1.
+        }
+        let win_share = wins as f32 / total as f32;
+        1. + MAX_ADJUSTMENT * 2. * (win_share - 0.5)
+    }
+    fn save(&self) {
+        let contents: String = self.records.iter()
+            .map(|(name, (wins, losses))| format!("{name}\t{wins}\t{losses}\n"))
+            .collect();
+        if let Err(e) = write_config(STATS_FILE, contents) {
+            error!("Could not save unit statistics: {e}");
+        }
+    }
+}
+
+/// Units each side has deployed in the current match
+#[derive(Debug, Default, Clone, Resource)]
+pub struct Deployments(PerSide<Vec<&'static str>>);
+
+pub fn reset_deployments(mut deployments: ResMut<Deployments>) {
+    *deployments = Deployments::default();
+}
+
+pub fn deployment_tracking_system(
+    mut deployments: ResMut<Deployments>,
+    query: Query<&UnitName, Added<UnitName>>,
+) {
+    for name in query.iter() {
+        deployments.0[name.side].push(name.kind);
+    }
+}
+
+pub fn unit_stats_system(
+    mut events: EventReader<MatchOverEvent>,
+    deployments: Res<Deployments>,
+    mut stats: ResMut<UnitStats>,
+) {
+    for event in events.iter() {
+        // Every other side lost, which is more than one in free-for-all
+        for (side, won) in Side::ALL.into_iter().map(|side| (side, side == event.winner)) {
+            for &kind in &deployments.0[side] {
+                let record = stats.records.entry(kind.to_owned()).or_default();
+                if won {
+                    record.0 += 1;
+                } else {
+                    record.1 += 1;
+                }
+            }
+        }
+        stats.save();
+    }
+}
@@ -0,0 +1,32 @@
+//! A detailed log of the battle, every deployment, hit and AI decision, for tracking down odd
+//! combat behaviour. It's switched on and off with F4 while playing, and is on from the start with `--log debug`.
+//! It's written at the info level, so `--log warn` or `--log error` keep it quiet either way.
+//!
+//! With `--log debug`, movement, combat and the AI also run in spans of their own, which show up in
+//! profilers and as context on whatever they log.
+
+use bevy::{log::Level, prelude::*};
+
+use crate::{bindings::Bindings, cli::Args};
+
+/// Whether the detailed log is being written
+#[derive(Debug, Clone, Copy, Resource)]
+pub struct VerboseLog(pub bool);
+
+impl FromWorld for VerboseLog {
+    fn from_world(world: &mut World) -> Self {
+        let level = world.resource::<Args>().log_level;
+        VerboseLog(level.is_some_and(|level| level >= Level::DEBUG))
+    }
+}
+
+pub fn verbose_log_toggle_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    mut verbose: ResMut<VerboseLog>,
+) {
+    if keyboard_input.just_pressed(bindings.verbose_log) {
+        verbose.0 = !verbose.0;
+        info!(enabled = verbose.0, "verbose log");
+    }
+}
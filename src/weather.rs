@@ -0,0 +1,156 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{ai::spawn_banner, locale::Locale, match_rng::MatchRng, Battlefield, Materials, Side, Timeout};
+
+/// Shortest and longest time a kind of weather lasts
+const WEATHER_TIME: (f32, f32) = (25., 50.);
+/// How much rain slows units down
+const RAIN_SPEED_FACTOR: f32 = 0.7;
+/// How much further or shorter ranged units reach with or against the wind
+const WIND_REACH: f32 = 0.3;
+/// Particles spawned per second
+const PARTICLE_RATE: f32 = 150.;
+const RAIN_VELOCITY: Vec2 = Vec2::new(-40., -500.);
+const WIND_VELOCITY: f32 = 600.;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum WeatherKind {
+    #[default]
+    Clear,
+    Rain,
+    /// Blowing towards the given side, which is always left or right
+    Wind(Side),
+}
+
+impl WeatherKind {
+    /// Key of the banner announcing the weather in the [`Locale`]
+    fn announcement(self) -> &'static str {
+        match self {
+            WeatherKind::Clear => "weather.clear",
+            WeatherKind::Rain => "weather.rain",
+            WeatherKind::Wind(Side::Left) => "weather.wind_left",
+            WeatherKind::Wind(_) => "weather.wind_right",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, Resource)]
+pub struct Weather {
+    pub kind: WeatherKind,
+    time_left: f32,
+}
+
+impl Weather {
+    pub fn speed_factor(&self) -> f32 {
+        match self.kind {
+            WeatherKind::Rain => RAIN_SPEED_FACTOR,
+            _ => 1.,
+        }
+    }
+    /// How far a ranged unit on `side` reaches given the wind
+    pub fn reach(&self, side: Side, reach: f32) -> f32 {
+        match self.kind {
+            // Units shoot towards the side they aren't on
+            WeatherKind::Wind(towards) if towards == side => reach * (1. - WIND_REACH),
+            // Top and bottom shoot across it
+            WeatherKind::Wind(towards) if towards.facing().dot(side.facing()) == 0. => reach,
+            WeatherKind::Wind(_) => reach * (1. + WIND_REACH),
+            _ => reach,
+        }
+    }
+}
+
+/// A raindrop or gust of wind, purely for show
+#[derive(Debug, Clone, Copy, Component)]
+pub struct WeatherParticle {
+    velocity: Vec2,
+}
+
+pub fn reset_weather(mut rng: ResMut<MatchRng>, mut weather: ResMut<Weather>) {
+    *weather = Weather {
+        kind: WeatherKind::Clear,
+        time_left: rng.gen_range(WEATHER_TIME.0..WEATHER_TIME.1),
+    };
+}
+
+pub fn weather_system(
+    mut commands: Commands,
+    fixed_time: Res<FixedTime>,
+    materials: Res<Materials>,
+    locale: Res<Locale>,
+    mut rng: ResMut<MatchRng>,
+    mut weather: ResMut<Weather>,
+) {
+    weather.time_left -= fixed_time.period.as_secs_f32();
+    if weather.time_left > 0. {
+        return
+    }
+    let kind = match rng.gen_range(0..4) {
+        0 => WeatherKind::Clear,
+        1 => WeatherKind::Rain,
+        2 => WeatherKind::Wind(Side::Left),
+        _ => WeatherKind::Wind(Side::Right),
+    };
+    if kind != weather.kind {
+        spawn_banner(&mut commands, &materials, locale.text(kind.announcement()), Color::WHITE, 3.);
+    }
+    weather.kind = kind;
+    weather.time_left = rng.gen_range(WEATHER_TIME.0..WEATHER_TIME.1);
+}
+
+pub fn weather_particle_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    battlefield: Res<Battlefield>,
+    weather: Res<Weather>,
+    mut query: Query<(&mut Transform, &WeatherParticle)>,
+) {
+    let delta = time.delta_seconds();
+    for (mut transform, particle) in query.iter_mut() {
+        transform.translation += (particle.velocity * delta).extend(0.);
+    }
+
+    let half_size = battlefield.half_size;
+    let mut rng = rand::thread_rng();
+
+    // Round the expected number of particles this frame up or down at random
+    let expected = PARTICLE_RATE * delta;
+    let count = expected as usize + (rng.gen::<f32>() < expected.fract()) as usize;
+    for _ in 0..count {
+        let (velocity, start, size, color) = match weather.kind {
+            WeatherKind::Clear => return,
+            WeatherKind::Rain => (
+                RAIN_VELOCITY,
+                Vec2::new(rng.gen_range(-half_size.x..half_size.x * 1.2), half_size.y),
+                Vec2::new(2., 14.),
+                Color::rgba(0.6, 0.7, 1., 0.6),
+            ),
+            WeatherKind::Wind(side) => {
+                let velocity = Vec2::new(side.direction() * -WIND_VELOCITY, 0.);
+                (
+                    velocity,
+                    Vec2::new(-velocity.x.signum() * half_size.x, rng.gen_range(-half_size.y..half_size.y)),
+                    Vec2::new(30., 1.),
+                    Color::rgba(1., 1., 1., 0.4),
+                )
+            }
+        };
+        let crossing_time = 2. * half_size.max_element() / velocity.length();
+        commands.spawn(SpriteBundle {
+            sprite: Sprite {
+                color,
+                custom_size: Some(size),
+                ..default()
+            },
+            transform: Transform::from_translation(start.extend(5.)),
+            ..default()
+        }).insert((WeatherParticle { velocity }, Timeout::new(crossing_time)));
+    }
+}
+
+pub fn cleanup_weather(mut commands: Commands, query: Query<Entity, With<WeatherParticle>>) {
+    for ent in query.iter() {
+        commands.entity(ent).despawn();
+    }
+}
@@ -0,0 +1,89 @@
+use bevy::{
+    prelude::*,
+    math::Rect,
+};
+
+use crate::{maps::Maps, Battlefield, ControlOptions, MatchConfig, Materials, PerSide, Side};
+
+/// The strips along the edges of the battlefield where each side deploys, in world coordinates
+#[derive(Debug, Clone, Copy, Resource)]
+pub struct SpawnZone(pub PerSide<Rect>);
+
+impl Default for SpawnZone {
+    fn default() -> Self {
+        SpawnZone(PerSide::splat(Rect::default()))
+    }
+}
+
+impl SpawnZone {
+    pub fn contains(&self, side: Side, point: Vec2) -> bool {
+        self.0[side].contains(point)
+    }
+    /// The side whose zone `point` is in, if any
+    pub fn side_at(&self, point: Vec2) -> Option<Side> {
+        Side::ALL.into_iter().find(|&side| self.contains(side, point))
+    }
+    /// The closest point to `point` inside `side`'s zone
+    pub fn clamp(&self, side: Side, point: Vec2) -> Vec2 {
+        let rect = self.0[side];
+        point.clamp(rect.min, rect.max)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct ZoneSprite(pub Side);
+
+pub fn setup_zones(mut commands: Commands) {
+    for side in Side::ALL {
+        commands.spawn(SpriteBundle {
+            transform: Transform::from_translation(Vec3::new(0., 0., -1.)),
+            ..default()
+        }).insert(ZoneSprite(side));
+    }
+}
+
+/// Keeps the zones fitted to the battlefield, as wide as the map says.
+/// Top and bottom get theirs between the other two, and only in free-for-all.
+pub fn spawn_zone_system(
+    battlefield: Res<Battlefield>,
+    config: Res<MatchConfig>,
+    maps: Res<Maps>,
+    mut zone: ResMut<SpawnZone>,
+) {
+    let zone_width = maps.0[config.map].zone_width;
+    let Vec2 { x: half_width, y: half_height } = battlefield.half_size;
+
+    let mut new = SpawnZone(PerSide {
+        left: Rect::new(-half_width, -half_height, -half_width + zone_width, half_height),
+        right: Rect::new(half_width - zone_width, -half_height, half_width, half_height),
+        top: Rect::new(-half_width + zone_width, half_height - zone_width, half_width - zone_width, half_height),
+        bottom: Rect::new(-half_width + zone_width, -half_height, half_width - zone_width, -half_height + zone_width),
+    });
+    for &side in &Side::ALL[config.sides().len()..] {
+        new.0[side] = Rect::default();
+    }
+    if new.0 != zone.0 {
+        *zone = new;
+    }
+}
+
+pub fn zone_sprite_system(
+    zone: Res<SpawnZone>,
+    options: Res<ControlOptions>,
+    materials: Res<Materials>,
+    mut query: Query<(&ZoneSprite, &mut Transform, &mut Sprite)>,
+) {
+    if !zone.is_changed() && !options.is_changed() && !materials.is_changed() {
+        return
+    }
+    for (&ZoneSprite(side), mut transform, mut sprite) in query.iter_mut() {
+        let rect = zone.0[side];
+        transform.translation = rect.center().extend(transform.translation.z);
+        sprite.custom_size = Some(rect.size());
+        sprite.color = if side == options.player_side {
+            materials.player_zone
+        } else {
+            materials.enemy_zone
+        };
+    }
+}